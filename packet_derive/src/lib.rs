@@ -1,151 +1,1153 @@
-use darling::{FromDeriveInput, FromField};
-use proc_macro2::Span;
-use quote::{format_ident, quote};
-use syn::{parse_macro_input, DeriveInput};
-
-#[derive(Debug, FromDeriveInput)]
-#[darling(attributes(packet), supports(struct_any))]
-struct PacketReciever {
-    ident: syn::Ident,
-    generics: syn::Generics,
-
-    id: i32,
-}
-
-#[proc_macro_derive(Packet, attributes(packet))]
-pub fn packet(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
-
-    let PacketReciever {
-        ident,
-        generics,
-        id,
-    } = PacketReciever::from_derive_input(&input).unwrap();
-
-    let (r#impl, ty, r#where) = generics.split_for_impl();
-
-    proc_macro::TokenStream::from(quote! {
-        #[automatically_derived]
-        impl #r#impl Packet for #ident #ty #r#where {
-            const PACKET_ID: i32 = #id;
-        }
-    })
-}
-
-#[derive(Debug, FromField)]
-#[darling(attributes(buftype))]
-struct BufTypeFieldReceiver {
-    ident: Option<syn::Ident>,
-    ty: syn::Type,
-
-    with: Option<syn::Path>,
-
-    read_with: Option<syn::Path>,
-    write_with: Option<syn::Path>,
-}
-
-#[derive(Debug, FromDeriveInput)]
-#[darling(supports(struct_any))]
-struct BufTypeReceiver {
-    ident: syn::Ident,
-    generics: syn::Generics,
-
-    data: darling::ast::Data<(), BufTypeFieldReceiver>,
-}
-
-#[proc_macro_derive(BufType, attributes(buftype))]
-pub fn buf_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
-
-    let BufTypeReceiver {
-        ident,
-        generics,
-        data,
-    } = BufTypeReceiver::from_derive_input(&input).unwrap();
-
-    let (r#impl, ty, r#where) = generics.split_for_impl();
-    let r#struct = data.take_struct().unwrap();
-
-    let fields = r#struct.fields.into_iter().enumerate().map(|(i, field)| {
-        let field_type = field.ty;
-
-        let value_ident = field
-            .ident
-            .clone()
-            .unwrap_or_else(|| format_ident!("value{}", i));
-
-        let self_field_ident = field
-            .ident
-            .map(|ident| quote! { self.#ident })
-            .unwrap_or_else(|| {
-                let i = syn::Index::from(i);
-                quote! { self.#i }
-            });
-
-        let read_with = field.read_with.or_else(|| {
-            field.with.clone().map(|mut path| {
-                path.segments
-                    .push(syn::Ident::new("buf_read_len", Span::call_site()).into());
-
-                path
-            })
-        });
-
-        let write_with = field.write_with.or_else(|| {
-            field.with.map(|mut path| {
-                path.segments
-                    .push(syn::Ident::new("buf_write", Span::call_site()).into());
-
-                path
-            })
-        });
-
-        let read_impl = if let Some(read_with) = read_with {
-            quote! { #read_with(__buf) }
-        } else {
-            quote! { <#field_type as mcproto::types::BufType>::buf_read_len(__buf) }
-        };
-
-        let write_impl = if let Some(write_with) = write_with {
-            quote! { #write_with(&#self_field_ident, __buf); }
-        } else {
-            quote! { mcproto::types::BufType::buf_write(&#self_field_ident, __buf); }
-        };
-
-        (
-            value_ident.clone(),
-            (
-                quote! {
-                    let (#value_ident, __value_length) = #read_impl?;
-                    __length += __value_length;
-                },
-                write_impl,
-            ),
-        )
-    });
-
-    let (field_idents, field_impls): (Vec<_>, (Vec<_>, Vec<_>)) = fields.unzip();
-    let field_read_impls = field_impls.0;
-    let field_write_impls = field_impls.1;
-
-    let struct_create_impl = match r#struct.style {
-        darling::ast::Style::Struct => quote! { Self { #(#field_idents),* } },
-        darling::ast::Style::Tuple => quote! { Self( #(#field_idents),* ) },
-        darling::ast::Style::Unit => quote! { Self },
-    };
-
-    proc_macro::TokenStream::from(quote! {
-        #[automatically_derived]
-        impl #r#impl BufType for #ident #ty #r#where {
-            fn buf_read_len<B: ::bytes::Buf>(__buf: &mut B) -> Result<(Self, usize), mcproto::types::ReadError> {
-                let mut __length = 0;
-                #(#field_read_impls)*
-                Ok((#struct_create_impl, __length))
-            }
-
-            fn buf_write<B: ::bytes::BufMut>(&self, __buf: &mut B) {
-                #(#field_write_impls)*
-            }
-        }
-    })
-}
+use darling::{util::Override, FromDeriveInput, FromField, FromMeta, FromVariant};
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{parse_macro_input, DeriveInput};
+
+#[derive(Debug, FromDeriveInput)]
+#[darling(attributes(packet), supports(struct_any))]
+struct PacketReciever {
+    ident: syn::Ident,
+    generics: syn::Generics,
+
+    id: i32,
+
+    /// The rest default to what the module path says, see `::mcproto::packet::Packet`.
+    direction: Option<PacketDirection>,
+    state: Option<PacketState>,
+    since: Option<i32>,
+    until: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, FromMeta)]
+#[darling(rename_all = "lowercase")]
+enum PacketDirection {
+    C2s,
+    S2c,
+}
+
+#[derive(Debug, Clone, Copy, FromMeta)]
+#[darling(rename_all = "lowercase")]
+enum PacketState {
+    Handshaking,
+    Status,
+    Login,
+    Configuration,
+    Play,
+}
+
+#[proc_macro_derive(Packet, attributes(packet))]
+pub fn packet(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let PacketReciever {
+        ident,
+        generics,
+        id,
+        direction,
+        state,
+        since,
+        until,
+    } = match PacketReciever::from_derive_input(&input) {
+        Ok(receiver) => receiver,
+        Err(err) => return err.write_errors().into(),
+    };
+
+    let (r#impl, ty, r#where) = generics.split_for_impl();
+
+    let name = ident.to_string();
+    let direction = match direction {
+        Some(PacketDirection::C2s) => quote! { ::mcproto::packet::Direction::C2s },
+        Some(PacketDirection::S2c) => quote! { ::mcproto::packet::Direction::S2c },
+        None => quote! { ::mcproto::packet::module_path::direction(::core::module_path!()) },
+    };
+    let state = match state {
+        Some(state) => {
+            let variant = syn::Ident::new(&format!("{:?}", state), Span::call_site());
+            quote! { ::mcproto::packet::PacketState::#variant }
+        }
+        None => quote! { ::mcproto::packet::module_path::state(::core::module_path!()) },
+    };
+    let since = match since {
+        Some(since) => quote! { #since },
+        None => quote! { ::mcproto::packet::module_path::protocol_version(::core::module_path!()) },
+    };
+    let until = match until {
+        Some(until) => quote! { ::core::option::Option::Some(#until) },
+        None => quote! { ::core::option::Option::None },
+    };
+
+    // evaluate the module path defaults now, instead of wherever they're first used
+    let check_defaults = if generics.params.is_empty() {
+        quote! {
+            const _: () = {
+                let _ = <#ident as ::mcproto::packet::Packet>::DIRECTION;
+                let _ = <#ident as ::mcproto::packet::Packet>::STATE;
+                let _ = <#ident as ::mcproto::packet::Packet>::MIN_PROTOCOL_VERSION;
+            };
+        }
+    } else {
+        quote! {}
+    };
+
+    proc_macro::TokenStream::from(quote! {
+        #[automatically_derived]
+        impl #r#impl ::mcproto::packet::Packet for #ident #ty #r#where {
+            const PACKET_ID: i32 = #id;
+
+            const NAME: &'static str = #name;
+            const DIRECTION: ::mcproto::packet::Direction = #direction;
+            const STATE: ::mcproto::packet::PacketState = #state;
+
+            const MIN_PROTOCOL_VERSION: i32 = #since;
+            const MAX_PROTOCOL_VERSION: ::core::option::Option<i32> = #until;
+        }
+
+        #check_defaults
+    })
+}
+
+#[derive(Debug, FromField)]
+#[darling(attributes(buftype), and_then = Self::validate)]
+struct BufTypeFieldReceiver {
+    ident: Option<syn::Ident>,
+    ty: syn::Type,
+
+    with: Option<syn::Path>,
+
+    read_with: Option<syn::Path>,
+    write_with: Option<syn::Path>,
+
+    /// `#[buftype(if = "...")]`, only read when the expression over earlier fields is true.
+    /// The field is an `Option`.
+    ///
+    /// The value is written whenever it's `Some`, debug builds assert that matches the condition.
+    /// The earlier fields the condition uses are cloned for the check, so they must be `Clone`.
+    condition: Option<syn::Expr>,
+
+    /// Read as many values as the earlier field says. The field is a `Vec`.
+    ///
+    /// The earlier field is written as is, debug builds assert it matches the number of values.
+    count: Option<syn::Ident>,
+
+    /// Neither read nor written, filled with `default`.
+    #[darling(default)]
+    skip: bool,
+    default: Option<Override<syn::Path>>,
+
+    /// The bit a bool field is in with `#[buftype(bits = ...)]`, its position by default.
+    bit: Option<u32>,
+}
+
+impl BufTypeFieldReceiver {
+    fn validate(self) -> darling::Result<Self> {
+        let span = self
+            .ident
+            .as_ref()
+            .map_or_else(Span::call_site, |ident| ident.span());
+
+        if self.default.is_some() && !self.skip {
+            return Err(darling::Error::custom("`default` needs `skip`").with_span(&span));
+        }
+        if self.skip && (self.condition.is_some() || self.count.is_some()) {
+            return Err(
+                darling::Error::custom("`skip` can't be used with `if` or `count`")
+                    .with_span(&span),
+            );
+        }
+
+        Ok(self)
+    }
+}
+
+#[derive(Debug, FromVariant)]
+#[darling(attributes(buftype))]
+struct BufTypeVariantReceiver {
+    ident: syn::Ident,
+    discriminant: Option<syn::Expr>,
+    fields: darling::ast::Fields<BufTypeFieldReceiver>,
+
+    /// Overrides the rust discriminant, needed for bool and identifier discriminants.
+    #[darling(default, with = expr_value)]
+    value: Option<syn::Expr>,
+
+    /// Catch-all for discriminants no other variant has, the first field holds it.
+    #[darling(default)]
+    unknown: bool,
+}
+
+/// Takes `value = ...` as is, instead of parsing string literals as expressions.
+fn expr_value(meta: &syn::Meta) -> darling::Result<Option<syn::Expr>> {
+    match meta {
+        syn::Meta::NameValue(name_value) => Ok(Some(name_value.value.clone())),
+        _ => Err(darling::Error::unsupported_format("list or word").with_span(meta)),
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, FromMeta)]
+#[darling(rename_all = "lowercase")]
+enum Discriminant {
+    #[default]
+    VarInt,
+    U8,
+    I32,
+    Bool,
+    Identifier,
+}
+
+impl Discriminant {
+    fn ty(self) -> TokenStream {
+        match self {
+            Discriminant::VarInt | Discriminant::I32 => quote! { i32 },
+            Discriminant::U8 => quote! { u8 },
+            Discriminant::Bool => quote! { bool },
+            Discriminant::Identifier => quote! { ::mcproto::types::Identifier },
+        }
+    }
+
+    fn read(self) -> TokenStream {
+        match self {
+            Discriminant::VarInt => {
+                quote! { ::mcproto::types::proxy::i32_as_v32::buf_read_len(__buf) }
+            }
+            other => {
+                let ty = other.ty();
+                quote! { <#ty as ::mcproto::types::BufType>::buf_read_len(__buf) }
+            }
+        }
+    }
+
+    /// `value` is a reference to the discriminant.
+    fn write(self, value: TokenStream) -> TokenStream {
+        match self {
+            Discriminant::VarInt => {
+                quote! { ::mcproto::types::proxy::i32_as_v32::buf_write(#value, __buf); }
+            }
+            other => {
+                let ty = other.ty();
+                quote! { <#ty as ::mcproto::types::BufType>::buf_write(#value, __buf); }
+            }
+        }
+    }
+
+    /// `value` is a reference to the discriminant.
+    fn len(self, value: TokenStream) -> TokenStream {
+        match self {
+            Discriminant::VarInt => {
+                quote! { ::mcproto::types::proxy::i32_as_v32::encoded_len(#value) }
+            }
+            other => {
+                let ty = other.ty();
+                quote! { <#ty as ::mcproto::types::BufType>::encoded_len(#value) }
+            }
+        }
+    }
+
+    /// A reference to the discriminant given to a variant.
+    fn value_ref(self, value: &syn::Expr) -> TokenStream {
+        match self {
+            Discriminant::Identifier => {
+                quote! { &::mcproto::identifier!(#value) }
+            }
+            _ => quote! { &(#value) },
+        }
+    }
+
+    fn matches(self, value: &syn::Expr) -> TokenStream {
+        match self {
+            Discriminant::Identifier => {
+                quote! { ::core::cmp::PartialEq::<&str>::eq(&__discriminant, &#value) }
+            }
+            _ => quote! { ::core::cmp::PartialEq::eq(&__discriminant, &(#value)) },
+        }
+    }
+}
+
+#[derive(Debug, FromDeriveInput)]
+#[darling(attributes(buftype), supports(struct_any, enum_any))]
+struct BufTypeReceiver {
+    ident: syn::Ident,
+    generics: syn::Generics,
+
+    data: darling::ast::Data<BufTypeVariantReceiver, BufTypeFieldReceiver>,
+
+    /// How enum variants are told apart, `varint` by default.
+    #[darling(default)]
+    discriminant: Discriminant,
+
+    /// Packs the struct's bool fields into a bitmask.
+    bits: Option<Bits>,
+}
+
+#[derive(Debug, Clone, Copy, FromMeta)]
+#[darling(rename_all = "lowercase")]
+enum Bits {
+    U8,
+    I32,
+    VarInt,
+}
+
+impl Bits {
+    fn ty(self) -> TokenStream {
+        match self {
+            Bits::U8 => quote! { u8 },
+            Bits::I32 | Bits::VarInt => quote! { i32 },
+        }
+    }
+
+    fn width(self) -> u32 {
+        match self {
+            Bits::U8 => 8,
+            Bits::I32 | Bits::VarInt => 32,
+        }
+    }
+
+    fn read(self) -> TokenStream {
+        match self {
+            Bits::VarInt => quote! { ::mcproto::types::proxy::i32_as_v32::buf_read_len(__buf) },
+            other => {
+                let ty = other.ty();
+                quote! { <#ty as ::mcproto::types::BufType>::buf_read_len(__buf) }
+            }
+        }
+    }
+
+    fn write(self) -> TokenStream {
+        match self {
+            Bits::VarInt => {
+                quote! { ::mcproto::types::proxy::i32_as_v32::buf_write(&__bits, __buf); }
+            }
+            other => {
+                let ty = other.ty();
+                quote! { <#ty as ::mcproto::types::BufType>::buf_write(&__bits, __buf); }
+            }
+        }
+    }
+
+    fn len(self) -> TokenStream {
+        match self {
+            Bits::VarInt => quote! { ::mcproto::types::proxy::i32_as_v32::encoded_len(&__bits) },
+            other => {
+                let ty = other.ty();
+                quote! { <#ty as ::mcproto::types::BufType>::encoded_len(&__bits) }
+            }
+        }
+    }
+}
+
+fn field_value_ident(field: &BufTypeFieldReceiver, i: usize) -> syn::Ident {
+    field
+        .ident
+        .clone()
+        .unwrap_or_else(|| format_ident!("value{}", i))
+}
+
+fn field_read_impl(field: &BufTypeFieldReceiver, value_ident: &syn::Ident) -> TokenStream {
+    let field_type = &field.ty;
+
+    if field.skip {
+        let default = match &field.default {
+            Some(Override::Explicit(default)) => quote! { #default() },
+            _ => quote! { ::core::default::Default::default() },
+        };
+
+        return quote! { let #value_ident = #default; };
+    }
+
+    let read_with = field.read_with.clone().or_else(|| {
+        field.with.clone().map(|mut path| {
+            path.segments
+                .push(syn::Ident::new("buf_read_len", Span::call_site()).into());
+
+            path
+        })
+    });
+
+    let mut read_impl = if let Some(read_with) = read_with {
+        quote! { #read_with(__buf) }
+    } else if field.condition.is_some() || field.count.is_some() {
+        // the field's type is the Option or Vec, let the value's be inferred
+        quote! { ::mcproto::types::BufType::buf_read_len(__buf) }
+    } else {
+        quote! { <#field_type as ::mcproto::types::BufType>::buf_read_len(__buf) }
+    };
+
+    if let Some(count) = &field.count {
+        read_impl = quote! {
+            {
+                let mut __values = ::std::vec::Vec::new();
+                let mut __values_length = 0;
+                for _ in 0..#count {
+                    let (__value, __value_length) = #read_impl?;
+                    __values.push(__value);
+                    __values_length += __value_length;
+                }
+                ::core::result::Result::<_, ::mcproto::types::ReadError>::Ok((__values, __values_length))
+            }
+        };
+    }
+
+    if let Some(condition) = &field.condition {
+        read_impl = quote! {
+            if #condition {
+                (#read_impl).map(|(__value, __value_length)| (::core::option::Option::Some(__value), __value_length))
+            } else {
+                ::core::result::Result::Ok((::core::option::Option::None, 0))
+            }
+        };
+    }
+
+    quote! {
+        let (#value_ident, __value_length) = #read_impl?;
+        __length += __value_length;
+    }
+}
+
+/// The fields out of `fields` that `condition` uses, method calls and field accesses aside.
+fn condition_fields<'a>(condition: &syn::Expr, fields: &'a [syn::Ident]) -> Vec<&'a syn::Ident> {
+    fn collect(tokens: TokenStream, used: &mut Vec<syn::Ident>) {
+        let mut after_dot = false;
+        for token in tokens {
+            match &token {
+                TokenTree::Ident(ident) if !after_dot => used.push(ident.clone()),
+                TokenTree::Group(group) => collect(group.stream(), used),
+                _ => {}
+            }
+            after_dot = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '.');
+        }
+    }
+
+    let mut used = Vec::new();
+    collect(condition.to_token_stream(), &mut used);
+
+    fields.iter().filter(|field| used.contains(field)).collect()
+}
+
+/// `value` is a reference to the field, `field_ref` gives one to another field of the same struct
+/// or variant, and `earlier_fields` are the ones before it.
+fn field_write_impl(
+    field: &BufTypeFieldReceiver,
+    value: TokenStream,
+    field_ref: &dyn Fn(&syn::Ident) -> TokenStream,
+    earlier_fields: &[syn::Ident],
+) -> TokenStream {
+    if field.skip {
+        return quote! {};
+    }
+
+    let write_with = field.write_with.clone().or_else(|| {
+        field.with.clone().map(|mut path| {
+            path.segments
+                .push(syn::Ident::new("buf_write", Span::call_site()).into());
+
+            path
+        })
+    });
+
+    let element = if field.condition.is_some() || field.count.is_some() {
+        quote! { __value }
+    } else {
+        value.clone()
+    };
+
+    let mut write_impl = if let Some(write_with) = write_with {
+        quote! { #write_with(#element, __buf); }
+    } else {
+        quote! { ::mcproto::types::BufType::buf_write(#element, __buf); }
+    };
+
+    // the count field is written as is, it has to match
+    if let Some(count) = &field.count {
+        let values = if field.condition.is_some() {
+            quote! { __value }
+        } else {
+            value.clone()
+        };
+        let count_ref = field_ref(count);
+        let message = format!("`{}` doesn't match the number of values written", count);
+
+        write_impl = quote! {
+            // counted like reading does, so negative counts are none
+            ::core::debug_assert_eq!(
+                ::core::iter::Iterator::count(0..*#count_ref),
+                ::core::iter::Iterator::count(::core::iter::IntoIterator::into_iter(#values)),
+                #message,
+            );
+            for __value in #values {
+                #write_impl
+            }
+        };
+    }
+
+    // written whenever it's there, the condition is only for reading so it has to agree
+    if let Some(condition) = &field.condition {
+        let condition_fields = condition_fields(condition, earlier_fields);
+        let condition_refs = condition_fields.iter().map(|ident| field_ref(ident));
+        let name = field
+            .ident
+            .as_ref()
+            .map_or_else(|| "the field".to_owned(), |ident| format!("`{}`", ident));
+        let message = format!(
+            "whether {} is `Some` doesn't match its `if` condition",
+            name
+        );
+
+        write_impl = quote! {
+            // like `debug_assert!`, checked in debug builds only but always compiled
+            if ::core::cfg!(debug_assertions) {
+                #(let #condition_fields = ::core::clone::Clone::clone(#condition_refs);)*
+                ::core::assert_eq!(
+                    #condition,
+                    ::core::option::Option::is_some(#value),
+                    #message,
+                );
+            }
+            if let ::core::option::Option::Some(__value) = #value {
+                #write_impl
+            }
+        };
+    }
+
+    write_impl
+}
+
+/// `value` is a reference to the field, evaluates to the number of bytes it's written as.
+fn field_len_impl(field: &BufTypeFieldReceiver, value: TokenStream) -> TokenStream {
+    if field.skip {
+        return quote! { 0 };
+    }
+
+    let element = if field.condition.is_some() || field.count.is_some() {
+        quote! { __value }
+    } else {
+        value.clone()
+    };
+
+    let mut len_impl = if let Some(with) = &field.with {
+        let mut write_with = with.clone();
+        write_with
+            .segments
+            .push(syn::Ident::new("buf_write", Span::call_site()).into());
+
+        // the module's `encoded_len` shadows the written and measured fallback, so modules
+        // written before `encoded_len` existed still work
+        quote! {
+            {
+                fn __measure<T: ?::core::marker::Sized, F: ::core::ops::Fn(&T) -> usize>(_: &T, measure: F) -> F {
+                    measure
+                }
+
+                #[allow(unused_variables)]
+                let encoded_len = __measure(#element, |__value| {
+                    let mut __vec = ::std::vec::Vec::new();
+                    #write_with(__value, &mut __vec);
+                    __vec.len()
+                });
+                {
+                    #[allow(unused_imports)]
+                    use #with::*;
+                    encoded_len(#element)
+                }
+            }
+        }
+    } else if let Some(write_with) = &field.write_with {
+        // nothing to ask for the length, so it has to be written
+        quote! {
+            {
+                let mut __vec = ::std::vec::Vec::new();
+                #write_with(#element, &mut __vec);
+                __vec.len()
+            }
+        }
+    } else {
+        quote! { ::mcproto::types::BufType::encoded_len(#element) }
+    };
+
+    if field.count.is_some() {
+        let values = if field.condition.is_some() {
+            quote! { __value }
+        } else {
+            value.clone()
+        };
+
+        len_impl = quote! {
+            ::core::iter::Iterator::sum::<usize>(
+                ::core::iter::Iterator::map(::core::iter::IntoIterator::into_iter(#values), |__value| #len_impl)
+            )
+        };
+    }
+
+    if field.condition.is_some() {
+        len_impl = quote! {
+            match #value {
+                ::core::option::Option::Some(__value) => #len_impl,
+                ::core::option::Option::None => 0,
+            }
+        };
+    }
+
+    len_impl
+}
+
+fn create_impl(
+    path: TokenStream,
+    style: darling::ast::Style,
+    value_idents: &[syn::Ident],
+) -> TokenStream {
+    match style {
+        darling::ast::Style::Struct => quote! { #path { #(#value_idents),* } },
+        darling::ast::Style::Tuple => quote! { #path( #(#value_idents),* ) },
+        darling::ast::Style::Unit => quote! { #path },
+    }
+}
+
+/// `if` is a keyword so syn won't parse it as a meta path, `if = ...` is renamed to `condition = ...`.
+fn rename_if_keys(input: &mut DeriveInput) {
+    let fields: Vec<&mut syn::Field> = match &mut input.data {
+        syn::Data::Struct(data) => data.fields.iter_mut().collect(),
+        syn::Data::Enum(data) => data
+            .variants
+            .iter_mut()
+            .flat_map(|variant| variant.fields.iter_mut())
+            .collect(),
+        syn::Data::Union(_) => return,
+    };
+
+    for field in fields {
+        for attr in &mut field.attrs {
+            let syn::Meta::List(list) = &mut attr.meta else {
+                continue;
+            };
+            if !list.path.is_ident("buftype") {
+                continue;
+            }
+
+            let mut tokens: Vec<_> = list.tokens.clone().into_iter().collect();
+            for i in 0..tokens.len() {
+                let is_if_key = matches!(&tokens[i], TokenTree::Ident(ident) if ident == "if")
+                    && matches!(tokens.get(i + 1), Some(TokenTree::Punct(punct)) if punct.as_char() == '=');
+                if is_if_key {
+                    tokens[i] = TokenTree::Ident(syn::Ident::new("condition", tokens[i].span()));
+                }
+            }
+            list.tokens = tokens.into_iter().collect();
+        }
+    }
+}
+
+#[proc_macro_derive(BufType, attributes(buftype))]
+pub fn buf_type(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    if let syn::Data::Union(data) = &input.data {
+        return syn::Error::new_spanned(data.union_token, "unions aren't supported")
+            .into_compile_error()
+            .into();
+    }
+    rename_if_keys(&mut input);
+
+    let BufTypeReceiver {
+        ident,
+        generics,
+        data,
+        discriminant,
+        bits,
+    } = match BufTypeReceiver::from_derive_input(&input) {
+        Ok(receiver) => receiver,
+        Err(err) => return err.write_errors().into(),
+    };
+
+    let (r#impl, ty, r#where) = generics.split_for_impl();
+
+    let (read_impl, write_impl, len_impl) = match data {
+        darling::ast::Data::Struct(r#struct) => match bits {
+            Some(bits) => match buf_type_bits(bits, r#struct) {
+                Ok(impls) => impls,
+                Err(err) => return err.write_errors().into(),
+            },
+            None => buf_type_struct(r#struct),
+        },
+        darling::ast::Data::Enum(_) if bits.is_some() => {
+            return darling::Error::custom("`bits` is only supported for structs")
+                .with_span(&ident)
+                .write_errors()
+                .into();
+        }
+        darling::ast::Data::Enum(variants) => match buf_type_enum(&ident, discriminant, variants) {
+            Ok(impls) => impls,
+            Err(err) => return err.write_errors().into(),
+        },
+    };
+
+    proc_macro::TokenStream::from(quote! {
+        #[automatically_derived]
+        impl #r#impl ::mcproto::types::BufType for #ident #ty #r#where {
+            fn buf_read_len<B: ::mcproto::bytes::Buf>(__buf: &mut B) -> ::core::result::Result<(Self, usize), ::mcproto::types::ReadError> {
+                #read_impl
+            }
+
+            fn buf_write<B: ::mcproto::bytes::BufMut>(&self, __buf: &mut B) {
+                #write_impl
+            }
+
+            fn encoded_len(&self) -> usize {
+                #len_impl
+            }
+        }
+    })
+}
+
+fn buf_type_struct(
+    r#struct: darling::ast::Fields<BufTypeFieldReceiver>,
+) -> (TokenStream, TokenStream, TokenStream) {
+    let mut value_idents = Vec::new();
+    let mut field_read_impls = Vec::new();
+    let mut field_write_impls = Vec::new();
+    let mut field_len_impls = Vec::new();
+
+    let self_field = |i: usize| match &r#struct.fields[i].ident {
+        Some(ident) => quote! { &self.#ident },
+        None => {
+            let i = syn::Index::from(i);
+            quote! { &self.#i }
+        }
+    };
+    // tuple fields are read into `value0`, `value1`, ...
+    let field_ref = |ident: &syn::Ident| {
+        (0..r#struct.fields.len())
+            .find(|i| field_value_ident(&r#struct.fields[*i], *i) == *ident)
+            .map_or_else(|| quote! { &self.#ident }, self_field)
+    };
+
+    for (i, field) in r#struct.fields.iter().enumerate() {
+        let value_ident = field_value_ident(field, i);
+
+        field_read_impls.push(field_read_impl(field, &value_ident));
+        field_write_impls.push(field_write_impl(
+            field,
+            self_field(i),
+            &field_ref,
+            &value_idents,
+        ));
+        field_len_impls.push(field_len_impl(field, self_field(i)));
+        value_idents.push(value_ident);
+    }
+
+    let struct_create_impl = create_impl(quote! { Self }, r#struct.style, &value_idents);
+
+    (
+        quote! {
+            let mut __length = 0;
+            #(#field_read_impls)*
+            ::core::result::Result::Ok((#struct_create_impl, __length))
+        },
+        quote! { #(#field_write_impls)* },
+        quote! {
+            #[allow(unused_mut)]
+            let mut __length = 0;
+            #(__length += #field_len_impls;)*
+            __length
+        },
+    )
+}
+
+fn buf_type_bits(
+    bits: Bits,
+    r#struct: darling::ast::Fields<BufTypeFieldReceiver>,
+) -> darling::Result<(TokenStream, TokenStream, TokenStream)> {
+    let mut errors = darling::Error::accumulator();
+
+    let bits_type = bits.ty();
+    let mut value_idents = Vec::new();
+    let mut field_read_impls = Vec::new();
+    let mut field_write_impls = Vec::new();
+
+    for (i, field) in r#struct.fields.iter().enumerate() {
+        let value_ident = field_value_ident(field, i);
+        let span = field
+            .ident
+            .as_ref()
+            .map_or_else(Span::call_site, |ident| ident.span());
+
+        if field.skip {
+            field_read_impls.push(field_read_impl(field, &value_ident));
+            value_idents.push(value_ident);
+            continue;
+        }
+
+        let has_other_options = field.with.is_some()
+            || field.read_with.is_some()
+            || field.write_with.is_some()
+            || field.condition.is_some()
+            || field.count.is_some();
+        if has_other_options {
+            errors.push(
+                darling::Error::custom("only `bit` and `skip` can be used with `bits`")
+                    .with_span(&span),
+            );
+            continue;
+        }
+
+        let bit = field.bit.unwrap_or(i as u32);
+        if bit >= bits.width() {
+            errors.push(
+                darling::Error::custom(format!("bit {} doesn't fit in the bitmask", bit))
+                    .with_span(&span),
+            );
+            continue;
+        }
+
+        let self_field = match &field.ident {
+            Some(ident) => quote! { self.#ident },
+            None => {
+                let i = syn::Index::from(i);
+                quote! { self.#i }
+            }
+        };
+
+        field_read_impls.push(quote! {
+            let #value_ident: bool = __bits & (1 << #bit) != 0;
+        });
+        field_write_impls.push(quote! {
+            if #self_field {
+                __bits |= 1 << #bit;
+            }
+        });
+        value_idents.push(value_ident);
+    }
+
+    errors.finish()?;
+
+    let read_bits = bits.read();
+    let write_bits = bits.write();
+    let bits_len = bits.len();
+    let struct_create_impl = create_impl(quote! { Self }, r#struct.style, &value_idents);
+
+    Ok((
+        quote! {
+            let (__bits, __length): (#bits_type, _) = #read_bits?;
+            #(#field_read_impls)*
+            ::core::result::Result::Ok((#struct_create_impl, __length))
+        },
+        quote! {
+            #[allow(unused_mut)]
+            let mut __bits: #bits_type = 0;
+            #(#field_write_impls)*
+            #write_bits
+        },
+        quote! {
+            #[allow(unused_mut)]
+            let mut __bits: #bits_type = 0;
+            #(#field_write_impls)*
+            #bits_len
+        },
+    ))
+}
+
+fn buf_type_enum(
+    ident: &syn::Ident,
+    discriminant: Discriminant,
+    variants: Vec<BufTypeVariantReceiver>,
+) -> darling::Result<(TokenStream, TokenStream, TokenStream)> {
+    let mut errors = darling::Error::accumulator();
+
+    let mut variant_read_impls = Vec::new();
+    let mut variant_write_impls = Vec::new();
+    let mut variant_len_impls = Vec::new();
+    let mut unknown_read_impl = None;
+
+    // like rust, integer discriminants count up from the previous one
+    let mut next_value: syn::Expr = syn::parse_quote! { 0 };
+
+    for variant in variants {
+        let variant_ident = &variant.ident;
+        let fields = &variant.fields.fields;
+        let value_idents: Vec<_> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| field_value_ident(field, i))
+            .collect();
+        let create = create_impl(
+            quote! { Self::#variant_ident },
+            variant.fields.style,
+            &value_idents,
+        );
+
+        if variant.unknown {
+            if matches!(discriminant, Discriminant::Bool) {
+                errors.push(
+                    darling::Error::custom("bool discriminants can't have an unknown variant")
+                        .with_span(variant_ident),
+                );
+                continue;
+            }
+            if fields.is_empty() {
+                errors.push(
+                    darling::Error::custom(
+                        "the unknown variant needs a field for the discriminant",
+                    )
+                    .with_span(variant_ident),
+                );
+                continue;
+            }
+            if unknown_read_impl.is_some() {
+                errors.push(
+                    darling::Error::custom("only one variant can be unknown")
+                        .with_span(variant_ident),
+                );
+                continue;
+            }
+
+            let discriminant_ident = &value_idents[0];
+            let field_read_impls = fields
+                .iter()
+                .zip(&value_idents)
+                .skip(1)
+                .map(|(field, value_ident)| field_read_impl(field, value_ident));
+            let discriminant_write_impl = discriminant.write(quote! { #discriminant_ident });
+            let discriminant_len_impl = discriminant.len(quote! { #discriminant_ident });
+            let field_write_impls = fields.iter().zip(&value_idents).enumerate().skip(1).map(
+                |(i, (field, value_ident))| {
+                    field_write_impl(
+                        field,
+                        quote! { #value_ident },
+                        &|ident| quote! { #ident },
+                        &value_idents[..i],
+                    )
+                },
+            );
+            let field_len_impls = fields
+                .iter()
+                .zip(&value_idents)
+                .skip(1)
+                .map(|(field, value_ident)| field_len_impl(field, quote! { #value_ident }));
+
+            unknown_read_impl = Some(quote! {
+                let #discriminant_ident = __discriminant;
+                #(#field_read_impls)*
+                ::core::result::Result::Ok((#create, __length))
+            });
+            variant_write_impls.push(quote! {
+                #create => {
+                    #discriminant_write_impl
+                    #(#field_write_impls)*
+                }
+            });
+            variant_len_impls.push(quote! {
+                #create => {
+                    #[allow(unused_mut)]
+                    let mut __length = #discriminant_len_impl;
+                    #(__length += #field_len_impls;)*
+                    __length
+                }
+            });
+
+            continue;
+        }
+
+        let value = match (variant.value.or(variant.discriminant), discriminant) {
+            (Some(value), _) => value,
+            (None, Discriminant::VarInt | Discriminant::U8 | Discriminant::I32) => next_value,
+            (None, _) => {
+                errors.push(
+                    darling::Error::custom("missing `#[buftype(value = ...)]`")
+                        .with_span(variant_ident),
+                );
+                continue;
+            }
+        };
+        next_value = syn::parse_quote! { #value + 1 };
+
+        let matches = discriminant.matches(&value);
+        let discriminant_write_impl = discriminant.write(discriminant.value_ref(&value));
+        let discriminant_len_impl = discriminant.len(discriminant.value_ref(&value));
+        let field_read_impls = fields
+            .iter()
+            .zip(&value_idents)
+            .map(|(field, value_ident)| field_read_impl(field, value_ident));
+        let field_write_impls =
+            fields
+                .iter()
+                .zip(&value_idents)
+                .enumerate()
+                .map(|(i, (field, value_ident))| {
+                    field_write_impl(
+                        field,
+                        quote! { #value_ident },
+                        &|ident| quote! { #ident },
+                        &value_idents[..i],
+                    )
+                });
+        let field_len_impls = fields
+            .iter()
+            .zip(&value_idents)
+            .map(|(field, value_ident)| field_len_impl(field, quote! { #value_ident }));
+
+        variant_read_impls.push(quote! {
+            if #matches {
+                #(#field_read_impls)*
+                return ::core::result::Result::Ok((#create, __length));
+            }
+        });
+        variant_write_impls.push(quote! {
+            #create => {
+                #discriminant_write_impl
+                #(#field_write_impls)*
+            }
+        });
+        variant_len_impls.push(quote! {
+            #create => {
+                #[allow(unused_mut)]
+                let mut __length = #discriminant_len_impl;
+                #(__length += #field_len_impls;)*
+                __length
+            }
+        });
+    }
+
+    errors.finish()?;
+
+    let read_discriminant = discriminant.read();
+    let fallback_impl = unknown_read_impl.unwrap_or_else(|| {
+        quote! {
+            ::core::result::Result::Err(::mcproto::types::ReadError::UnknownDiscriminant(
+                ::core::stringify!(#ident),
+                ::std::format!("{:?}", __discriminant),
+            ))
+        }
+    });
+
+    Ok((
+        quote! {
+            #[allow(unused_mut)]
+            let (__discriminant, mut __length) = #read_discriminant?;
+            #(#variant_read_impls)*
+            #fallback_impl
+        },
+        quote! {
+            match self {
+                #(#variant_write_impls)*
+            }
+        },
+        quote! {
+            match self {
+                #(#variant_len_impls)*
+            }
+        },
+    ))
+}
+
+/// `impl_packets_enum![PacketA, PacketB, ...]`, the `Packets` enum of a state's direction.
+#[proc_macro]
+pub fn impl_packets_enum(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let packets = parse_macro_input!(
+        input with syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated
+    );
+    let packets: Vec<_> = packets.into_iter().collect();
+
+    // ids are only known once types are, so duplicates are caught by const asserts
+    let duplicate_id_asserts = packets.iter().enumerate().flat_map(|(i, a)| {
+        packets[i + 1..].iter().map(move |b| {
+            let message = format!("`{}` and `{}` have the same PACKET_ID", a, b);
+            quote! {
+                ::core::assert!(
+                    <#a as ::mcproto::packet::Packet>::PACKET_ID != <#b as ::mcproto::packet::Packet>::PACKET_ID,
+                    #message
+                );
+            }
+        })
+    });
+
+    proc_macro::TokenStream::from(quote! {
+        #[derive(Debug)]
+        pub enum Packets {
+            #(#packets(#packets),)*
+
+            Unknown(i32, ::mcproto::bytes::Bytes)
+        }
+
+        const _: () = {
+            #(#duplicate_id_asserts)*
+        };
+
+        #[automatically_derived]
+        impl Packets {
+            /// `(id, name)` of every known packet.
+            pub const PACKETS: &'static [(i32, &'static str)] = &[
+                #((<#packets as ::mcproto::packet::Packet>::PACKET_ID, <#packets as ::mcproto::packet::Packet>::NAME),)*
+            ];
+
+            pub fn is_known(&self) -> bool {
+                !::core::matches!(self, Self::Unknown(_, _))
+            }
+
+            pub fn id(&self) -> i32 {
+                match self {
+                    #(Self::#packets(_) => <#packets as ::mcproto::packet::Packet>::PACKET_ID,)*
+                    Self::Unknown(id, _) => *id,
+                }
+            }
+
+            /// The packet's type name, `"Unknown"` for unknown packets.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(Self::#packets(_) => <#packets as ::mcproto::packet::Packet>::NAME,)*
+                    Self::Unknown(_, _) => "Unknown",
+                }
+            }
+
+            /// Writes the body, unknown packets write theirs back as is.
+            pub fn write_body<B: ::mcproto::bytes::BufMut>(&self, buf: &mut B) {
+                match self {
+                    #(Self::#packets(packet) => ::mcproto::packet::PacketWrite::write_body(packet, buf),)*
+                    Self::Unknown(_, body) => buf.put_slice(body),
+                }
+            }
+
+            /// Exact number of bytes `write_body` writes.
+            pub fn body_len(&self) -> usize {
+                match self {
+                    #(Self::#packets(packet) => ::mcproto::packet::PacketWrite::body_len(packet),)*
+                    Self::Unknown(_, body) => body.len(),
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl ::mcproto::packet::PacketFromIdBody for Packets {
+            fn from_id_body(id: i32, mut body: ::mcproto::bytes::Bytes) -> ::core::result::Result<Self, ::mcproto::types::ReadError> {
+                match id {
+                    #(<#packets as ::mcproto::packet::Packet>::PACKET_ID => <#packets as ::mcproto::packet::PacketRead>::read_body(&mut body).map(Self::#packets),)*
+
+                    other => ::core::result::Result::Ok(Self::Unknown(other, body)),
+                }
+            }
+
+            fn name_of(id: i32) -> ::core::option::Option<&'static str> {
+                Self::PACKETS
+                    .iter()
+                    .find(|(packet_id, _)| *packet_id == id)
+                    .map(|(_, name)| *name)
+            }
+        }
+
+        #(
+            #[automatically_derived]
+            impl ::core::convert::From<#packets> for Packets {
+                fn from(packet: #packets) -> Self {
+                    Packets::#packets(packet)
+                }
+            }
+
+            #[automatically_derived]
+            impl ::core::convert::TryFrom<Packets> for #packets {
+                type Error = ::mcproto::error::Error;
+
+                fn try_from(value: Packets) -> ::core::result::Result<Self, Self::Error> {
+                    match value {
+                        Packets::#packets(packet) => ::core::result::Result::Ok(packet),
+                        _ => ::core::result::Result::Err(::mcproto::error::Error::IncorectPacket),
+                    }
+                }
+            }
+        )*
+    })
+}
@@ -0,0 +1,58 @@
+use rand::{rngs::OsRng, RngCore};
+use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Encrypt, RsaPublicKey};
+
+use super::{server_hash, AuthError, EncryptionRequestPacket, EncryptionResponsePacket};
+
+/// Everything the client needs to answer an `EncryptionRequest`.
+///
+/// The flow is:
+/// 1. create this from the server's `EncryptionRequest`
+/// 2. [`super::SessionServer::join`] with [`ClientEncryption::server_hash`]
+/// 3. send [`ClientEncryption::response`]
+/// 4. enable encryption on the connection with [`ClientEncryption::shared_secret`]
+#[derive(Debug, Clone)]
+pub struct ClientEncryption {
+    pub shared_secret: [u8; 16],
+    pub server_hash: String,
+
+    pub encrypted_shared_secret: Vec<u8>,
+    pub encrypted_verify_token: Vec<u8>,
+}
+
+impl ClientEncryption {
+    /// Generates a random shared secret for the request.
+    pub fn new<R: EncryptionRequestPacket>(request: &R) -> Result<Self, AuthError> {
+        let mut shared_secret = [0; 16];
+        OsRng.fill_bytes(&mut shared_secret);
+
+        Self::with_shared_secret(request, shared_secret)
+    }
+
+    pub fn with_shared_secret<R: EncryptionRequestPacket>(
+        request: &R,
+        shared_secret: [u8; 16],
+    ) -> Result<Self, AuthError> {
+        let public_key = RsaPublicKey::from_public_key_der(request.public_key())
+            .map_err(|err| AuthError::InvalidPublicKey(err.to_string()))?;
+
+        let encrypted_shared_secret =
+            public_key.encrypt(&mut OsRng, Pkcs1v15Encrypt, &shared_secret)?;
+        let encrypted_verify_token =
+            public_key.encrypt(&mut OsRng, Pkcs1v15Encrypt, request.verify_token())?;
+
+        Ok(ClientEncryption {
+            shared_secret,
+            server_hash: server_hash(request.server_id(), &shared_secret, request.public_key()),
+            encrypted_shared_secret,
+            encrypted_verify_token,
+        })
+    }
+
+    /// The `EncryptionResponse` for whichever version is in use.
+    pub fn response<R: EncryptionResponsePacket>(&self) -> R {
+        R::new(
+            self.encrypted_shared_secret.clone(),
+            self.encrypted_verify_token.clone(),
+        )
+    }
+}
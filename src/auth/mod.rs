@@ -0,0 +1,476 @@
+//! Online-mode authentication against Mojang's session server.
+// https://wiki.vg/Protocol_Encryption
+
+use std::net::IpAddr;
+
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use uuid::Uuid;
+
+use crate::{
+    profile::GameProfile,
+    versions::{v3, v47, v759, v761, v766},
+};
+
+pub mod client;
+pub mod server;
+
+pub const DEFAULT_SESSION_SERVER: &str = "https://sessionserver.mojang.com";
+
+pub type HttpError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("invalid public key: {0}")]
+    InvalidPublicKey(String),
+
+    #[error("rsa error: {0}")]
+    Rsa(#[from] rsa::Error),
+
+    #[error("http error: {0}")]
+    Http(HttpError),
+
+    #[error("session server responded with {status}: {body}")]
+    SessionServer { status: u16, body: String },
+
+    #[error("invalid session server response: {0}")]
+    InvalidResponse(#[from] serde_json::Error),
+
+    #[error("verify token doesn't match")]
+    VerifyTokenMismatch,
+
+    #[error("shared secret should be 16 bytes, got {0}")]
+    InvalidSharedSecretLength(usize),
+
+    #[error("client sent a message signature instead of a verify token, this isn't supported")]
+    MessageSignatureUnsupported,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// HTTP client used to talk to the session server, bring your own.
+pub trait HttpClient {
+    fn get(&self, url: &str) -> Result<HttpResponse, HttpError>;
+
+    fn post_json(&self, url: &str, body: &str) -> Result<HttpResponse, HttpError>;
+}
+
+impl<H: HttpClient + ?Sized> HttpClient for &H {
+    fn get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+        (**self).get(url)
+    }
+
+    fn post_json(&self, url: &str, body: &str) -> Result<HttpResponse, HttpError> {
+        (**self).post_json(url, body)
+    }
+}
+
+pub struct SessionServer<H> {
+    http: H,
+    base_url: String,
+}
+
+impl<H: HttpClient> SessionServer<H> {
+    pub fn new(http: H) -> Self {
+        Self::with_base_url(http, DEFAULT_SESSION_SERVER)
+    }
+
+    pub fn with_base_url<U: Into<String>>(http: H, base_url: U) -> Self {
+        SessionServer {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+        }
+    }
+
+    /// Tells the session server the client is joining the server with `server_hash`.
+    ///
+    /// Must be done before sending `EncryptionResponse`, the server checks it with `hasJoined`.
+    pub fn join(
+        &self,
+        access_token: &str,
+        selected_profile: Uuid,
+        server_hash: &str,
+    ) -> Result<(), AuthError> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct JoinRequest<'a> {
+            access_token: &'a str,
+            selected_profile: String,
+            server_id: &'a str,
+        }
+
+        let body = serde_json::to_string(&JoinRequest {
+            access_token,
+            selected_profile: selected_profile.simple().to_string(),
+            server_id: server_hash,
+        })?;
+
+        let url = format!("{}/session/minecraft/join", self.base_url);
+        let response = self.http.post_json(&url, &body).map_err(AuthError::Http)?;
+
+        match response.status {
+            200..=299 => Ok(()),
+            status => Err(AuthError::SessionServer {
+                status,
+                body: response.body,
+            }),
+        }
+    }
+
+    /// Checks the client told the session server it joined with `server_hash`.
+    ///
+    /// Returns the player's profile, or `None` if the client hasn't joined.
+    /// Passing the client's ip makes the session server check it matches the one that joined.
+    pub fn has_joined(
+        &self,
+        username: &str,
+        server_hash: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<Option<GameProfile>, AuthError> {
+        let mut url = format!(
+            "{}/session/minecraft/hasJoined?username={}&serverId={}",
+            self.base_url,
+            percent_encode(username),
+            percent_encode(server_hash),
+        );
+
+        if let Some(client_ip) = client_ip {
+            url.push_str("&ip=");
+            url.push_str(&percent_encode(&client_ip.to_string()));
+        }
+
+        let response = self.http.get(&url).map_err(AuthError::Http)?;
+
+        match response.status {
+            200 => Ok(Some(serde_json::from_str(&response.body)?)),
+            204 => Ok(None),
+            status => Err(AuthError::SessionServer {
+                status,
+                body: response.body,
+            }),
+        }
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            other => format!("%{:02X}", other),
+        })
+        .collect()
+}
+
+/// Mojang's server hash, a sha1 digest formatted as a signed ("two's complement") hex number.
+pub fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let digest: [u8; 20] = Sha1::new()
+        .chain_update(server_id.as_bytes())
+        .chain_update(shared_secret)
+        .chain_update(public_key)
+        .finalize()
+        .into();
+
+    signed_hex_digest(digest)
+}
+
+fn signed_hex_digest(mut digest: [u8; 20]) -> String {
+    let negative = digest[0] & 0x80 != 0;
+    if negative {
+        // two's complement negation, invert then add one
+        let mut carry = true;
+        for byte in digest.iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                let (value, overflowed) = byte.overflowing_add(1);
+                *byte = value;
+                carry = overflowed;
+            }
+        }
+    }
+
+    let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+    let hex = match hex.trim_start_matches('0') {
+        "" => "0",
+        hex => hex,
+    };
+
+    if negative {
+        format!("-{}", hex)
+    } else {
+        hex.to_owned()
+    }
+}
+
+/// Common view of every version's `EncryptionRequest` layout.
+pub trait EncryptionRequestPacket: Sized {
+    fn new(server_id: String, public_key: Vec<u8>, verify_token: Vec<u8>) -> Self;
+
+    fn server_id(&self) -> &str;
+    fn public_key(&self) -> &[u8];
+    fn verify_token(&self) -> &[u8];
+}
+
+/// Common view of every version's `EncryptionResponse` layout.
+pub trait EncryptionResponsePacket: Sized {
+    fn new(shared_secret: Vec<u8>, verify_token: Vec<u8>) -> Self;
+
+    fn shared_secret(&self) -> &[u8];
+    /// `None` if the client sent a message signature instead (1.19 only).
+    fn verify_token(&self) -> Option<&[u8]>;
+}
+
+macro_rules! impl_encryption_request_packet {
+    ($packet:ty $(, $field:ident: $value:expr)*) => {
+        impl EncryptionRequestPacket for $packet {
+            fn new(server_id: String, public_key: Vec<u8>, verify_token: Vec<u8>) -> Self {
+                Self {
+                    server_id,
+                    public_key,
+                    verify_token,
+                    $($field: $value,)*
+                }
+            }
+
+            fn server_id(&self) -> &str {
+                &self.server_id
+            }
+
+            fn public_key(&self) -> &[u8] {
+                &self.public_key
+            }
+
+            fn verify_token(&self) -> &[u8] {
+                &self.verify_token
+            }
+        }
+    };
+}
+
+impl_encryption_request_packet!(v3::packets::login::s2c::EncryptionRequest);
+impl_encryption_request_packet!(v47::packets::login::s2c::EncryptionRequest);
+impl_encryption_request_packet!(v766::packets::login::s2c::EncryptionRequest, should_authenticate: true);
+
+macro_rules! impl_encryption_response_packet {
+    ($packet:ty) => {
+        impl EncryptionResponsePacket for $packet {
+            fn new(shared_secret: Vec<u8>, verify_token: Vec<u8>) -> Self {
+                Self {
+                    shared_secret,
+                    verify_token,
+                }
+            }
+
+            fn shared_secret(&self) -> &[u8] {
+                &self.shared_secret
+            }
+
+            fn verify_token(&self) -> Option<&[u8]> {
+                Some(&self.verify_token)
+            }
+        }
+    };
+}
+
+impl_encryption_response_packet!(v3::packets::login::c2s::EncryptionResponse);
+impl_encryption_response_packet!(v47::packets::login::c2s::EncryptionResponse);
+impl_encryption_response_packet!(v761::packets::login::c2s::EncryptionResponse);
+
+impl EncryptionResponsePacket for v759::packets::login::c2s::EncryptionResponse {
+    fn new(shared_secret: Vec<u8>, verify_token: Vec<u8>) -> Self {
+        use v759::packets::login::c2s::encryption_response::VerifyTokenOrMessageSignature;
+
+        Self {
+            shared_secret,
+            verify_token_or_message_signature: VerifyTokenOrMessageSignature::VerifyToken(
+                verify_token,
+            ),
+        }
+    }
+
+    fn shared_secret(&self) -> &[u8] {
+        &self.shared_secret
+    }
+
+    fn verify_token(&self) -> Option<&[u8]> {
+        use v759::packets::login::c2s::encryption_response::VerifyTokenOrMessageSignature;
+
+        match &self.verify_token_or_message_signature {
+            VerifyTokenOrMessageSignature::VerifyToken(verify_token) => Some(verify_token),
+            VerifyTokenOrMessageSignature::MessageSignature { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use rsa::RsaPrivateKey;
+
+    use super::{
+        client::ClientEncryption,
+        server::{ServerEncryption, ServerKey},
+        *,
+    };
+
+    #[test]
+    fn known_server_hashes() {
+        // https://wiki.vg/Protocol_Encryption#Sample_Code
+        assert_eq!(
+            server_hash("Notch", &[], &[]),
+            "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"
+        );
+        assert_eq!(
+            server_hash("jeb_", &[], &[]),
+            "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+        );
+        assert_eq!(
+            server_hash("simon", &[], &[]),
+            "88e16a1019277b15d58faf0541e11910eb756f6"
+        );
+    }
+
+    #[test]
+    fn signed_hex_edge_cases() {
+        assert_eq!(signed_hex_digest([0; 20]), "0");
+        assert_eq!(signed_hex_digest([0xff; 20]), "-1");
+
+        let mut min = [0; 20];
+        min[0] = 0x80;
+        assert_eq!(signed_hex_digest(min), format!("-8{}", "0".repeat(39)));
+    }
+
+    /// Session server that remembers joins, like Mojang's.
+    struct StubSessionServer {
+        profile: GameProfile,
+        access_token: &'static str,
+        joined: RefCell<Option<String>>,
+    }
+
+    impl HttpClient for StubSessionServer {
+        fn get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+            let query = url
+                .strip_prefix("http://stub/session/minecraft/hasJoined?")
+                .ok_or("unexpected url")?;
+            let param = |name: &str| {
+                query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+            };
+
+            let joined = self.joined.borrow();
+            if param("username") == Some(self.profile.name.as_str())
+                && param("serverId") == joined.as_deref()
+            {
+                Ok(HttpResponse {
+                    status: 200,
+                    body: serde_json::to_string(&self.profile)?,
+                })
+            } else {
+                Ok(HttpResponse {
+                    status: 204,
+                    body: String::new(),
+                })
+            }
+        }
+
+        fn post_json(&self, url: &str, body: &str) -> Result<HttpResponse, HttpError> {
+            assert_eq!(url, "http://stub/session/minecraft/join");
+
+            let body: serde_json::Value = serde_json::from_str(body)?;
+            if body["accessToken"] != self.access_token
+                || body["selectedProfile"] != self.profile.id.simple().to_string()
+            {
+                return Ok(HttpResponse {
+                    status: 403,
+                    body: "invalid session".to_owned(),
+                });
+            }
+
+            *self.joined.borrow_mut() = body["serverId"].as_str().map(str::to_owned);
+            Ok(HttpResponse {
+                status: 204,
+                body: String::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn join_flow() {
+        let stub = StubSessionServer {
+            profile: GameProfile {
+                id: Uuid::from_u128(0x069a79f444e94726a5befca90e38aaf5),
+                name: "Notch".to_owned(),
+                properties: Vec::new(),
+            },
+            access_token: "token",
+            joined: RefCell::new(None),
+        };
+        let session_server = SessionServer::with_base_url(&stub, "http://stub/");
+
+        // small key to keep the test fast
+        let key =
+            ServerKey::from_private_key(RsaPrivateKey::new(&mut rand::rngs::OsRng, 512).unwrap())
+                .unwrap();
+        let server = ServerEncryption::new(&key);
+        let request: v766::packets::login::s2c::EncryptionRequest = server.request();
+
+        let client = ClientEncryption::new(&request).unwrap();
+        assert!(matches!(
+            session_server.join("wrong", stub.profile.id, &client.server_hash),
+            Err(AuthError::SessionServer { status: 403, .. })
+        ));
+        assert_eq!(
+            session_server
+                .has_joined("Notch", &client.server_hash, None)
+                .unwrap(),
+            None
+        );
+
+        session_server
+            .join("token", stub.profile.id, &client.server_hash)
+            .unwrap();
+
+        let response: v761::packets::login::c2s::EncryptionResponse = client.response();
+        let shared = server.decrypt_response(&response).unwrap();
+        assert_eq!(shared.shared_secret, client.shared_secret);
+        assert_eq!(shared.server_hash, client.server_hash);
+
+        assert_eq!(
+            session_server
+                .has_joined("Notch", &shared.server_hash, None)
+                .unwrap(),
+            Some(stub.profile.clone())
+        );
+        assert_eq!(
+            session_server
+                .has_joined("jeb_", &shared.server_hash, None)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn wrong_verify_token() {
+        let key =
+            ServerKey::from_private_key(RsaPrivateKey::new(&mut rand::rngs::OsRng, 512).unwrap())
+                .unwrap();
+        let server = ServerEncryption::new(&key);
+        let mut request: v47::packets::login::s2c::EncryptionRequest = server.request();
+        request.verify_token = vec![0; 4];
+
+        let client = ClientEncryption::new(&request).unwrap();
+        let response: v47::packets::login::c2s::EncryptionResponse = client.response();
+        assert!(matches!(
+            server.decrypt_response(&response),
+            Err(AuthError::VerifyTokenMismatch)
+        ));
+    }
+}
@@ -0,0 +1,114 @@
+use std::convert::TryInto;
+
+use rand::{rngs::OsRng, RngCore};
+use rsa::{pkcs8::EncodePublicKey, Pkcs1v15Encrypt, RsaPrivateKey};
+
+use super::{server_hash, AuthError, EncryptionRequestPacket, EncryptionResponsePacket};
+
+/// Vanilla servers send an empty server id.
+pub const DEFAULT_SERVER_ID: &str = "";
+
+/// The server's RSA keypair.
+///
+/// Generating one is slow, create it once and share it between connections.
+#[derive(Debug, Clone)]
+pub struct ServerKey {
+    private_key: RsaPrivateKey,
+    public_key_der: Vec<u8>,
+}
+
+impl ServerKey {
+    /// Generates a 1024-bit keypair, like vanilla.
+    pub fn generate() -> Result<Self, AuthError> {
+        Self::from_private_key(RsaPrivateKey::new(&mut OsRng, 1024)?)
+    }
+
+    pub fn from_private_key(private_key: RsaPrivateKey) -> Result<Self, AuthError> {
+        let public_key_der = private_key
+            .to_public_key()
+            .to_public_key_der()
+            .map_err(|err| AuthError::InvalidPublicKey(err.to_string()))?
+            .into_vec();
+
+        Ok(ServerKey {
+            private_key,
+            public_key_der,
+        })
+    }
+
+    /// The public key in the DER form sent in `EncryptionRequest`.
+    pub fn public_key_der(&self) -> &[u8] {
+        &self.public_key_der
+    }
+}
+
+/// Server half of the encryption exchange for a single connection.
+#[derive(Debug, Clone)]
+pub struct ServerEncryption<'key> {
+    key: &'key ServerKey,
+    server_id: String,
+    verify_token: [u8; 4],
+}
+
+/// Result of a successful exchange, use the server hash to check `hasJoined`.
+#[derive(Debug, Clone)]
+pub struct SharedSecret {
+    pub shared_secret: [u8; 16],
+    pub server_hash: String,
+}
+
+impl<'key> ServerEncryption<'key> {
+    pub fn new(key: &'key ServerKey) -> Self {
+        Self::with_server_id(key, DEFAULT_SERVER_ID)
+    }
+
+    pub fn with_server_id<S: Into<String>>(key: &'key ServerKey, server_id: S) -> Self {
+        let mut verify_token = [0; 4];
+        OsRng.fill_bytes(&mut verify_token);
+
+        ServerEncryption {
+            key,
+            server_id: server_id.into(),
+            verify_token,
+        }
+    }
+
+    /// The `EncryptionRequest` for whichever version is in use.
+    pub fn request<R: EncryptionRequestPacket>(&self) -> R {
+        R::new(
+            self.server_id.clone(),
+            self.key.public_key_der.clone(),
+            self.verify_token.to_vec(),
+        )
+    }
+
+    /// Decrypts the client's shared secret, checking the verify token matches.
+    pub fn decrypt_response<R: EncryptionResponsePacket>(
+        &self,
+        response: &R,
+    ) -> Result<SharedSecret, AuthError> {
+        let verify_token = response
+            .verify_token()
+            .ok_or(AuthError::MessageSignatureUnsupported)?;
+        let verify_token = self
+            .key
+            .private_key
+            .decrypt(Pkcs1v15Encrypt, verify_token)?;
+        if verify_token != self.verify_token {
+            return Err(AuthError::VerifyTokenMismatch);
+        }
+
+        let shared_secret = self
+            .key
+            .private_key
+            .decrypt(Pkcs1v15Encrypt, response.shared_secret())?;
+        let shared_secret: [u8; 16] = shared_secret
+            .try_into()
+            .map_err(|secret: Vec<u8>| AuthError::InvalidSharedSecretLength(secret.len()))?;
+
+        Ok(SharedSecret {
+            shared_secret,
+            server_hash: server_hash(&self.server_id, &shared_secret, &self.key.public_key_der),
+        })
+    }
+}
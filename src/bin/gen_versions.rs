@@ -87,8 +87,8 @@ fn main() {
             .insert(0, version.0);
     }
 
-    for (proto_version, minecraft_versions) in &mut grouped_versions {
-        let last_version = minecraft_versions.remove(minecraft_versions.len() - 1);
+    for (proto_version, minecraft_versions) in &grouped_versions {
+        let (last_version, minecraft_versions) = minecraft_versions.split_last().unwrap();
 
         if minecraft_versions.is_empty() {
             println!("/// for minecraft versions: {}", last_version);
@@ -107,4 +107,14 @@ fn main() {
         "\npub use v{} as latest;",
         grouped_versions.last_entry().unwrap().key()
     );
+
+    println!("\n/// protocol versions with a module, and the minecraft versions they're for");
+    println!("pub const SUPPORTED: &[(i32, &[&str])] = &[");
+    for (proto_version, minecraft_versions) in &grouped_versions {
+        println!("    ({}, &{:?}),", proto_version, minecraft_versions);
+    }
+    println!("];");
+
+    // the tests are hand written, in `src/versions/tests.rs`
+    println!("\n#[cfg(test)]\nmod tests;");
 }
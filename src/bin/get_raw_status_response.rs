@@ -1,58 +1,30 @@
-use std::{env, error::Error, net::ToSocketAddrs, time::Instant};
+use std::{env, error::Error};
 
-use mcproto::{
-    handshake::{Handshake, NextState},
-    role,
-    stdio::{self, StdIoConnection},
-    versions::latest::{
-        packets::status::{
-            c2s::{PingRequest, StatusRequest},
-            s2c::{PingResponse, StatusResponse},
-        },
-        states,
-    },
-};
+use mcproto::{ping::DEFAULT_TIMEOUT, stdio};
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let address = env::args()
-        .nth(1)
-        .expect("address required")
-        .to_socket_addrs()
-        .expect("address is invalid")
-        .next()
-        .unwrap();
+    let address = env::args().nth(1).expect("address required");
+    let protocol_version = env::args()
+        .nth(2)
+        .map(|version| version.parse().expect("protocol version is invalid"));
 
-    let mut connection: StdIoConnection<role::Client, _> = stdio::connect_stdio_stream(address)?;
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().expect("port is invalid")),
+        None => (address.as_str(), 25565),
+    };
 
-    connection.write_packet(Handshake {
-        protocol_version: 110,
-        server_address: "localhost".into(),
-        server_port: 25565,
-        next_state: NextState::Status,
-        forge: None,
-    })?;
-
-    let mut connection = connection.next_state::<states::StatusState>();
-
-    connection.write_packet(StatusRequest)?;
-    let response: StatusResponse = connection.expect_next_packet()?;
-
-    let now = Instant::now();
-
-    connection.write_packet(PingRequest { payload: 1 })?;
-    let pong: PingResponse = connection.expect_next_packet()?;
-
-    let duration = now.elapsed().as_millis();
-
-    if pong.payload != 1 {
-        println!("! server replied with different payload")
-    }
+    let ping = stdio::ping(host, port, protocol_version, DEFAULT_TIMEOUT)?;
 
     println!(
-        "---------- status response (took {}ms) ----------",
-        duration
+        "---------- status response (took {}ms{}) ----------",
+        ping.latency.as_millis(),
+        if ping.legacy { ", legacy" } else { "" }
     );
-    println!("{}", response.response);
+    match &ping.raw_status {
+        Some(raw_status) => println!("{}", raw_status),
+        // legacy pings don't send json
+        None => println!("{}", ping.status.to_json()),
+    }
     println!("-------------------------------------------------");
 
     Ok(())
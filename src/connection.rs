@@ -1,5 +1,10 @@
 use bytes::{Buf, BufMut, Bytes, BytesMut};
-use std::{io::Write, marker::PhantomData};
+use std::{
+    io::{self, Read, Write},
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
 
 use aes::cipher::{BlockDecryptMut, BlockEncryptMut};
 use crypto_common::{generic_array::GenericArray, KeyIvInit};
@@ -26,11 +31,179 @@ where
     Packet(State::RecvPacket),
 }
 
+/// Controls when a [`WriteQueue`] should be flushed without an explicit flush.
+///
+/// The default policy never flushes automatically.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlushPolicy {
+    /// Flush once at least this many bytes are queued.
+    pub max_bytes: Option<usize>,
+
+    /// Flush once the oldest queued packet has been waiting this long.
+    ///
+    /// The tokio connection also flushes on this while waiting in `next_packet`. Otherwise it's
+    /// only checked when something is queued, callers that aren't reading have to call
+    /// `flush_if_due` themselves, or wait for [`WriteQueue::flush_deadline`].
+    pub max_delay: Option<Duration>,
+}
+
+/// Encoded packets waiting to be written to a stream as a single write.
+///
+/// Packets are encoded straight into the queue's buffer, which keeps its capacity across
+/// flushes, so queueing doesn't allocate per packet.
+#[derive(Debug, Default)]
+pub struct WriteQueue {
+    buffer: BytesMut,
+    oldest: Option<Instant>,
+
+    policy: FlushPolicy,
+}
+
+impl WriteQueue {
+    pub fn new(policy: FlushPolicy) -> Self {
+        WriteQueue {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    pub fn policy(&self) -> FlushPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: FlushPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.push_with(|buffer| buffer.put_slice(data))
+    }
+
+    /// Lets `write` append to the queue's buffer, like [`Connection::send_into`].
+    pub fn push_with<T, F: FnOnce(&mut BytesMut) -> T>(&mut self, write: F) -> T {
+        let result = write(&mut self.buffer);
+
+        if !self.buffer.is_empty() {
+            self.oldest.get_or_insert_with(Instant::now);
+        }
+
+        result
+    }
+
+    /// Number of queued bytes.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Whether the flush policy's size or time threshold has been reached.
+    pub fn should_flush(&self) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        let over_size = self.policy.max_bytes.is_some_and(|max| self.len() >= max);
+        let over_time = self
+            .policy
+            .max_delay
+            .zip(self.oldest)
+            .is_some_and(|(max, oldest)| oldest.elapsed() >= max);
+
+        over_size || over_time
+    }
+
+    /// When the oldest queued packet is due to be flushed, `None` if nothing's queued or there's
+    /// no `max_delay`.
+    pub fn flush_deadline(&self) -> Option<Instant> {
+        self.policy
+            .max_delay
+            .zip(self.oldest)
+            .map(|(max, oldest)| oldest + max)
+    }
+
+    /// Empties the queue, keeping the buffer's capacity.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.oldest = None;
+    }
+}
+
+/// Default number of bytes read from a stream at a time.
+pub const DEFAULT_READ_SIZE: usize = 512;
+
+/// Recieve buffers shared between connections.
+///
+/// A connection hands its buffer back to the pool whenever it has no partial packet
+/// left, and takes one again the next time it needs to read.
+/// This keeps idle connections from each holding on to a large buffer.
+#[derive(Debug, Clone)]
+pub struct BufferPool {
+    buffers: Arc<Mutex<Vec<BytesMut>>>,
+    max_buffers: usize,
+}
+
+impl BufferPool {
+    /// Creates a pool that keeps at most `max_buffers` unused buffers around.
+    pub fn new(max_buffers: usize) -> Self {
+        BufferPool {
+            buffers: Arc::new(Mutex::new(Vec::new())),
+            max_buffers,
+        }
+    }
+
+    /// Takes a buffer from the pool, or allocates a new one if the pool is empty.
+    pub fn take(&self, capacity: usize) -> BytesMut {
+        let buffer = self.buffers().pop();
+
+        let mut buffer = buffer.unwrap_or_default();
+        buffer.reserve(capacity);
+        buffer
+    }
+
+    /// Returns a buffer to the pool, it's dropped instead if the pool is full.
+    pub fn give(&self, mut buffer: BytesMut) {
+        if buffer.capacity() == 0 {
+            return;
+        }
+
+        buffer.clear();
+
+        let mut buffers = self.buffers();
+        if buffers.len() < self.max_buffers {
+            buffers.push(buffer);
+        }
+    }
+
+    /// Number of unused buffers currently in the pool.
+    pub fn len(&self) -> usize {
+        self.buffers().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn buffers(&self) -> MutexGuard<'_, Vec<BytesMut>> {
+        // the list of buffers is valid no matter where a panic happened
+        self.buffers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
 pub struct Connection<Role: ConnectionRole, State: ProtocolState> {
     recv_buffer: BytesMut,
     ciphers: Option<(cfb8::Encryptor<aes::Aes128>, cfb8::Decryptor<aes::Aes128>)>,
     compression_threshold: Option<usize>,
 
+    read_size: usize,
+    buffer_pool: Option<BufferPool>,
+
     _role: PhantomData<Role>,
     _state: PhantomData<State>,
 }
@@ -40,12 +213,23 @@ pub fn create_new_connection<Role: ConnectionRole>() -> Connection<Role, handsha
     create_connection()
 }
 
+fn check_packet_length(length: usize) -> Result<(), packet::PacketTooLarge> {
+    if length > packet::MAX_PACKET_LENGTH {
+        return Err(packet::PacketTooLarge(length));
+    }
+
+    Ok(())
+}
+
 pub fn create_connection<Role: ConnectionRole, State: ProtocolState>() -> Connection<Role, State> {
     Connection {
         recv_buffer: BytesMut::new(),
         ciphers: None,
         compression_threshold: None,
 
+        read_size: DEFAULT_READ_SIZE,
+        buffer_pool: None,
+
         _role: std::marker::PhantomData,
         _state: std::marker::PhantomData,
     }
@@ -64,53 +248,220 @@ impl<Role: ConnectionRole, State: ProtocolState> Connection<Role, State> {
         self.compression_threshold = threshold;
     }
 
-    pub fn send<Packet: RoleStateWritePacket<Role, State>>(&mut self, packet: Packet) -> Bytes {
-        // id + packet data
-        let mut packet_data = BytesMut::new();
-        i32_as_v32::buf_write(&Packet::PACKET_ID, &mut packet_data);
-        packet.write_body(&mut packet_data);
+    pub fn read_size(&self) -> usize {
+        self.read_size
+    }
 
-        trace!(id = Packet::PACKET_ID, data = ?packet_data, "send");
+    /// Sets how many bytes are read from the stream at a time.
+    pub fn set_read_size(&mut self, size: usize) {
+        self.read_size = size.max(1);
+    }
 
-        self.send_packet_data(packet_data)
+    pub fn set_buffer_pool<T: Into<Option<BufferPool>>>(&mut self, pool: T) {
+        self.buffer_pool = pool.into();
+        self.release_recv_buffer();
     }
 
-    pub fn send_id_body(&mut self, id: i32, body: &mut Bytes) -> Bytes {
-        // id + packet data
-        let mut packet_data = BytesMut::new();
-        i32_as_v32::buf_write(&id, &mut packet_data);
-        packet_data.put(body);
+    /// Makes room for at least `size` more bytes in the recieve buffer.
+    ///
+    /// Data written to the returned buffer must be passed through [`Self::recieved`].
+    pub fn reserve_recv_buffer(&mut self, size: usize) -> &mut BytesMut {
+        if self.recv_buffer.capacity() == 0 {
+            if let Some(pool) = &self.buffer_pool {
+                self.recv_buffer = pool.take(size);
+            }
+        }
 
-        trace!(id, data = ?packet_data, "send");
+        self.recv_buffer.reserve(size);
+        &mut self.recv_buffer
+    }
 
-        self.send_packet_data(packet_data)
+    /// Finishes a read into the buffer from [`Self::reserve_recv_buffer`], `start` being its
+    /// length before the read.
+    ///
+    /// Decrypts the new data and hands the buffer back to the pool if nothing was read.
+    pub fn recieved(&mut self, start: usize) {
+        self.decrypt_recieved(start);
+        self.release_recv_buffer();
     }
 
-    fn send_packet_data(&mut self, packet_data: BytesMut) -> Bytes {
-        // compression
-        let mut compressed_data = if let Some(threshold) = self.compression_threshold {
-            let mut data = BytesMut::new();
+    /// Decrypts everything in the recieve buffer after `start`, if encryption is enabled.
+    fn decrypt_recieved(&mut self, start: usize) {
+        if let Some((_, cipher)) = &mut self.ciphers {
+            // TODO: this was copied from the old enc struct, should check if theres a better way to do this
+
+            // safe as long as `<cfb8::Decryptor as BlockSizeUser>::BlockSize == typenum::U1`
+            // which is true as of 0.8.1
+            let blocks: &mut [GenericArray<u8, crypto_common::typenum::U1>] =
+                unsafe { std::mem::transmute(&mut self.recv_buffer[start..]) };
+
+            cipher.decrypt_blocks_mut(blocks);
+        }
+    }
 
-            if packet_data.len() >= threshold {
+    pub fn recieve_data(&mut self, data: &[u8]) {
+        let start = self.reserve_recv_buffer(data.len()).len();
+        self.recv_buffer.put_slice(data);
+
+        self.decrypt_recieved(start);
+    }
+
+    /// Reads up to the read size from `reader` straight into the recieve buffer.
+    pub fn recieve_from<R: Read>(&mut self, reader: &mut R) -> io::Result<usize> {
+        let size = self.read_size;
+        let buffer = self.reserve_recv_buffer(size);
+
+        let start = buffer.len();
+        // zero the room for the read first, `Read` has to be given initialized memory
+        buffer.resize(start + size, 0);
+        let result = reader.read(&mut buffer[start..]);
+        let len = *result.as_ref().unwrap_or(&0);
+        assert!(
+            len <= size,
+            "reader reported more bytes than it was given room for"
+        );
+        buffer.truncate(start + len);
+
+        self.recieved(start);
+        result
+    }
+
+    /// Whether the recieve buffer has been handed back to the buffer pool.
+    ///
+    /// Callers can wait for the stream to become readable before reading, so idle
+    /// connections don't take a buffer out of the pool while they wait.
+    pub fn recv_buffer_released(&self) -> bool {
+        self.buffer_pool.is_some() && self.recv_buffer.capacity() == 0
+    }
+
+    fn release_recv_buffer(&mut self) {
+        if let Some(pool) = &self.buffer_pool {
+            if self.recv_buffer.is_empty() {
+                pool.give(std::mem::take(&mut self.recv_buffer));
+            }
+        }
+    }
+
+    pub fn send<Packet: RoleStateWritePacket<Role, State>>(
+        &mut self,
+        packet: Packet,
+    ) -> Result<Bytes, packet::PacketTooLarge> {
+        let mut data = BytesMut::new();
+        self.send_into(packet, &mut data)?;
+
+        Ok(data.freeze())
+    }
+
+    /// Appends the encoded packet to `buf`, so several packets can share one buffer.
+    ///
+    /// Nothing is appended if the packet is too large.
+    pub fn send_into<Packet: RoleStateWritePacket<Role, State>>(
+        &mut self,
+        packet: Packet,
+        buf: &mut BytesMut,
+    ) -> Result<(), packet::PacketTooLarge> {
+        self.frame_into(buf, packet.packet_len(), |buf| {
+            // id + packet data
+            let start = buf.len();
+            i32_as_v32::buf_write(&Packet::PACKET_ID, buf);
+            packet.write_body(buf);
+
+            trace!(
+                id = Packet::PACKET_ID,
+                name = Packet::NAME,
+                state = State::LABEL,
+                data = ?&buf[start..],
+                "send"
+            );
+        })
+    }
+
+    pub fn send_id_body(
+        &mut self,
+        id: i32,
+        body: &mut Bytes,
+    ) -> Result<Bytes, packet::PacketTooLarge> {
+        let mut data = BytesMut::new();
+        self.send_id_body_into(id, body, &mut data)?;
+
+        Ok(data.freeze())
+    }
+
+    pub fn send_id_body_into(
+        &mut self,
+        id: i32,
+        body: &mut Bytes,
+        buf: &mut BytesMut,
+    ) -> Result<(), packet::PacketTooLarge> {
+        let packet_len = i32_as_v32::encoded_len(&id) + body.remaining();
+
+        self.frame_into(buf, packet_len, |buf| {
+            // id + packet data
+            let start = buf.len();
+            i32_as_v32::buf_write(&id, buf);
+            buf.put(body);
+
+            trace!(id, state = State::LABEL, data = ?&buf[start..], "send");
+        })
+    }
+
+    /// Appends the length prefixed, compressed and encrypted packet to `buf`.
+    ///
+    /// `write` writes the `packet_len` bytes of id and body. Only packets that get compressed
+    /// go through a separate buffer, everything else is written in place.
+    ///
+    /// Packets longer than [`packet::MAX_PACKET_LENGTH`] fail before anything is appended, the
+    /// compressed length is what counts once they're compressed.
+    fn frame_into<F: FnOnce(&mut BytesMut)>(
+        &mut self,
+        buf: &mut BytesMut,
+        packet_len: usize,
+        write: F,
+    ) -> Result<(), packet::PacketTooLarge> {
+        let start = buf.len();
+
+        match self.compression_threshold {
+            Some(threshold) if packet_len >= threshold => {
+                let mut packet_data = BytesMut::with_capacity(packet_len);
+                write(&mut packet_data);
+
+                let mut data = BytesMut::new();
                 i32_as_v32::buf_write(&(packet_data.len() as _), &mut data);
 
                 let mut encoder = ZlibEncoder::new(data.writer(), Compression::default());
                 // TODO: check unwrap safety
                 encoder.write_all(&packet_data).unwrap();
-                encoder.finish().unwrap().into_inner()
-            } else {
-                i32_as_v32::buf_write(&0, &mut data);
-                data.put(packet_data);
-                data
+                let compressed_data = encoder.finish().unwrap().into_inner();
+                check_packet_length(compressed_data.len())?;
+
+                // length + data
+                buf.reserve(
+                    i32_as_v32::encoded_len(&(compressed_data.len() as _)) + compressed_data.len(),
+                );
+                i32_as_v32::buf_write(&(compressed_data.len() as _), buf);
+                buf.put(compressed_data);
             }
-        } else {
-            packet_data
-        };
-
-        // length + data
-        let mut data = BytesMut::new();
-        i32_as_v32::buf_write(&(compressed_data.len() as _), &mut data);
-        data.put(&mut compressed_data);
+            threshold => {
+                // uncompressed packets get a data length of 0 once compression is enabled
+                let data_len = packet_len + threshold.map_or(0, |_| 1);
+                check_packet_length(data_len)?;
+
+                // length + data
+                buf.reserve(i32_as_v32::encoded_len(&(data_len as _)) + data_len);
+                i32_as_v32::buf_write(&(data_len as _), buf);
+                if threshold.is_some() {
+                    i32_as_v32::buf_write(&0, buf);
+                }
+
+                let packet_start = buf.len();
+                write(buf);
+                debug_assert_eq!(
+                    buf.len() - packet_start,
+                    packet_len,
+                    "the packet's encoded length doesn't match what it wrote"
+                );
+            }
+        }
 
         // encryption
         if let Some((cipher, _)) = &mut self.ciphers {
@@ -119,14 +470,14 @@ impl<Role: ConnectionRole, State: ProtocolState> Connection<Role, State> {
             // safe as long as `<cfb8::Encryptor as BlockSizeUser>::BlockSize == typenum::U1`
             // which is true as of 0.8.1
             let blocks = unsafe {
-                &mut *(&mut data as &mut [u8] as *mut [u8]
+                &mut *(&mut buf[start..] as *mut [u8]
                     as *mut [GenericArray<u8, crypto_common::typenum::U1>])
             };
 
             cipher.encrypt_blocks_mut(blocks);
         }
 
-        data.freeze()
+        Ok(())
     }
 
     pub fn next_state<NextState: NextProtocolState<State>>(self) -> Connection<Role, NextState> {
@@ -137,6 +488,9 @@ impl<Role: ConnectionRole, State: ProtocolState> Connection<Role, State> {
             ciphers: self.ciphers,
             compression_threshold: self.compression_threshold,
 
+            read_size: self.read_size,
+            buffer_pool: self.buffer_pool,
+
             _role: PhantomData,
             _state: PhantomData,
         }
@@ -152,60 +506,319 @@ where
     State: RoleStatePackets<Role>,
     State::RecvPacket: packet::PacketFromIdBody,
 {
-    pub fn recieve_data(&mut self, data: &[u8]) {
-        self.recv_buffer.put_slice(data);
-
-        if let Some((_, cipher)) = &mut self.ciphers {
-            // TODO: this was copied from the old enc struct, should check if theres a better way to do this
-
-            // safe as long as `<cfb8::Decryptor as BlockSizeUser>::BlockSize == typenum::U1`
-            // which is true as of 0.8.1
-            let start = self.recv_buffer.len() - data.len();
-            let blocks: &mut [GenericArray<u8, crypto_common::typenum::U1>] =
-                unsafe { std::mem::transmute(&mut self.recv_buffer[start..]) };
-
-            cipher.decrypt_blocks_mut(blocks);
-        }
-    }
-
+    /// A packet that fails to decode is left in the recieve buffer, every later call returns the
+    /// same error.
     pub fn next_event(&mut self) -> Result<Event<Role, State>, ReadError> {
-        let mut recv_buffer = self.recv_buffer.clone();
+        let mut header = &self.recv_buffer[..];
 
-        let length = match i32_as_v32::buf_read(&mut recv_buffer) {
-            Ok(length) => length as usize,
+        let (length, length_length) = match i32_as_v32::buf_read_len(&mut header) {
+            Ok((length, length_length)) => (length as usize, length_length),
             Err(ReadError::ReadOutOfBounds(..)) => return Ok(Event::NeedMoreData),
             Err(other) => return Err(other),
         };
 
-        if recv_buffer.remaining() < length {
+        if header.remaining() < length {
             return Ok(Event::NeedMoreData);
         }
 
+        // only take the frame out of the recieve buffer once it's complete,
+        // the rest of the buffer stays in place for the next packet
+        let frame = self.recv_buffer.split_to(length_length + length).freeze();
+
+        let packet = match self.decode_frame(frame.slice(length_length..)) {
+            Ok(packet) => packet,
+            Err(err) => {
+                // put the frame back, so a packet that fails to decode isn't silently skipped
+                let mut recv_buffer = BytesMut::from(&frame[..]);
+                recv_buffer.unsplit(std::mem::take(&mut self.recv_buffer));
+                self.recv_buffer = recv_buffer;
+
+                return Err(err);
+            }
+        };
+        self.release_recv_buffer();
+
+        Ok(Event::Packet(packet))
+    }
+
+    fn decode_frame(&self, mut frame: Bytes) -> Result<State::RecvPacket, ReadError> {
         let mut data = if self.compression_threshold.is_some() {
-            let (data_length, data_length_length) =
-                i32_as_v32::buf_read_len(&mut recv_buffer).unwrap();
+            let data_length = i32_as_v32::buf_read(&mut frame)?;
 
-            let compressed_data = recv_buffer.split_to(length - data_length_length);
             if data_length > 0 {
                 let data = BytesMut::with_capacity(data_length as _);
                 let mut decoder = ZlibDecoder::new(data.writer());
                 // TODO: check unwrap safety
-                decoder.write_all(&compressed_data).unwrap();
-                decoder.finish().unwrap().into_inner()
+                decoder.write_all(&frame).unwrap();
+                decoder.finish().unwrap().into_inner().freeze()
             } else {
-                compressed_data
+                frame
             }
         } else {
-            recv_buffer.split_to(length)
+            frame
         };
 
-        let id = i32_as_v32::buf_read(&mut data).unwrap();
+        let id = i32_as_v32::buf_read(&mut data)?;
 
-        let body = data.freeze();
-        trace!(id, ?body, "next event");
-        let packet = State::RecvPacket::from_id_body(id, body)?;
+        trace!(
+            id,
+            name = State::RecvPacket::name_of(id).unwrap_or("unknown"),
+            state = State::LABEL,
+            body = ?data,
+            "next event"
+        );
+        State::RecvPacket::from_id_body(id, data)
+    }
+}
 
-        std::mem::swap(&mut recv_buffer, &mut self.recv_buffer);
-        Ok(Event::Packet(packet))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        handshake::{Handshake, HandshakingState, NextState, Packets},
+        role::{Client, Server},
+    };
+
+    fn handshake(server_address: &str) -> Handshake {
+        Handshake {
+            protocol_version: 767,
+            server_address: server_address.to_owned(),
+            server_port: 25565,
+            next_state: NextState::Login,
+            forge: None,
+            bungee_forwarding: None,
+        }
+    }
+
+    fn round_trip(configure: impl Fn(&mut Connection<Client, HandshakingState>)) {
+        let mut sender = create_connection::<Client, HandshakingState>();
+        configure(&mut sender);
+
+        let long_address = "a".repeat(300);
+        let mut queued = BytesMut::new();
+        sender
+            .send_into(handshake("localhost"), &mut queued)
+            .unwrap();
+        sender
+            .send_into(handshake(&long_address), &mut queued)
+            .unwrap();
+
+        let mut reciever = create_connection::<Server, HandshakingState>();
+        reciever.compression_threshold = sender.compression_threshold;
+        reciever.ciphers = sender.ciphers.as_ref().map(|_| {
+            let secret = [7; 16];
+            (
+                cfb8::Encryptor::new(&secret.into(), &secret.into()),
+                cfb8::Decryptor::new(&secret.into(), &secret.into()),
+            )
+        });
+        reciever.recieve_data(&queued);
+
+        for address in ["localhost", &long_address[..]] {
+            match reciever.next_event().unwrap() {
+                Event::Packet(Packets::Handshake(handshake)) => {
+                    assert_eq!(handshake.server_address, address)
+                }
+                _ => panic!("expected a handshake"),
+            }
+        }
+        assert!(matches!(reciever.next_event(), Ok(Event::NeedMoreData)));
+    }
+
+    #[test]
+    fn queued_packets() {
+        round_trip(|_| {});
+    }
+
+    #[test]
+    fn queued_packets_compressed() {
+        // only the long one goes over the threshold
+        round_trip(|connection| connection.set_compression_threshold(256));
+    }
+
+    #[test]
+    fn queued_packets_encrypted() {
+        round_trip(|connection| {
+            connection.set_compression_threshold(256);
+            connection.set_encryption_secret(&[7; 16]);
+        });
+    }
+
+    #[test]
+    fn send_matches_send_into() {
+        let mut connection = create_connection::<Client, HandshakingState>();
+        let mut queued = BytesMut::from(&b"queued"[..]);
+        connection
+            .send_into(handshake("localhost"), &mut queued)
+            .unwrap();
+
+        assert_eq!(
+            connection.send(handshake("localhost")).unwrap(),
+            queued.split_off(6).freeze()
+        );
+    }
+
+    #[test]
+    fn send_too_long() {
+        let mut connection = create_connection::<Client, HandshakingState>();
+        let mut queued = BytesMut::from(&b"queued"[..]);
+        // the id's byte takes it over
+        let mut body = Bytes::from(vec![0; packet::MAX_PACKET_LENGTH]);
+
+        assert!(matches!(
+            connection.send_id_body_into(0, &mut body, &mut queued),
+            Err(packet::PacketTooLarge(len)) if len == packet::MAX_PACKET_LENGTH + 1
+        ));
+        assert_eq!(queued, b"queued"[..]);
+        assert_eq!(body.len(), packet::MAX_PACKET_LENGTH);
+    }
+
+    #[test]
+    fn send_max_length() {
+        let mut connection = create_connection::<Client, HandshakingState>();
+        let mut body = Bytes::from(vec![0; packet::MAX_PACKET_LENGTH - 1]);
+        assert_eq!(
+            connection.send_id_body(0, &mut body).unwrap().len(),
+            3 + packet::MAX_PACKET_LENGTH
+        );
+
+        // compressed it's the compressed length that counts
+        connection.compression_threshold = Some(256);
+        let mut body = Bytes::from(vec![0; packet::MAX_PACKET_LENGTH * 2]);
+        assert!(connection.send_id_body(0, &mut body).unwrap().len() < packet::MAX_PACKET_LENGTH);
+    }
+
+    #[test]
+    fn recieve_from_reads_in_chunks() {
+        let mut sender = create_connection::<Client, HandshakingState>();
+        let data = sender.send(handshake("localhost")).unwrap();
+
+        let mut reciever = create_connection::<Server, HandshakingState>();
+        reciever.set_read_size(4);
+        let mut reader = &data[..];
+
+        let mut reads = 0;
+        let packet = loop {
+            match reciever.next_event().unwrap() {
+                Event::NeedMoreData => {
+                    assert!(reciever.recieve_from(&mut reader).unwrap() <= 4);
+                    reads += 1;
+                }
+                Event::Packet(packet) => break packet,
+            }
+        };
+
+        assert_eq!(reads, data.len().div_ceil(4));
+        assert!(matches!(packet, Packets::Handshake(_)));
+    }
+
+    #[test]
+    fn recieve_from_keeps_only_read_bytes() {
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::Interrupted.into())
+            }
+        }
+
+        let mut reciever = create_connection::<Server, HandshakingState>();
+        reciever.recieve_data(&[1, 2]);
+        assert!(reciever.recieve_from(&mut Failing).is_err());
+        assert_eq!(reciever.recv_buffer[..], [1, 2]);
+
+        assert_eq!(reciever.recieve_from(&mut &[3][..]).unwrap(), 1);
+        assert_eq!(reciever.recv_buffer[..], [1, 2, 3]);
+    }
+
+    #[test]
+    fn frame_kept_on_decode_error() {
+        let mut reciever = create_connection::<Server, HandshakingState>();
+        // a handshake that ends in the middle of the protocol version
+        let data = [2, 0x00, 0x80];
+        reciever.recieve_data(&data);
+
+        assert!(matches!(
+            reciever.next_event(),
+            Err(ReadError::ReadOutOfBounds(..))
+        ));
+        assert_eq!(reciever.recv_buffer[..], data);
+        assert!(reciever.next_event().is_err());
+    }
+
+    #[test]
+    fn buffer_pool_returns_buffers() {
+        let pool = BufferPool::new(1);
+        let mut sender = create_connection::<Client, HandshakingState>();
+        let data = sender.send(handshake("localhost")).unwrap();
+
+        let mut reciever = create_connection::<Server, HandshakingState>();
+        reciever.set_buffer_pool(pool.clone());
+        assert!(reciever.recv_buffer_released());
+
+        reciever.recieve_from(&mut &data[..]).unwrap();
+        assert!(pool.is_empty());
+
+        assert!(matches!(reciever.next_event(), Ok(Event::Packet(_))));
+        assert!(reciever.recv_buffer_released());
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn buffer_pool_survives_poisoning() {
+        let pool = BufferPool::new(1);
+
+        let poisoner = pool.clone();
+        std::thread::spawn(move || {
+            let _buffers = poisoner.buffers.lock().unwrap();
+            panic!("poison the pool");
+        })
+        .join()
+        .unwrap_err();
+
+        pool.give(BytesMut::with_capacity(8));
+        assert_eq!(pool.len(), 1);
+        assert!(pool.take(8).capacity() >= 8);
+    }
+
+    #[test]
+    fn write_queue_keeps_capacity() {
+        let mut queue = WriteQueue::default();
+        queue.push(&[0; 64]);
+        let capacity = queue.buffer.capacity();
+
+        queue.clear();
+        assert!(queue.is_empty() && !queue.should_flush());
+        assert_eq!(queue.buffer.capacity(), capacity);
+
+        queue.set_policy(FlushPolicy {
+            max_bytes: Some(2),
+            max_delay: None,
+        });
+        queue.push(&[1]);
+        assert!(!queue.should_flush());
+        queue.push(&[2]);
+        assert!(queue.should_flush());
+        assert_eq!(queue.as_bytes(), [1, 2]);
+    }
+
+    #[test]
+    fn write_queue_flush_deadline() {
+        let mut queue = WriteQueue::new(FlushPolicy {
+            max_bytes: None,
+            max_delay: Some(Duration::from_millis(50)),
+        });
+        assert_eq!(queue.flush_deadline(), None);
+
+        let before = Instant::now();
+        queue.push(&[1]);
+        let deadline = queue.flush_deadline().unwrap();
+        assert!(deadline >= before + Duration::from_millis(50));
+
+        // the oldest packet decides
+        queue.push(&[2]);
+        assert_eq!(queue.flush_deadline(), Some(deadline));
+
+        queue.clear();
+        assert_eq!(queue.flush_deadline(), None);
     }
 }
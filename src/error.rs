@@ -1,6 +1,9 @@
 use std::io;
 
-use crate::types::ReadError;
+use crate::{
+    auth::AuthError, packet::PacketTooLarge, ping::PingError, profile::ProfileError,
+    proxy_protocol::ProxyHeaderError, types::ReadError,
+};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -15,6 +18,24 @@ pub enum Error {
     #[error("read error: {0}")]
     ReadError(#[from] ReadError),
 
+    #[error("{0}")]
+    PacketTooLarge(#[from] PacketTooLarge),
+
+    #[error("authentication error: {0}")]
+    Auth(#[from] AuthError),
+
+    #[error("ping error: {0}")]
+    Ping(#[from] PingError),
+
+    #[error("profile error: {0}")]
+    Profile(#[from] ProfileError),
+
+    #[error("proxy header error: {0}")]
+    ProxyHeader(#[from] ProxyHeaderError),
+
+    #[error("timed out")]
+    Timeout,
+
     #[error("stream shutdown")]
     StreamShutdown,
 
@@ -0,0 +1,502 @@
+use crate::packet::prelude::*;
+
+pub const CHANNEL: &str = "FML|HS";
+
+/// Channels a forge client or server registers (via `REGISTER`) before the handshake starts.
+pub const REGISTERED_CHANNELS: [&str; 4] = ["FML|HS", "FML", "FML|MP", "FORGE"];
+
+pub const PROTOCOL_VERSION: u8 = 2;
+
+/// `REGISTER` plugin message data for [`REGISTERED_CHANNELS`].
+pub fn register_data() -> Vec<u8> {
+    REGISTERED_CHANNELS.join("\0").into_bytes()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct ModInfo {
+    pub mod_id: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct RegistryEntry {
+    pub name: String,
+    #[buftype(with = "i32_as_v32")]
+    pub id: i32,
+}
+
+// 1.8 - 1.12 layout, forge 1.7 sends a mod id map with the same discriminator instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryData {
+    pub has_more: bool,
+    pub name: String,
+    pub ids: Vec<RegistryEntry>,
+    pub substitutions: Vec<String>,
+    /// Only sent by forge 1.12.
+    pub dummied: Option<Vec<String>>,
+}
+
+impl BufType for RegistryData {
+    fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize), ReadError> {
+        let (has_more, has_more_len) = bool::buf_read_len(buf)?;
+        let (name, name_len) = String::buf_read_len(buf)?;
+        let (ids, ids_len) = length_prefix_array::buf_read_len(buf)?;
+        let (substitutions, substitutions_len) = length_prefix_array::buf_read_len(buf)?;
+
+        let (dummied, dummied_len) = if buf.has_remaining() {
+            let (dummied, dummied_len) = length_prefix_array::buf_read_len(buf)?;
+            (Some(dummied), dummied_len)
+        } else {
+            (None, 0)
+        };
+
+        let registry_data = RegistryData {
+            has_more,
+            name,
+            ids,
+            substitutions,
+            dummied,
+        };
+
+        Ok((
+            registry_data,
+            has_more_len + name_len + ids_len + substitutions_len + dummied_len,
+        ))
+    }
+
+    fn buf_write<B: BufMut>(&self, buf: &mut B) {
+        self.has_more.buf_write(buf);
+        self.name.buf_write(buf);
+        length_prefix_array::buf_write(&self.ids, buf);
+        length_prefix_array::buf_write(&self.substitutions, buf);
+
+        if let Some(dummied) = &self.dummied {
+            length_prefix_array::buf_write(dummied, buf);
+        }
+    }
+}
+
+/// Phase sent in a [`Message::HandshakeAck`], the meaning depends on who sent it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AckPhase {
+    Client(ClientPhase),
+    Server(ServerPhase),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientPhase {
+    WaitingServerData = 2,
+    WaitingServerComplete = 3,
+    PendingComplete = 4,
+    Complete = 5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerPhase {
+    WaitingClientAck = 2,
+    Complete = 3,
+}
+
+impl ClientPhase {
+    fn from_u8(phase: u8) -> Option<Self> {
+        Some(match phase {
+            2 => Self::WaitingServerData,
+            3 => Self::WaitingServerComplete,
+            4 => Self::PendingComplete,
+            5 => Self::Complete,
+            _ => return None,
+        })
+    }
+}
+
+impl ServerPhase {
+    fn from_u8(phase: u8) -> Option<Self> {
+        Some(match phase {
+            2 => Self::WaitingClientAck,
+            3 => Self::Complete,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+#[buftype(discriminant = "u8")]
+pub enum Message {
+    ServerHello {
+        protocol_version: u8,
+        /// Only sent with protocol version 2 and above, where it has to be `Some`.
+        #[buftype(if = "protocol_version > 1")]
+        override_dimension: Option<i32>,
+    },
+    ClientHello {
+        protocol_version: u8,
+    },
+    ModList(#[buftype(with = "length_prefix_array")] Vec<ModInfo>),
+    RegistryData(RegistryData),
+    /// The raw phase, use [`Message::client_phase`] or [`Message::server_phase`] to interpret it.
+    #[buftype(value = 255)]
+    HandshakeAck(u8),
+    #[buftype(value = 254)]
+    HandshakeReset,
+
+    #[buftype(unknown)]
+    Unknown(u8, #[buftype(with = "remaining_bytes")] Vec<u8>),
+}
+
+impl Message {
+    pub fn client_ack(phase: ClientPhase) -> Self {
+        Self::HandshakeAck(phase as u8)
+    }
+
+    pub fn server_ack(phase: ServerPhase) -> Self {
+        Self::HandshakeAck(phase as u8)
+    }
+
+    /// Phase of an ack sent by the client.
+    pub fn client_phase(&self) -> Option<ClientPhase> {
+        match self {
+            Self::HandshakeAck(phase) => ClientPhase::from_u8(*phase),
+            _ => None,
+        }
+    }
+
+    /// Phase of an ack sent by the server.
+    pub fn server_phase(&self) -> Option<ServerPhase> {
+        match self {
+            Self::HandshakeAck(phase) => ServerPhase::from_u8(*phase),
+            _ => None,
+        }
+    }
+
+    /// Reads a message from `FML|HS` plugin message data.
+    pub fn from_data(mut data: &[u8]) -> Result<Self, ReadError> {
+        Self::buf_read(&mut data)
+    }
+
+    pub fn to_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.buf_write(&mut data);
+        data
+    }
+}
+
+/// Client half of the handshake, feed it every message the server sends on [`CHANNEL`].
+#[derive(Debug)]
+pub struct ClientHandshake {
+    mods: Vec<ModInfo>,
+
+    server_mods: Option<Vec<ModInfo>>,
+    registries: Vec<RegistryData>,
+    complete: bool,
+}
+
+impl ClientHandshake {
+    pub fn new(mods: Vec<ModInfo>) -> Self {
+        ClientHandshake {
+            mods,
+            server_mods: None,
+            registries: Vec::new(),
+            complete: false,
+        }
+    }
+
+    /// Handles a message from the server, returning the messages to reply with.
+    pub fn handle(&mut self, message: Message) -> Vec<Message> {
+        match message {
+            Message::ServerHello {
+                protocol_version, ..
+            } => vec![
+                Message::ClientHello {
+                    protocol_version: protocol_version.min(PROTOCOL_VERSION),
+                },
+                Message::ModList(self.mods.clone()),
+            ],
+            Message::ModList(mods) => {
+                self.server_mods = Some(mods);
+                vec![Message::client_ack(ClientPhase::WaitingServerData)]
+            }
+            Message::RegistryData(registry_data) => {
+                let has_more = registry_data.has_more;
+                self.registries.push(registry_data);
+
+                if has_more {
+                    vec![]
+                } else {
+                    vec![Message::client_ack(ClientPhase::WaitingServerComplete)]
+                }
+            }
+            ref ack @ Message::HandshakeAck(_) => match ack.server_phase() {
+                Some(ServerPhase::WaitingClientAck) => {
+                    vec![Message::client_ack(ClientPhase::PendingComplete)]
+                }
+                Some(ServerPhase::Complete) => {
+                    self.complete = true;
+                    vec![Message::client_ack(ClientPhase::Complete)]
+                }
+                None => vec![],
+            },
+            Message::HandshakeReset => {
+                *self = Self::new(std::mem::take(&mut self.mods));
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn server_mods(&self) -> Option<&[ModInfo]> {
+        self.server_mods.as_deref()
+    }
+
+    pub fn registries(&self) -> &[RegistryData] {
+        &self.registries
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+/// Server half of the handshake, start with [`ServerHandshake::hello`] then feed it every
+/// message the client sends on [`CHANNEL`].
+#[derive(Debug)]
+pub struct ServerHandshake {
+    mods: Vec<ModInfo>,
+    registries: Vec<RegistryData>,
+
+    client_mods: Option<Vec<ModInfo>>,
+    complete: bool,
+}
+
+impl ServerHandshake {
+    /// Without any registries a single empty one is sent, the client only moves on once it sees
+    /// the last registry.
+    pub fn new(mods: Vec<ModInfo>, registries: Vec<RegistryData>) -> Self {
+        ServerHandshake {
+            mods,
+            registries,
+            client_mods: None,
+            complete: false,
+        }
+    }
+
+    pub fn hello(&self) -> Message {
+        Message::ServerHello {
+            protocol_version: PROTOCOL_VERSION,
+            override_dimension: Some(0),
+        }
+    }
+
+    /// Handles a message from the client, returning the messages to reply with.
+    pub fn handle(&mut self, message: Message) -> Vec<Message> {
+        match message {
+            Message::ModList(mods) => {
+                self.client_mods = Some(mods);
+                vec![Message::ModList(self.mods.clone())]
+            }
+            ref ack @ Message::HandshakeAck(_) => match ack.client_phase() {
+                Some(ClientPhase::WaitingServerData) => {
+                    if self.registries.is_empty() {
+                        return vec![Message::RegistryData(RegistryData {
+                            has_more: false,
+                            name: String::new(),
+                            ids: Vec::new(),
+                            substitutions: Vec::new(),
+                            dummied: None,
+                        })];
+                    }
+
+                    let last = self.registries.len() - 1;
+
+                    self.registries
+                        .iter()
+                        .cloned()
+                        .enumerate()
+                        .map(|(i, mut registry_data)| {
+                            registry_data.has_more = i != last;
+                            Message::RegistryData(registry_data)
+                        })
+                        .collect()
+                }
+                Some(ClientPhase::WaitingServerComplete) => {
+                    vec![Message::server_ack(ServerPhase::WaitingClientAck)]
+                }
+                Some(ClientPhase::PendingComplete) => {
+                    vec![Message::server_ack(ServerPhase::Complete)]
+                }
+                Some(ClientPhase::Complete) => {
+                    self.complete = true;
+                    vec![]
+                }
+                None => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
+    pub fn client_mods(&self) -> Option<&[ModInfo]> {
+        self.client_mods.as_deref()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mods(names: &[&str]) -> Vec<ModInfo> {
+        names
+            .iter()
+            .map(|name| ModInfo {
+                mod_id: (*name).to_owned(),
+                version: "1.0".to_owned(),
+            })
+            .collect()
+    }
+
+    fn registry(name: &str) -> RegistryData {
+        RegistryData {
+            // set by the server
+            has_more: true,
+            name: name.to_owned(),
+            ids: vec![RegistryEntry {
+                name: "minecraft:stone".to_owned(),
+                id: 1,
+            }],
+            substitutions: Vec::new(),
+            dummied: Some(Vec::new()),
+        }
+    }
+
+    /// Passes messages back and forth through their wire format until neither side replies.
+    fn run(client: &mut ClientHandshake, server: &mut ServerHandshake) {
+        fn wire(message: &Message) -> Message {
+            Message::from_data(&message.to_data()).unwrap()
+        }
+
+        let mut to_client = vec![server.hello()];
+        for _ in 0..20 {
+            let to_server: Vec<_> = to_client
+                .drain(..)
+                .flat_map(|message| client.handle(wire(&message)))
+                .collect();
+            if to_server.is_empty() {
+                return;
+            }
+
+            to_client = to_server
+                .iter()
+                .flat_map(|message| server.handle(wire(message)))
+                .collect();
+        }
+
+        panic!("handshake didn't settle");
+    }
+
+    #[test]
+    fn handshake_round_trip() {
+        let mut client = ClientHandshake::new(mods(&["forge", "client_mod"]));
+        let mut server = ServerHandshake::new(
+            mods(&["forge", "server_mod"]),
+            vec![registry("minecraft:blocks"), registry("minecraft:items")],
+        );
+
+        run(&mut client, &mut server);
+
+        assert!(client.is_complete() && server.is_complete());
+        assert_eq!(
+            client.server_mods(),
+            Some(&mods(&["forge", "server_mod"])[..])
+        );
+        assert_eq!(
+            server.client_mods(),
+            Some(&mods(&["forge", "client_mod"])[..])
+        );
+
+        let registries = client.registries();
+        assert_eq!(registries.len(), 2);
+        assert!(registries[0].has_more && !registries[1].has_more);
+        assert_eq!(registries[1].ids, registry("").ids);
+    }
+
+    #[test]
+    fn handshake_without_registries() {
+        let mut client = ClientHandshake::new(mods(&["forge"]));
+        let mut server = ServerHandshake::new(mods(&["forge"]), Vec::new());
+
+        run(&mut client, &mut server);
+
+        assert!(client.is_complete() && server.is_complete());
+        assert_eq!(client.registries().len(), 1);
+    }
+
+    #[test]
+    fn reset_restarts_client() {
+        let mut client = ClientHandshake::new(mods(&["forge"]));
+        let mut server = ServerHandshake::new(mods(&["forge"]), vec![registry("a")]);
+        run(&mut client, &mut server);
+
+        assert!(client.handle(Message::HandshakeReset).is_empty());
+        assert!(!client.is_complete());
+        assert_eq!(client.server_mods(), None);
+
+        let mut server = ServerHandshake::new(mods(&["forge"]), vec![registry("a")]);
+        run(&mut client, &mut server);
+        assert!(client.is_complete());
+    }
+
+    #[test]
+    fn message_discriminators() {
+        for (message, data) in [
+            (
+                Message::ClientHello {
+                    protocol_version: 2,
+                },
+                vec![1, 2],
+            ),
+            (
+                Message::ModList(mods(&["a"])),
+                vec![2, 1, 1, b'a', 3, b'1', b'.', b'0'],
+            ),
+            (Message::client_ack(ClientPhase::Complete), vec![255, 5]),
+            (Message::HandshakeReset, vec![254]),
+            (Message::Unknown(42, vec![1, 2]), vec![42, 1, 2]),
+        ]
+        .iter()
+        {
+            assert_eq!(&message.to_data(), data);
+            assert_eq!(&Message::from_data(data).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn server_hello_versions() {
+        let hello = Message::ServerHello {
+            protocol_version: 1,
+            override_dimension: None,
+        };
+        assert_eq!(hello.to_data(), [0, 1]);
+        assert_eq!(Message::from_data(&hello.to_data()).unwrap(), hello);
+
+        let hello = Message::ServerHello {
+            protocol_version: 2,
+            override_dimension: Some(-1),
+        };
+        assert_eq!(hello.to_data(), [0, 2, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(Message::from_data(&hello.to_data()).unwrap(), hello);
+
+        // newer servers are answered with the version this speaks
+        let mut client = ClientHandshake::new(Vec::new());
+        assert_eq!(
+            client.handle(Message::ServerHello {
+                protocol_version: 3,
+                override_dimension: Some(0)
+            })[0],
+            Message::ClientHello {
+                protocol_version: PROTOCOL_VERSION
+            }
+        );
+    }
+}
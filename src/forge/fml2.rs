@@ -0,0 +1,380 @@
+use crate::{
+    packet::prelude::*,
+    versions::v393::packets::login::{c2s::LoginPluginResponse, s2c::LoginPluginRequest},
+};
+
+pub const LOGIN_WRAPPER_CHANNEL: &str = "fml:loginwrapper";
+pub const HANDSHAKE_CHANNEL: &str = "fml:handshake";
+
+/// Every forge login plugin message is wrapped with the channel it's actually meant for.
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct LoginWrapper {
+    pub channel: Identifier,
+    #[buftype(with = "length_prefix_bytes")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct ChannelInfo {
+    pub name: Identifier,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct RegistryMarker {
+    pub name: Identifier,
+    pub marker: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct ModList {
+    #[buftype(with = "length_prefix_array")]
+    pub mods: Vec<String>,
+    #[buftype(with = "length_prefix_array")]
+    pub channels: Vec<ChannelInfo>,
+    #[buftype(with = "length_prefix_array")]
+    pub registries: Vec<Identifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct ModListReply {
+    #[buftype(with = "length_prefix_array")]
+    pub mods: Vec<String>,
+    #[buftype(with = "length_prefix_array")]
+    pub channels: Vec<ChannelInfo>,
+    #[buftype(with = "length_prefix_array")]
+    pub registries: Vec<RegistryMarker>,
+}
+
+impl ModListReply {
+    /// Reply claiming to have exactly what the server has, what a matching client would send.
+    pub fn matching(mod_list: &ModList) -> Self {
+        ModListReply {
+            mods: mod_list.mods.clone(),
+            channels: mod_list.channels.clone(),
+            registries: mod_list
+                .registries
+                .iter()
+                .map(|name| RegistryMarker {
+                    name: name.clone(),
+                    marker: "1.0".to_owned(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct SnapshotId {
+    pub name: Identifier,
+    #[buftype(with = "i32_as_v32")]
+    pub id: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct SnapshotAlias {
+    pub from: Identifier,
+    pub to: Identifier,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct SnapshotOverride {
+    pub name: Identifier,
+    pub owner: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct RegistrySnapshot {
+    #[buftype(with = "length_prefix_array")]
+    pub ids: Vec<SnapshotId>,
+    #[buftype(with = "length_prefix_array")]
+    pub aliases: Vec<SnapshotAlias>,
+    #[buftype(with = "length_prefix_array")]
+    pub overrides: Vec<SnapshotOverride>,
+    #[buftype(with = "crate::forge::v32_array")]
+    pub blocked: Vec<i32>,
+    #[buftype(with = "length_prefix_array")]
+    pub dummied: Vec<Identifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct Registry {
+    pub name: Identifier,
+    pub snapshot: Option<RegistrySnapshot>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct ConfigData {
+    pub file_name: String,
+    #[buftype(with = "length_prefix_bytes")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct ModData {
+    pub mod_id: String,
+    pub display_name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// s2c
+    ModList(ModList),
+    /// c2s
+    ModListReply(ModListReply),
+    /// s2c
+    Registry(Registry),
+    /// s2c
+    ConfigData(ConfigData),
+    /// s2c, only sent by forge 1.17+.
+    ModData(Vec<ModData>),
+    /// c2s
+    Acknowledge,
+
+    Unknown(i32, Vec<u8>),
+}
+
+impl BufType for Message {
+    fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize), ReadError> {
+        let (discriminator, discriminator_len) = i32_as_v32::buf_read_len(buf)?;
+
+        let (message, message_len) = match discriminator {
+            1 => ModList::buf_read_len(buf).map(|(v, len)| (Self::ModList(v), len))?,
+            2 => ModListReply::buf_read_len(buf).map(|(v, len)| (Self::ModListReply(v), len))?,
+            3 => Registry::buf_read_len(buf).map(|(v, len)| (Self::Registry(v), len))?,
+            4 => ConfigData::buf_read_len(buf).map(|(v, len)| (Self::ConfigData(v), len))?,
+            5 => length_prefix_array::buf_read_len(buf).map(|(v, len)| (Self::ModData(v), len))?,
+            99 => (Self::Acknowledge, 0),
+            other => {
+                let (data, data_len) = remaining_bytes::buf_read_len(buf)?;
+                (Self::Unknown(other, data), data_len)
+            }
+        };
+
+        Ok((message, discriminator_len + message_len))
+    }
+
+    fn buf_write<B: BufMut>(&self, buf: &mut B) {
+        let discriminator = match self {
+            Self::ModList(_) => 1,
+            Self::ModListReply(_) => 2,
+            Self::Registry(_) => 3,
+            Self::ConfigData(_) => 4,
+            Self::ModData(_) => 5,
+            Self::Acknowledge => 99,
+            Self::Unknown(discriminator, _) => *discriminator,
+        };
+        i32_as_v32::buf_write(&discriminator, buf);
+
+        match self {
+            Self::ModList(mod_list) => mod_list.buf_write(buf),
+            Self::ModListReply(reply) => reply.buf_write(buf),
+            Self::Registry(registry) => registry.buf_write(buf),
+            Self::ConfigData(config_data) => config_data.buf_write(buf),
+            Self::ModData(mod_data) => length_prefix_array::buf_write(mod_data, buf),
+            Self::Acknowledge => {}
+            Self::Unknown(_, data) => remaining_bytes::buf_write(data, buf),
+        }
+    }
+}
+
+impl Message {
+    /// Unwraps login plugin message data, `None` if it isn't meant for `fml:handshake`.
+    pub fn from_wrapped(mut data: &[u8]) -> Result<Option<Self>, ReadError> {
+        let wrapper = LoginWrapper::buf_read(&mut data)?;
+        if wrapper.channel != HANDSHAKE_CHANNEL {
+            return Ok(None);
+        }
+
+        Self::buf_read(&mut wrapper.data.as_slice()).map(Some)
+    }
+
+    pub fn to_wrapped(&self) -> Vec<u8> {
+        let mut message = Vec::new();
+        self.buf_write(&mut message);
+
+        let wrapper = LoginWrapper {
+            channel: crate::identifier!(HANDSHAKE_CHANNEL),
+            data: message,
+        };
+
+        let mut data = Vec::new();
+        wrapper.buf_write(&mut data);
+        data
+    }
+
+    pub fn to_request(&self, message_id: i32) -> LoginPluginRequest {
+        LoginPluginRequest {
+            message_id,
+            channel: crate::identifier!(LOGIN_WRAPPER_CHANNEL),
+            data: self.to_wrapped().into(),
+        }
+    }
+
+    pub fn to_response(&self, message_id: i32) -> LoginPluginResponse {
+        LoginPluginResponse {
+            message_id,
+            successful: true,
+            data: self.to_wrapped().into(),
+        }
+    }
+
+    /// `None` if the request isn't a forge handshake message.
+    pub fn from_request(request: &LoginPluginRequest) -> Result<Option<Self>, ReadError> {
+        if request.channel != LOGIN_WRAPPER_CHANNEL {
+            return Ok(None);
+        }
+
+        Self::from_wrapped(&request.data)
+    }
+
+    /// `None` if the client didn't understand the request, or it isn't a forge handshake message.
+    pub fn from_response(response: &LoginPluginResponse) -> Result<Option<Self>, ReadError> {
+        if !response.successful {
+            return Ok(None);
+        }
+
+        Self::from_wrapped(&response.data)
+    }
+}
+
+/// Answers a forge handshake request as a client, `None` if the request isn't one.
+///
+/// The mod list is answered using `reply`, everything else is acknowledged.
+pub fn client_response<F>(
+    request: &LoginPluginRequest,
+    reply: F,
+) -> Result<Option<LoginPluginResponse>, ReadError>
+where
+    F: FnOnce(&ModList) -> ModListReply,
+{
+    let response = match Message::from_request(request)? {
+        Some(Message::ModList(mod_list)) => Message::ModListReply(reply(&mod_list)),
+        Some(_) => Message::Acknowledge,
+        None => return Ok(None),
+    };
+
+    Ok(Some(response.to_response(request.message_id)))
+}
+
+/// Requests a server sends to start the handshake, numbered from `first_message_id`.
+///
+/// Every request must be answered before the server sends `LoginSuccess`.
+pub fn server_requests(
+    first_message_id: i32,
+    mod_list: ModList,
+    registries: Vec<Registry>,
+    configs: Vec<ConfigData>,
+) -> Vec<LoginPluginRequest> {
+    std::iter::once(Message::ModList(mod_list))
+        .chain(registries.into_iter().map(Message::Registry))
+        .chain(configs.into_iter().map(Message::ConfigData))
+        .zip(first_message_id..)
+        .map(|(message, message_id)| message.to_request(message_id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mod_list() -> ModList {
+        ModList {
+            mods: vec!["forge".to_owned(), "example".to_owned()],
+            channels: vec![ChannelInfo {
+                name: crate::identifier!("forge:tier_sorting"),
+                version: "1.0".to_owned(),
+            }],
+            registries: vec![crate::identifier!("minecraft:item")],
+        }
+    }
+
+    fn registry() -> Registry {
+        Registry {
+            name: crate::identifier!("minecraft:item"),
+            snapshot: Some(RegistrySnapshot {
+                ids: vec![SnapshotId {
+                    name: crate::identifier!("minecraft:stone"),
+                    id: 1,
+                }],
+                aliases: Vec::new(),
+                overrides: Vec::new(),
+                blocked: vec![7, 300],
+                dummied: Vec::new(),
+            }),
+        }
+    }
+
+    #[test]
+    fn handshake_round_trip() {
+        let configs = vec![ConfigData {
+            file_name: "example-server.toml".to_owned(),
+            data: b"a = 1".to_vec(),
+        }];
+        let requests = server_requests(10, mod_list(), vec![registry()], configs.clone());
+        assert_eq!(requests.len(), 3);
+
+        let mut sent = Vec::new();
+        for request in &requests {
+            sent.push(Message::from_request(request).unwrap().unwrap());
+
+            let response = client_response(request, ModListReply::matching)
+                .unwrap()
+                .unwrap();
+            assert_eq!(response.message_id, request.message_id);
+
+            let reply = Message::from_response(&response).unwrap().unwrap();
+            if request.message_id == 10 {
+                assert_eq!(
+                    reply,
+                    Message::ModListReply(ModListReply::matching(&mod_list()))
+                );
+            } else {
+                assert_eq!(reply, Message::Acknowledge);
+            }
+        }
+
+        assert_eq!(
+            sent,
+            [
+                Message::ModList(mod_list()),
+                Message::Registry(registry()),
+                Message::ConfigData(configs[0].clone()),
+            ]
+        );
+    }
+
+    #[test]
+    fn other_requests_are_ignored() {
+        let mut request = Message::Acknowledge.to_request(0);
+        request.channel = crate::identifier!("velocity:player_info");
+        assert!(client_response(&request, ModListReply::matching)
+            .unwrap()
+            .is_none());
+
+        // wrapped, but for a channel other than the handshake
+        let wrapper = LoginWrapper {
+            channel: crate::identifier!("fml:play"),
+            data: vec![99],
+        };
+        let mut data = Vec::new();
+        wrapper.buf_write(&mut data);
+        let request = LoginPluginRequest {
+            message_id: 0,
+            channel: crate::identifier!(LOGIN_WRAPPER_CHANNEL),
+            data: data.into(),
+        };
+        assert!(client_response(&request, ModListReply::matching)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn unknown_messages_round_trip() {
+        let message = Message::Unknown(42, vec![1, 2, 3]);
+        let request = message.to_request(0);
+
+        assert_eq!(Message::from_request(&request).unwrap(), Some(message));
+    }
+}
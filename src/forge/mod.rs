@@ -0,0 +1,40 @@
+//! Forge's mod negotiation, see [`crate::handshake::ForgeHandshake`] for the marker in the handshake.
+// https://wiki.vg/Minecraft_Forge_Handshake
+
+/// Forge 1.7 - 1.12, negotiated over the `FML|HS` plugin channel in the play state.
+pub mod fml1;
+
+/// Forge 1.13+, negotiated with login plugin messages on the `fml:loginwrapper` channel.
+pub mod fml2;
+
+pub(crate) mod v32_array {
+    use crate::types::{proxy::i32_as_v32, ReadError};
+    use bytes::{Buf, BufMut};
+
+    pub fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Vec<i32>, usize), ReadError> {
+        let mut values = Vec::new();
+
+        let (values_count, mut length) = i32_as_v32::buf_read_len(buf)?;
+        for _ in 0..values_count {
+            let (value, value_length) = i32_as_v32::buf_read_len(buf)?;
+
+            values.push(value);
+            length += value_length;
+        }
+
+        Ok((values, length))
+    }
+
+    pub fn buf_write<B: BufMut>(values: &[i32], buf: &mut B) {
+        i32_as_v32::buf_write(&(values.len() as _), buf);
+
+        for value in values {
+            i32_as_v32::buf_write(value, buf);
+        }
+    }
+
+    pub fn encoded_len(values: &[i32]) -> usize {
+        i32_as_v32::encoded_len(&(values.len() as _))
+            + values.iter().map(i32_as_v32::encoded_len).sum::<usize>()
+    }
+}
@@ -1,3 +1,7 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
 use crate::packet::prelude::*;
 use crate::role;
 use crate::state::{self, impl_state};
@@ -21,20 +25,14 @@ pub enum ForgeHandshake {
 }
 
 impl ForgeHandshake {
-    fn separate_address(full_address: String) -> (String, Option<Self>) {
-        let (address, version) = full_address.split_once('\0').unzip();
-
-        let version = version.map(|v| match v {
+    fn from_net_id(net_id: &str) -> Self {
+        match net_id {
             "FML\0" => Self::Version1,
             "FML2\0" => Self::Version2,
             "FML3\0" => Self::Version3,
 
             other => Self::Other(other.to_owned()),
-        });
-
-        let address = address.map(ToOwned::to_owned).unwrap_or(full_address);
-
-        (address, version)
+        }
     }
 
     fn net_id(&self) -> &str {
@@ -48,6 +46,159 @@ impl ForgeHandshake {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum BungeeForwardingError {
+    #[error("forwarded address has {0} parts, expected 3 or 4")]
+    PartCount(usize),
+
+    #[error("invalid forwarded uuid: {0}")]
+    Uuid(#[from] uuid::Error),
+
+    #[error("invalid forwarded properties: {0}")]
+    Properties(#[from] serde_json::Error),
+}
+
+// https://github.com/SpigotMC/BungeeCord/blob/master/proxy/src/main/java/net/md_5/bungee/ServerConnector.java
+/// Player info forwarded by BungeeCord's legacy ip forwarding.
+///
+/// Sent in the handshake's server address as `host\0client_ip\0uuid[\0properties_json]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BungeeForwarding {
+    pub client_address: IpAddr,
+    pub uuid: Uuid,
+    pub properties: Vec<BungeeProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BungeeProperty {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl BungeeForwarding {
+    pub const BUNGEEGUARD_TOKEN_PROPERTY: &'static str = "bungeeguard-token";
+
+    // properties bungee uses to pass on the forge marker when forwarding
+    const FORGE_CLIENT_PROPERTY: &'static str = "forgeClient";
+    const FORGE_EXTRA_DATA_PROPERTY: &'static str = "extraData";
+
+    pub fn new(client_address: IpAddr, uuid: Uuid) -> Self {
+        BungeeForwarding {
+            client_address,
+            uuid,
+            properties: Vec::new(),
+        }
+    }
+
+    pub fn property(&self, name: &str) -> Option<&BungeeProperty> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+
+    /// BungeeGuard tokens, used by backends to check the handshake came from a trusted proxy.
+    pub fn bungeeguard_tokens(&self) -> impl Iterator<Item = &str> {
+        self.properties
+            .iter()
+            .filter(|property| property.name == Self::BUNGEEGUARD_TOKEN_PROPERTY)
+            .map(|property| property.value.as_str())
+    }
+
+    /// The forge marker bungee moved into the properties, if any.
+    fn forge(&self) -> Option<ForgeHandshake> {
+        let extra_data = self.property(Self::FORGE_EXTRA_DATA_PROPERTY)?;
+        let net_id = extra_data.value.replace('\u{1}', "\0");
+
+        Some(ForgeHandshake::from_net_id(
+            net_id.strip_prefix('\0').unwrap_or(&net_id),
+        ))
+    }
+
+    /// Parses the parts after the host, `None` if they aren't bungee forwarding at all.
+    fn parse(parts: &[&str]) -> Option<Result<Self, BungeeForwardingError>> {
+        // forwarding always starts with the client's ip, forge markers never do
+        let client_address = parts.first()?.parse().ok()?;
+
+        Some(Self::parse_forwarded(client_address, &parts[1..]))
+    }
+
+    fn parse_forwarded(
+        client_address: IpAddr,
+        parts: &[&str],
+    ) -> Result<Self, BungeeForwardingError> {
+        let (uuid, properties) = match parts {
+            [uuid] => (uuid, None),
+            [uuid, properties] => (uuid, Some(properties)),
+            _ => return Err(BungeeForwardingError::PartCount(parts.len() + 2)),
+        };
+
+        let properties = match properties {
+            Some(properties) => serde_json::from_str(properties)?,
+            None => Vec::new(),
+        };
+
+        Ok(BungeeForwarding {
+            client_address,
+            uuid: Uuid::try_parse(uuid)?,
+            properties,
+        })
+    }
+
+    fn write_to(&self, address: &mut String, forge: Option<&ForgeHandshake>) {
+        address.push('\0');
+        address.push_str(&self.client_address.to_string());
+        address.push('\0');
+        address.push_str(&self.uuid.simple().to_string());
+
+        let mut properties = self.properties.clone();
+        if let Some(forge) = forge {
+            if self.property(Self::FORGE_EXTRA_DATA_PROPERTY).is_none() {
+                properties.push(BungeeProperty {
+                    name: Self::FORGE_CLIENT_PROPERTY.to_owned(),
+                    value: "true".to_owned(),
+                    signature: None,
+                });
+                properties.push(BungeeProperty {
+                    name: Self::FORGE_EXTRA_DATA_PROPERTY.to_owned(),
+                    value: format!("\0{}", forge.net_id()).replace('\0', "\u{1}"),
+                    signature: Some(String::new()),
+                });
+            }
+        }
+
+        if !properties.is_empty() {
+            address.push('\0');
+            // serializing plain strings can't fail
+            address.push_str(&serde_json::to_string(&properties).unwrap());
+        }
+    }
+}
+
+/// Splits the server address into the actual address, any bungee forwarding and any forge marker.
+fn separate_address(
+    full_address: String,
+) -> Result<(String, Option<BungeeForwarding>, Option<ForgeHandshake>), BungeeForwardingError> {
+    let parts: Vec<_> = full_address.split('\0').collect();
+
+    if let Some(forwarding) = BungeeForwarding::parse(&parts[1..]) {
+        let forwarding = forwarding?;
+        let forge = forwarding.forge();
+        return Ok((parts[0].to_owned(), Some(forwarding), forge));
+    }
+
+    let (address, forge) = match full_address.split_once('\0') {
+        Some((address, net_id)) => (
+            address.to_owned(),
+            Some(ForgeHandshake::from_net_id(net_id)),
+        ),
+        None => (full_address, None),
+    };
+
+    Ok((address, None, forge))
+}
+
 #[derive(Debug)]
 pub enum NextState {
     Status,
@@ -67,7 +218,7 @@ v32_prefix_enum!(
 );
 
 #[derive(Debug, Packet)]
-#[packet(id = 0x00)]
+#[packet(id = 0x00, direction = "c2s", state = "handshaking", since = 3)]
 pub struct Handshake {
     pub protocol_version: i32,
     pub server_address: String,
@@ -75,12 +226,16 @@ pub struct Handshake {
     pub next_state: NextState,
 
     pub forge: Option<ForgeHandshake>,
+    pub bungee_forwarding: Option<BungeeForwarding>,
 }
 
 impl Handshake {
     fn modified_address(&self) -> String {
         let mut address = self.server_address.clone();
-        if let Some(forge) = &self.forge {
+        if let Some(forwarding) = &self.bungee_forwarding {
+            // bungee passes the forge marker on through the forwarded properties
+            forwarding.write_to(&mut address, self.forge.as_ref());
+        } else if let Some(forge) = &self.forge {
             address.push('\0');
             address.push_str(forge.net_id());
         }
@@ -97,7 +252,7 @@ impl PacketRead for Handshake {
         let server_port = u16::buf_read(data)?;
         let next_state = NextState::buf_read(data)?;
 
-        let (server_address, forge) = ForgeHandshake::separate_address(server_address);
+        let (server_address, bungee_forwarding, forge) = separate_address(server_address)?;
 
         Ok(Handshake {
             protocol_version,
@@ -105,6 +260,7 @@ impl PacketRead for Handshake {
             server_port,
             next_state,
             forge,
+            bungee_forwarding,
         })
     }
 }
@@ -116,6 +272,13 @@ impl PacketWrite for Handshake {
         self.server_port.buf_write(buf);
         self.next_state.buf_write(buf);
     }
+
+    fn body_len(&self) -> usize {
+        i32_as_v32::encoded_len(&self.protocol_version)
+            + self.modified_address().encoded_len()
+            + self.server_port.encoded_len()
+            + self.next_state.encoded_len()
+    }
 }
 
 impl_state!(HandshakingState("handshaking"), [], c2s[Handshake]);
@@ -123,3 +286,178 @@ impl_state!(HandshakingState("handshaking"), [], c2s[Handshake]);
 impl state::RoleStatePackets<role::Server> for HandshakingState {
     type RecvPacket = Packets;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const UUID: &str = "069a79f444e94726a5befca90e38aaf5";
+
+    fn read(server_address: &str) -> Result<Handshake, ReadError> {
+        let mut data = Vec::new();
+        i32_as_v32::buf_write(&767, &mut data);
+        server_address.to_owned().buf_write(&mut data);
+        25565u16.buf_write(&mut data);
+        NextState::Login.buf_write(&mut data);
+
+        Handshake::read_body(&mut &data[..])
+    }
+
+    fn round_trip(handshake: &Handshake) -> Handshake {
+        let mut data = Vec::new();
+        handshake.write_body(&mut data);
+        assert_eq!(data.len(), handshake.body_len());
+
+        Handshake::read_body(&mut &data[..]).unwrap()
+    }
+
+    fn new_handshake(
+        forge: Option<ForgeHandshake>,
+        bungee_forwarding: Option<BungeeForwarding>,
+    ) -> Handshake {
+        Handshake {
+            protocol_version: 767,
+            server_address: "localhost".to_owned(),
+            server_port: 25565,
+            next_state: NextState::Login,
+            forge,
+            bungee_forwarding,
+        }
+    }
+
+    fn forwarding() -> BungeeForwarding {
+        BungeeForwarding::new(
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
+            Uuid::try_parse(UUID).unwrap(),
+        )
+    }
+
+    #[test]
+    fn forwarding_without_properties() {
+        let address = format!("localhost\x00127.0.0.1\0{}", UUID);
+        let handshake = read(&address).unwrap();
+
+        assert_eq!(handshake.server_address, "localhost");
+        assert_eq!(handshake.bungee_forwarding, Some(forwarding()));
+        assert!(handshake.forge.is_none());
+        assert_eq!(handshake.modified_address(), address);
+    }
+
+    #[test]
+    fn forwarding_with_properties() {
+        let properties = r#"[{"name":"textures","value":"e30=","signature":"c2ln"}]"#;
+        let address = format!("localhost\x00127.0.0.1\0{}\0{}", UUID, properties);
+        let handshake = read(&address).unwrap();
+
+        let mut expected = forwarding();
+        expected.properties.push(BungeeProperty {
+            name: "textures".to_owned(),
+            value: "e30=".to_owned(),
+            signature: Some("c2ln".to_owned()),
+        });
+        assert_eq!(handshake.bungee_forwarding, Some(expected));
+        assert_eq!(handshake.modified_address(), address);
+    }
+
+    #[test]
+    fn forwarding_round_trip() {
+        let mut forwarding = forwarding();
+        forwarding.properties.push(BungeeProperty {
+            name: "textures".to_owned(),
+            value: "e30=".to_owned(),
+            signature: None,
+        });
+
+        let read = round_trip(&new_handshake(None, Some(forwarding.clone())));
+        assert_eq!(read.server_address, "localhost");
+        assert_eq!(read.bungee_forwarding, Some(forwarding));
+        assert!(read.forge.is_none());
+    }
+
+    #[test]
+    fn bungeeguard_tokens() {
+        let properties = r#"[{"name":"bungeeguard-token","value":"secret"}]"#;
+        let address = format!("localhost\x00127.0.0.1\0{}\0{}", UUID, properties);
+        let handshake = read(&address).unwrap();
+
+        let forwarding = handshake.bungee_forwarding.unwrap();
+        assert_eq!(
+            forwarding.bungeeguard_tokens().collect::<Vec<_>>(),
+            ["secret"]
+        );
+        assert!(forwarding
+            .property(BungeeForwarding::BUNGEEGUARD_TOKEN_PROPERTY)
+            .unwrap()
+            .signature
+            .is_none());
+    }
+
+    #[test]
+    fn forge_marker_in_forwarding() {
+        // how bungee forwards an fml2 client
+        let properties = r#"[{"name":"forgeClient","value":"true"},{"name":"extraData","value":"\u0001FML2\u0001","signature":""}]"#;
+        let address = format!("localhost\x00127.0.0.1\0{}\0{}", UUID, properties);
+        let handshake = read(&address).unwrap();
+
+        assert!(matches!(handshake.forge, Some(ForgeHandshake::Version2)));
+        assert!(handshake.bungee_forwarding.is_some());
+        assert_eq!(handshake.modified_address(), address);
+
+        let read = round_trip(&new_handshake(
+            Some(ForgeHandshake::Version1),
+            Some(forwarding()),
+        ));
+        assert!(matches!(read.forge, Some(ForgeHandshake::Version1)));
+        let extra_data = read
+            .bungee_forwarding
+            .as_ref()
+            .unwrap()
+            .property(BungeeForwarding::FORGE_EXTRA_DATA_PROPERTY)
+            .unwrap();
+        assert_eq!(extra_data.value, "\u{1}FML\u{1}");
+    }
+
+    #[test]
+    fn forge_without_forwarding() {
+        let handshake = read("localhost\0FML\0").unwrap();
+        assert_eq!(handshake.server_address, "localhost");
+        assert!(matches!(handshake.forge, Some(ForgeHandshake::Version1)));
+        assert!(handshake.bungee_forwarding.is_none());
+
+        let handshake = read("localhost\0FML2\0").unwrap();
+        assert!(matches!(handshake.forge, Some(ForgeHandshake::Version2)));
+        assert!(handshake.bungee_forwarding.is_none());
+
+        let read = round_trip(&new_handshake(Some(ForgeHandshake::Version2), None));
+        assert_eq!(read.server_address, "localhost");
+        assert!(matches!(read.forge, Some(ForgeHandshake::Version2)));
+    }
+
+    #[test]
+    fn malformed_forwarding() {
+        assert!(matches!(
+            read("localhost\x00127.0.0.1\0not-a-uuid"),
+            Err(ReadError::BungeeForwarding(BungeeForwardingError::Uuid(_)))
+        ));
+        assert!(matches!(
+            read(&format!("localhost\x00127.0.0.1\0{}\0[{{", UUID)),
+            Err(ReadError::BungeeForwarding(
+                BungeeForwardingError::Properties(_)
+            ))
+        ));
+        assert!(matches!(
+            read("localhost\x00127.0.0.1"),
+            Err(ReadError::BungeeForwarding(
+                BungeeForwardingError::PartCount(2)
+            ))
+        ));
+        assert!(matches!(
+            read(&format!("localhost\x00127.0.0.1\0{}\0[]\0FML\0", UUID)),
+            Err(ReadError::BungeeForwarding(
+                BungeeForwardingError::PartCount(6)
+            ))
+        ));
+    }
+}
@@ -1,3 +1,7 @@
+// lets the derives' `::mcproto` paths work inside this crate too
+extern crate self as mcproto;
+
+pub use bytes;
 pub use packet_derive;
 pub use uuid;
 
@@ -13,11 +17,21 @@ pub mod role {
 
 pub mod error;
 
+pub mod auth;
 pub mod connection;
+pub mod forge;
 pub mod handshake;
+pub mod offline;
 pub mod packet;
+pub mod ping;
+pub mod probe;
+pub mod profile;
+pub mod proxy_protocol;
+pub mod resolve;
 pub mod state;
+pub mod status;
 pub mod types;
+pub mod velocity;
 pub mod versions;
 
 pub mod stdio;
@@ -0,0 +1,163 @@
+//! Offline-mode player uuids and vanilla username rules.
+
+use md5::{Digest, Md5};
+use uuid::{Builder, Uuid};
+
+use crate::profile::GameProfile;
+
+pub const MIN_USERNAME_LENGTH: usize = 3;
+pub const MAX_USERNAME_LENGTH: usize = 16;
+
+/// The uuid an offline-mode server gives a player, a v3 uuid of `"OfflinePlayer:" + username`.
+///
+/// Same as java's `UUID.nameUUIDFromBytes`, so no namespace is hashed.
+pub fn offline_uuid(username: &str) -> Uuid {
+    let digest = Md5::new()
+        .chain_update(b"OfflinePlayer:")
+        .chain_update(username.as_bytes())
+        .finalize();
+
+    Builder::from_md5_bytes(digest.into()).into_uuid()
+}
+
+/// Profile an offline-mode server would send in `LoginSuccess`.
+pub fn offline_profile(username: &str) -> GameProfile {
+    GameProfile::new(offline_uuid(username), username)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UsernameError {
+    #[error("username is too short ({0} characters)")]
+    TooShort(usize),
+
+    #[error("username is too long ({0} characters)")]
+    TooLong(usize),
+
+    #[error("username contains {0:?}, only A-Z, a-z, 0-9 and _ are allowed")]
+    InvalidCharacter(char),
+}
+
+/// Checks the username is one vanilla accepts, 3 to 16 of `[A-Za-z0-9_]`.
+pub fn validate_username(username: &str) -> Result<(), UsernameError> {
+    if let Some(invalid) = username
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '_')
+    {
+        return Err(UsernameError::InvalidCharacter(invalid));
+    }
+
+    // only ascii is left, so bytes are characters
+    match username.len() {
+        len if len < MIN_USERNAME_LENGTH => Err(UsernameError::TooShort(len)),
+        len if len > MAX_USERNAME_LENGTH => Err(UsernameError::TooLong(len)),
+        _ => Ok(()),
+    }
+}
+
+pub fn is_valid_username(username: &str) -> bool {
+    validate_username(username).is_ok()
+}
+
+/// How `LoginSuccess` writes the uuid as a string, before 1.16 switched to sending it as bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UuidFormat {
+    /// `069a79f444e94726a5befca90e38aaf5`, used by 1.7.1 to 1.7.5.
+    Simple,
+    /// `069a79f4-44e9-4726-a5be-fca90e38aaf5`, used by 1.7.6 to 1.15.2.
+    Hyphenated,
+}
+
+impl UuidFormat {
+    /// `None` if the version doesn't send the uuid as a string.
+    pub fn for_protocol(protocol_version: i32) -> Option<Self> {
+        match protocol_version {
+            v if v < crate::versions::v5::PROTOCOL_VERSION => Some(Self::Simple),
+            v if v < crate::versions::v735::PROTOCOL_VERSION => Some(Self::Hyphenated),
+            _ => None,
+        }
+    }
+
+    pub fn format(self, uuid: Uuid) -> String {
+        match self {
+            Self::Simple => uuid.simple().to_string(),
+            Self::Hyphenated => uuid.hyphenated().to_string(),
+        }
+    }
+}
+
+/// Parses a uuid in either format, servers don't always use the one their version should.
+///
+/// Anything else [`Uuid::try_parse`] accepts is let through too.
+pub fn parse_uuid(uuid: &str) -> Result<Uuid, uuid::Error> {
+    Uuid::try_parse(uuid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::versions::{v3, v4, v5, v578, v735};
+
+    #[test]
+    fn offline_uuid_matches_java() {
+        // UUID.nameUUIDFromBytes("OfflinePlayer:Notch".getBytes(UTF_8))
+        let uuid = offline_uuid("Notch");
+        assert_eq!(uuid.to_string(), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+        assert_eq!(uuid.get_version_num(), 3);
+
+        assert_eq!(offline_profile("Notch").id, uuid);
+        assert_ne!(offline_uuid("notch"), uuid);
+    }
+
+    #[test]
+    fn login_success_uuid_format() {
+        let uuid = offline_uuid("Notch");
+
+        for protocol_version in [v3::PROTOCOL_VERSION, v4::PROTOCOL_VERSION].iter() {
+            assert_eq!(
+                UuidFormat::for_protocol(*protocol_version),
+                Some(UuidFormat::Simple)
+            );
+        }
+        for protocol_version in [v5::PROTOCOL_VERSION, v578::PROTOCOL_VERSION].iter() {
+            assert_eq!(
+                UuidFormat::for_protocol(*protocol_version),
+                Some(UuidFormat::Hyphenated)
+            );
+        }
+        assert_eq!(UuidFormat::for_protocol(v735::PROTOCOL_VERSION), None);
+
+        let simple = v3::packets::login::s2c::LoginSuccess::new(
+            uuid,
+            "Notch".to_owned(),
+            v3::PROTOCOL_VERSION,
+        );
+        assert_eq!(simple.uuid, "b50ad385829d3141a2167e7d7539ba7f");
+        assert_eq!(simple.parse_uuid().unwrap(), uuid);
+
+        let hyphenated = v3::packets::login::s2c::LoginSuccess::new(
+            uuid,
+            "Notch".to_owned(),
+            v5::PROTOCOL_VERSION,
+        );
+        assert_eq!(hyphenated.uuid, "b50ad385-829d-3141-a216-7e7d7539ba7f");
+        assert_eq!(hyphenated.parse_uuid().unwrap(), uuid);
+
+        assert!(parse_uuid("b50ad385-829d").is_err());
+    }
+
+    #[test]
+    fn usernames() {
+        assert_eq!(validate_username("Notch"), Ok(()));
+        assert_eq!(validate_username("a_1"), Ok(()));
+        assert_eq!(validate_username("ab"), Err(UsernameError::TooShort(2)));
+        assert_eq!(
+            validate_username("abcdefghijklmnopq"),
+            Err(UsernameError::TooLong(17))
+        );
+        assert_eq!(
+            validate_username("Nötch"),
+            Err(UsernameError::InvalidCharacter('ö'))
+        );
+        assert!(!is_valid_username("a b"));
+    }
+}
@@ -1,8 +1,158 @@
 use crate::types::{BufType, ReadError};
 use bytes::{Buf, BufMut, Bytes};
+use std::fmt;
 
+pub use packet_derive::Packet;
+
+/// `#[derive(Packet)]` works out everything but the id from the packet's module,
+/// `versions::vNNN::packets::<state>::<direction>`, the `#[packet(...)]` options override it.
 pub trait Packet {
     const PACKET_ID: i32;
+
+    /// The type's name. Packets renamed in later versions keep their old name in older ones,
+    /// e.g. `Response` before 1.19 and `StatusResponse` since.
+    const NAME: &'static str;
+    const DIRECTION: Direction;
+    const STATE: PacketState;
+
+    /// First protocol version with this packet.
+    const MIN_PROTOCOL_VERSION: i32;
+    /// Last protocol version with this packet, `None` if the latest still has it.
+    const MAX_PROTOCOL_VERSION: Option<i32>;
+
+    fn is_valid_for(protocol_version: i32) -> bool {
+        protocol_version >= Self::MIN_PROTOCOL_VERSION
+            && !matches!(Self::MAX_PROTOCOL_VERSION, Some(max) if protocol_version > max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Serverbound
+    C2s,
+    /// Clientbound
+    S2c,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Direction::C2s => "c2s",
+            Direction::S2c => "s2c",
+        })
+    }
+}
+
+/// Like `ProtocolState::LABEL`, but the same for every version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PacketState {
+    Handshaking,
+    Status,
+    Login,
+    Configuration,
+    Play,
+}
+
+impl fmt::Display for PacketState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PacketState::Handshaking => "handshaking",
+            PacketState::Status => "status",
+            PacketState::Login => "login",
+            PacketState::Configuration => "configuration",
+            PacketState::Play => "play",
+        })
+    }
+}
+
+/// Const parsing of `module_path!()` for `#[derive(Packet)]`.
+#[doc(hidden)]
+pub mod module_path {
+    use super::{Direction, PacketState};
+
+    /// End of the `::` separated segment starting at `start`.
+    const fn segment_end(path: &[u8], start: usize) -> usize {
+        let mut end = start;
+        while end < path.len() && path[end] != b':' {
+            end += 1;
+        }
+        end
+    }
+
+    const fn has_segment(path: &str, segment: &str) -> bool {
+        let (path, segment) = (path.as_bytes(), segment.as_bytes());
+
+        let mut start = 0;
+        while start < path.len() {
+            let end = segment_end(path, start);
+
+            if end - start == segment.len() {
+                let mut i = 0;
+                while i < segment.len() && path[start + i] == segment[i] {
+                    i += 1;
+                }
+                if i == segment.len() {
+                    return true;
+                }
+            }
+
+            start = end + 2;
+        }
+
+        false
+    }
+
+    pub const fn direction(path: &str) -> Direction {
+        if has_segment(path, "c2s") {
+            Direction::C2s
+        } else if has_segment(path, "s2c") {
+            Direction::S2c
+        } else {
+            panic!("no c2s or s2c module, set `#[packet(direction = ...)]`")
+        }
+    }
+
+    pub const fn state(path: &str) -> PacketState {
+        if has_segment(path, "handshake") {
+            PacketState::Handshaking
+        } else if has_segment(path, "status") {
+            PacketState::Status
+        } else if has_segment(path, "login") {
+            PacketState::Login
+        } else if has_segment(path, "configuration") {
+            PacketState::Configuration
+        } else if has_segment(path, "play") {
+            PacketState::Play
+        } else {
+            panic!("no state module, set `#[packet(state = ...)]`")
+        }
+    }
+
+    /// From the `vNNN` module.
+    pub const fn protocol_version(path: &str) -> i32 {
+        let path = path.as_bytes();
+
+        let mut start = 0;
+        while start < path.len() {
+            let end = segment_end(path, start);
+
+            if end - start > 1 && path[start] == b'v' {
+                let mut version = 0;
+                let mut i = start + 1;
+                while i < end && path[i].is_ascii_digit() {
+                    version = version * 10 + (path[i] - b'0') as i32;
+                    i += 1;
+                }
+                if i == end {
+                    return version;
+                }
+            }
+
+            start = end + 2;
+        }
+
+        panic!("no vNNN module, set `#[packet(since = ...)]`")
+    }
 }
 
 pub trait PacketRead: Packet + Sized {
@@ -10,8 +160,29 @@ pub trait PacketRead: Packet + Sized {
     fn read_body<B: Buf>(data: &mut B) -> std::result::Result<Self, ReadError>;
 }
 
+/// Vanilla's limit on a packet's length, the length prefix itself excluded.
+pub const MAX_PACKET_LENGTH: usize = 2097151;
+
+/// A packet over [`MAX_PACKET_LENGTH`], vanilla disconnects on them so they aren't sent.
+#[derive(Debug, thiserror::Error)]
+#[error("packet is {0} bytes, more than the {MAX_PACKET_LENGTH} vanilla accepts")]
+pub struct PacketTooLarge(pub usize);
+
 pub trait PacketWrite: Packet {
     fn write_body<B: BufMut>(&self, buf: &mut B);
+
+    /// Exact number of bytes `write_body` writes.
+    fn body_len(&self) -> usize {
+        let mut buf = Vec::new();
+        self.write_body(&mut buf);
+        buf.len()
+    }
+
+    /// Length of the id and body, before compression. Uncompressed packets can be at most
+    /// [`MAX_PACKET_LENGTH`], the data length prefix included once compression is enabled.
+    fn packet_len(&self) -> usize {
+        crate::types::proxy::i32_as_v32::encoded_len(&Self::PACKET_ID) + self.body_len()
+    }
 }
 
 impl<P: Packet> PacketRead for P
@@ -30,72 +201,38 @@ where
     fn write_body<B: BufMut>(&self, buf: &mut B) {
         self.buf_write(buf)
     }
+
+    fn body_len(&self) -> usize {
+        self.encoded_len()
+    }
 }
 
 pub trait PacketFromIdBody {
     fn from_id_body(id: i32, body: Bytes) -> std::result::Result<Self, ReadError>
     where
         Self: Sized;
-}
-
-macro_rules! impl_packets_enum {
-    [$($packet:ident),* $(,)?] => {
-        #[derive(Debug)]
-        pub enum Packets {
-            $($packet($packet),)*
-
-            Unknown(i32, bytes::Bytes)
-        }
-
-        #[automatically_derived]
-        impl Packets {
-            pub fn is_known(&self) -> bool {
-                !matches!(self, Self::Unknown(_, _))
-            }
-        }
-
-        #[automatically_derived]
-        impl crate::packet::PacketFromIdBody for Packets {
-            fn from_id_body(id: i32, mut body: bytes::Bytes) -> std::result::Result<Self, crate::types::ReadError> {
-                match id {
-                    $(<$packet as crate::packet::Packet>::PACKET_ID => <$packet as crate::packet::PacketRead>::read_body(&mut body).map(Self::$packet),)*
-
-                    other => Ok(Self::Unknown(other, body)),
-                }
-            }
-        }
 
-        $(
-            impl std::convert::TryFrom<Packets> for $packet {
-                type Error = crate::error::Error;
-
-                fn try_from(value: Packets) -> Result<Self, Self::Error> {
-                    match value {
-                        Packets::$packet(packet) => Ok(packet),
-                        _ => Err(crate::error::Error::IncorectPacket),
-                    }
-                }
-            }
-        )*
-    };
+    /// Name of the known packet with `id`.
+    fn name_of(_id: i32) -> Option<&'static str> {
+        None
+    }
 }
 
-pub(crate) use impl_packets_enum;
+pub(crate) use packet_derive::impl_packets_enum;
 
 #[allow(unused_imports)]
 pub(crate) mod prelude {
-    pub use crate as mcproto;
-
     pub use super::{Packet, PacketRead, PacketWrite};
     pub use crate::types::{
         proxy::{
-            i32_as_v32, length_prefix_array, length_prefix_bytes, option_length_prefix_bytes,
-            remaining_bytes, u16_length_prefix_bytes,
+            i32_as_v32, i64_as_v64, legacy_position, length_prefix_array, length_prefix_bytes,
+            option_length_prefix_bytes, remaining_bytes, strict_identifier,
+            u16_length_prefix_bytes,
         },
-        BufType, Identifier, ReadError,
+        Angle, BitSet, BufType, BytesString, FixedBitSet, FixedPoint, Identifier, Position,
+        ReadError,
     };
-    pub use bytes::{Buf, BufMut};
-    pub use packet_derive::{BufType, Packet};
+    pub use bytes::{Buf, BufMut, Bytes};
     pub use std::collections::HashMap;
     pub use uuid::Uuid;
 
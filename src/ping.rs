@@ -0,0 +1,234 @@
+//! Server list ping, modern status with a fallback to the pre-1.7 legacy ping.
+// https://wiki.vg/Server_List_Ping
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    handshake::{Handshake, NextState},
+    status::{Description, Players, ServerStatus, Version},
+};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Protocol version sent in a legacy ping, 1.6.4's.
+pub const LEGACY_PROTOCOL_VERSION: u8 = 78;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PingError {
+    #[error("invalid status json: {0}")]
+    InvalidStatus(#[from] serde_json::Error),
+
+    #[error("invalid legacy ping response")]
+    InvalidLegacyResponse,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ping {
+    pub status: ServerStatus,
+    /// The status json exactly as the server sent it, `None` for legacy pings.
+    pub raw_status: Option<String>,
+    /// Round trip of the ping packet, or of the status request if the server didn't answer it.
+    pub latency: Duration,
+    /// The server only answered the legacy ping.
+    pub legacy: bool,
+}
+
+/// `Handshake` a vanilla client sends before requesting the status.
+pub fn status_handshake(host: &str, port: u16, protocol_version: i32) -> Handshake {
+    Handshake {
+        protocol_version,
+        server_address: host.to_owned(),
+        server_port: port,
+        next_state: NextState::Status,
+        forge: None,
+        bungee_forwarding: None,
+    }
+}
+
+/// Vanilla sends the current time, servers are expected to echo it back unchanged.
+pub(crate) fn ping_payload() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as i64)
+}
+
+/// 1.6's ping, which every later server still answers.
+pub fn legacy_request(host: &str, port: u16) -> Vec<u8> {
+    fn push_utf16(data: &mut Vec<u8>, string: &str) {
+        let chars: Vec<u16> = string.encode_utf16().collect();
+        data.extend_from_slice(&(chars.len() as u16).to_be_bytes());
+        chars
+            .iter()
+            .for_each(|c| data.extend_from_slice(&c.to_be_bytes()));
+    }
+
+    let mut data = vec![0xfe, 0x01, 0xfa];
+    push_utf16(&mut data, "MC|PingHost");
+
+    let host_len = host.encode_utf16().count();
+    data.extend_from_slice(&(7 + 2 * host_len as u16).to_be_bytes());
+    data.push(LEGACY_PROTOCOL_VERSION);
+    push_utf16(&mut data, host);
+    data.extend_from_slice(&i32::from(port).to_be_bytes());
+
+    data
+}
+
+/// Parses a legacy kick packet, `None` if more data is needed.
+///
+/// Handles both the 1.4+ (`§1\0`) format, and the older `motd§online§max` one.
+pub fn parse_legacy_response(data: &[u8]) -> Result<Option<ServerStatus>, PingError> {
+    if data.len() < 3 {
+        return Ok(None);
+    }
+    if data[0] != 0xff {
+        return Err(PingError::InvalidLegacyResponse);
+    }
+
+    let len = u16::from_be_bytes([data[1], data[2]]) as usize;
+    let data = match data.get(3..3 + 2 * len) {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+
+    let chars: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect();
+    let response = String::from_utf16(&chars).map_err(|_| PingError::InvalidLegacyResponse)?;
+
+    let (version, motd, online, max) = if let Some(response) = response.strip_prefix("§1\0") {
+        match response.split('\0').collect::<Vec<_>>()[..] {
+            [protocol, name, motd, online, max] => {
+                let version = Version {
+                    name: name.to_owned(),
+                    protocol: protocol.parse().unwrap_or(0),
+                };
+                (Some(version), motd, online, max)
+            }
+            _ => return Err(PingError::InvalidLegacyResponse),
+        }
+    } else {
+        // the motd can contain `§` too
+        match response.rsplitn(3, '§').collect::<Vec<_>>()[..] {
+            [max, online, motd] => (None, motd, online, max),
+            _ => return Err(PingError::InvalidLegacyResponse),
+        }
+    };
+
+    Ok(Some(ServerStatus {
+        version,
+        players: Some(Players {
+            max: max.parse().unwrap_or(0),
+            online: online.parse().unwrap_or(0),
+            sample: Vec::new(),
+        }),
+        description: Some(Description::Legacy(motd.to_owned())),
+        ..ServerStatus::default()
+    }))
+}
+
+/// Servers for the stdio and tokio ping tests, each answers a single ping on a thread.
+#[cfg(test)]
+pub(crate) mod test_servers {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        handshake, role,
+        stdio::{accept_stdio_stream, StdIoConnection},
+        versions::latest::{
+            packets::status::{
+                c2s::{PingRequest, StatusRequest},
+                s2c::{PingResponse, StatusResponse},
+            },
+            states,
+        },
+    };
+
+    pub(crate) const LEGACY_MOTD: &str = "A Minecraft Server";
+
+    /// Answers a single status request with `response`, then echoes the ping 200ms later with
+    /// `payload_offset` added.
+    pub(crate) fn status_server(response: &'static str, payload_offset: i64) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut connection: StdIoConnection<role::Server, handshake::HandshakingState> =
+                accept_stdio_stream(stream).unwrap();
+            connection.next_packet().unwrap();
+
+            let mut connection = connection.next_state::<states::StatusState>();
+            connection.expect_next_packet::<StatusRequest>().unwrap();
+            connection
+                .write_packet(StatusResponse {
+                    response: response.to_owned(),
+                })
+                .unwrap();
+
+            let ping: PingRequest = connection.expect_next_packet().unwrap();
+            thread::sleep(Duration::from_millis(200));
+            connection
+                .write_packet(PingResponse {
+                    payload: ping.payload + payload_offset,
+                })
+                .unwrap();
+        });
+
+        port
+    }
+
+    /// Sends a valid looking frame one byte every 50ms.
+    pub(crate) fn slow_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for byte in [40, 0, 38].iter().chain([b'{'; 38].iter()) {
+                thread::sleep(Duration::from_millis(50));
+                if stream.write_all(&[*byte]).is_err() {
+                    return;
+                }
+            }
+        });
+
+        port
+    }
+
+    /// A pre-1.7 server, it hangs up on the modern handshake and only answers `0xfe 0x01`.
+    pub(crate) fn legacy_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut byte = [0];
+            stream.read_exact(&mut byte).unwrap();
+            assert_ne!(byte[0], 0xfe);
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![0; super::legacy_request("127.0.0.1", port).len()];
+            stream.read_exact(&mut request).unwrap();
+            assert_eq!(request[..2], [0xfe, 0x01]);
+
+            let response = format!("§1\0127\01.4.7\0{}\03\020", LEGACY_MOTD);
+            let chars: Vec<u16> = response.encode_utf16().collect();
+            let mut data = vec![0xff];
+            data.extend_from_slice(&(chars.len() as u16).to_be_bytes());
+            chars
+                .iter()
+                .for_each(|c| data.extend_from_slice(&c.to_be_bytes()));
+            stream.write_all(&data).unwrap();
+        });
+
+        port
+    }
+}
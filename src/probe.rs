@@ -0,0 +1,250 @@
+//! Working out which protocol version a server speaks, to pick a `versions::vNNN` module.
+//!
+//! The status response is tried first. If the server doesn't expose one, or reports a version
+//! that can't be used, a login is started with a version no server speaks, and the version is
+//! read from the "outdated client/server" disconnect message.
+
+use crate::{
+    handshake::{Handshake, NextState},
+    status::ServerStatus,
+    versions::SUPPORTED,
+};
+
+/// Set in the protocol version of snapshots since 1.16.4.
+pub const SNAPSHOT_BIT: i32 = 0x4000_0000;
+
+/// Protocol version sent by the login probe, every server disconnects it with their version.
+pub const LOGIN_PROBE_PROTOCOL_VERSION: i32 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeMethod {
+    Status,
+    /// The pre-1.7 ping.
+    LegacyStatus,
+    Login,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Probe {
+    pub method: ProbeMethod,
+    /// What the status response said, `None` if it wasn't used.
+    pub reported_protocol: Option<i32>,
+    pub version_name: Option<String>,
+    /// The supported version to use, `None` if it couldn't be worked out or isn't supported.
+    pub protocol_version: Option<i32>,
+    /// The reported protocol doesn't match the version name.
+    ///
+    /// Proxies often do this, some also echo back the handshake's version which can't be told
+    /// apart from a vanilla server. Versions newer than the ones supported can't be checked.
+    pub non_vanilla: bool,
+}
+
+impl Probe {
+    pub fn from_status(status: &ServerStatus, legacy: bool) -> Self {
+        let (reported_protocol, version_name) = match &status.version {
+            Some(version) => (Some(version.protocol), Some(version.name.clone())),
+            None => (None, None),
+        };
+        let name_protocol = version_name.as_deref().and_then(protocol_for_name);
+
+        let non_vanilla = match (reported_protocol, name_protocol) {
+            (Some(reported), Some(name_protocol)) => reported != name_protocol,
+            _ => false,
+        };
+        let protocol_version = match reported_protocol {
+            Some(reported) if is_supported(reported) => Some(reported),
+            // an unknown protocol is only usable if the name says which version it really is
+            Some(_) if non_vanilla => name_protocol,
+            Some(_) => None,
+            None => name_protocol,
+        };
+
+        Probe {
+            method: if legacy {
+                ProbeMethod::LegacyStatus
+            } else {
+                ProbeMethod::Status
+            },
+            reported_protocol,
+            version_name,
+            protocol_version,
+            non_vanilla,
+        }
+    }
+
+    /// From the reason of the login probe's `Disconnect`.
+    pub fn from_disconnect_reason(reason: &str) -> Self {
+        let version_name = find_version_name(reason);
+
+        Probe {
+            method: ProbeMethod::Login,
+            reported_protocol: None,
+            protocol_version: version_name.and_then(protocol_for_name),
+            version_name: version_name.map(str::to_owned),
+            non_vanilla: false,
+        }
+    }
+}
+
+pub fn is_snapshot(protocol_version: i32) -> bool {
+    protocol_version > 0 && protocol_version & SNAPSHOT_BIT != 0
+}
+
+pub fn is_supported(protocol_version: i32) -> bool {
+    minecraft_versions(protocol_version).is_some()
+}
+
+/// The minecraft versions a supported protocol version is for.
+pub fn minecraft_versions(protocol_version: i32) -> Option<&'static [&'static str]> {
+    SUPPORTED
+        .iter()
+        .find(|(supported, _)| *supported == protocol_version)
+        .map(|(_, names)| *names)
+}
+
+/// Maps the first minecraft version in `text` (e.g. `Paper 1.20.4`) to its protocol version.
+pub fn protocol_for_name(text: &str) -> Option<i32> {
+    let name = find_version_name(text)?;
+
+    SUPPORTED
+        .iter()
+        .find(|(_, names)| names.contains(&name))
+        .map(|(protocol_version, _)| *protocol_version)
+}
+
+/// First `1.x` or `1.x.y` in `text`.
+fn find_version_name(text: &str) -> Option<&str> {
+    text.match_indices("1.").find_map(|(start, _)| {
+        let preceded_by_number = text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_digit() || c == '.');
+        if preceded_by_number {
+            return None;
+        }
+
+        let len = text[start..]
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(text.len() - start);
+        let name = text[start..start + len].trim_end_matches('.');
+
+        // needs at least the minor version
+        if name.len() > 2 {
+            Some(name)
+        } else {
+            None
+        }
+    })
+}
+
+/// `Handshake` for the login probe.
+pub fn login_probe_handshake(host: &str, port: u16) -> Handshake {
+    Handshake {
+        protocol_version: LOGIN_PROBE_PROTOCOL_VERSION,
+        server_address: host.to_owned(),
+        server_port: port,
+        next_state: NextState::Login,
+        forge: None,
+        bungee_forwarding: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::Version;
+
+    fn status(name: &str, protocol: i32) -> ServerStatus {
+        ServerStatus {
+            version: Some(Version {
+                name: name.to_owned(),
+                protocol,
+            }),
+            ..ServerStatus::default()
+        }
+    }
+
+    #[test]
+    fn vanilla_status() {
+        let probe = Probe::from_status(&status("1.21.1", 767), false);
+        assert_eq!(probe.method, ProbeMethod::Status);
+        assert_eq!(probe.reported_protocol, Some(767));
+        assert_eq!(probe.protocol_version, Some(767));
+        assert!(!probe.non_vanilla);
+
+        let probe = Probe::from_status(&status("Paper 1.8.8", 47), false);
+        assert_eq!(probe.protocol_version, Some(47));
+        assert!(!probe.non_vanilla);
+    }
+
+    #[test]
+    fn unknown_versions() {
+        // newer than the supported versions, still vanilla
+        let probe = Probe::from_status(&status("1.21.4", 769), false);
+        assert_eq!(probe.reported_protocol, Some(769));
+        assert_eq!(probe.protocol_version, None);
+        assert!(!probe.non_vanilla);
+
+        let probe = Probe::from_status(&status("24w14a", SNAPSHOT_BIT | 190), false);
+        assert_eq!(probe.protocol_version, None);
+        assert!(!probe.non_vanilla);
+
+        let probe = Probe::from_status(&status("Maintenance", -1), false);
+        assert_eq!(probe.protocol_version, None);
+        assert!(!probe.non_vanilla);
+
+        let probe = Probe::from_status(&ServerStatus::default(), false);
+        assert_eq!(probe.reported_protocol, None);
+        assert_eq!(probe.protocol_version, None);
+        assert!(!probe.non_vanilla);
+    }
+
+    #[test]
+    fn name_and_protocol_disagree() {
+        // a proxy echoing the handshake's version
+        let probe = Probe::from_status(&status("Velocity 1.7.2-1.21.1", 767), false);
+        assert_eq!(probe.protocol_version, Some(767));
+        assert!(probe.non_vanilla);
+
+        // the name says which version to use
+        let probe = Probe::from_status(&status("Waterfall 1.20.4", -1), false);
+        assert_eq!(probe.protocol_version, Some(765));
+        assert!(probe.non_vanilla);
+    }
+
+    #[test]
+    fn legacy_status() {
+        let probe = Probe::from_status(&status("1.6.4", 78), true);
+        assert_eq!(probe.method, ProbeMethod::LegacyStatus);
+        assert_eq!(probe.protocol_version, None);
+        assert!(!probe.non_vanilla);
+    }
+
+    #[test]
+    fn disconnect_reason() {
+        let probe =
+            Probe::from_disconnect_reason(r#"{"text":"Outdated server! I'm still on 1.20.4"}"#);
+        assert_eq!(probe.method, ProbeMethod::Login);
+        assert_eq!(probe.version_name.as_deref(), Some("1.20.4"));
+        assert_eq!(probe.protocol_version, Some(765));
+    }
+
+    #[test]
+    fn version_names() {
+        assert_eq!(find_version_name("1.20.4"), Some("1.20.4"));
+        assert_eq!(find_version_name("Paper 1.20.4"), Some("1.20.4"));
+        assert_eq!(
+            find_version_name("Outdated client! Please use 1.8."),
+            Some("1.8")
+        );
+        assert_eq!(find_version_name("BungeeCord 1.8.x-1.21.x"), Some("1.8"));
+        // not preceded by another number
+        assert_eq!(find_version_name("v21.1 then 1.12"), Some("1.12"));
+        assert_eq!(find_version_name("0.1.2"), None);
+        assert_eq!(find_version_name("1."), None);
+        assert_eq!(find_version_name("Spigot"), None);
+
+        assert_eq!(protocol_for_name("Spigot 1.7.10"), Some(5));
+        assert_eq!(protocol_for_name("1.99"), None);
+    }
+}
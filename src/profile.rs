@@ -0,0 +1,558 @@
+//! Player profiles, as returned by the session server and sent in `LoginSuccess`.
+
+use std::{convert::TryFrom, fs, io, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Sign, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use uuid::Uuid;
+
+use crate::versions::{v3, v735, v759, v766};
+
+pub const TEXTURES_PROPERTY: &str = "textures";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileError {
+    #[error("invalid uuid: {0}")]
+    InvalidUuid(#[from] uuid::Error),
+
+    #[error("invalid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("invalid textures: {0}")]
+    InvalidTextures(#[from] serde_json::Error),
+
+    #[error("invalid public key: {0}")]
+    InvalidPublicKey(String),
+
+    #[error("couldn't read public key: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("property {0:?} isn't signed")]
+    MissingSignature(String),
+
+    #[error("property {0:?} has an invalid signature")]
+    InvalidSignature(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameProfile {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Property {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl GameProfile {
+    pub fn new<S: Into<String>>(id: Uuid, name: S) -> Self {
+        GameProfile {
+            id,
+            name: name.into(),
+            properties: Vec::new(),
+        }
+    }
+
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+
+    /// Replaces any property with the same name, e.g. to swap in another player's skin.
+    pub fn set_property(&mut self, property: Property) {
+        self.properties
+            .retain(|existing| existing.name != property.name);
+        self.properties.push(property);
+    }
+
+    /// Decodes the `textures` property, `None` if there isn't one.
+    pub fn textures(&self) -> Result<Option<Textures>, ProfileError> {
+        self.property(TEXTURES_PROPERTY)
+            .map(Property::textures)
+            .transpose()
+    }
+}
+
+impl Property {
+    /// Decodes the value as textures, regardless of the property's name.
+    pub fn textures(&self) -> Result<Textures, ProfileError> {
+        let json = STANDARD.decode(&self.value)?;
+        let raw: RawTextures = serde_json::from_slice(&json)?;
+
+        Ok(Textures {
+            timestamp: raw.timestamp,
+            profile_id: raw.profile_id,
+            profile_name: raw.profile_name,
+            signature_required: raw.signature_required,
+            skin: raw.textures.skin.map(|skin| Skin {
+                url: skin.url,
+                slim: skin.metadata.and_then(|metadata| metadata.model).as_deref() == Some("slim"),
+            }),
+            cape: raw.textures.cape.map(|cape| Cape { url: cape.url }),
+        })
+    }
+}
+
+/// Decoded `textures` property.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Textures {
+    /// Milliseconds since the unix epoch.
+    pub timestamp: i64,
+    pub profile_id: Uuid,
+    pub profile_name: String,
+    pub signature_required: bool,
+    pub skin: Option<Skin>,
+    pub cape: Option<Cape>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skin {
+    pub url: String,
+    /// Uses the slim ("Alex") arm model.
+    pub slim: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cape {
+    pub url: String,
+}
+
+impl Textures {
+    /// Encodes as an unsigned `textures` property, clients ignore it if signatures are required.
+    pub fn to_property(&self) -> Property {
+        let raw = RawTextures {
+            timestamp: self.timestamp,
+            profile_id: self.profile_id,
+            profile_name: self.profile_name.clone(),
+            signature_required: self.signature_required,
+            textures: RawTextureMap {
+                skin: self.skin.as_ref().map(|skin| RawTexture {
+                    url: skin.url.clone(),
+                    metadata: if skin.slim {
+                        Some(RawTextureMetadata {
+                            model: Some("slim".to_owned()),
+                        })
+                    } else {
+                        None
+                    },
+                }),
+                cape: self.cape.as_ref().map(|cape| RawTexture {
+                    url: cape.url.clone(),
+                    metadata: None,
+                }),
+            },
+        };
+
+        let json = serde_json::to_vec(&raw).expect("textures always serialize");
+
+        Property {
+            name: TEXTURES_PROPERTY.to_owned(),
+            value: STANDARD.encode(json),
+            signature: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawTextures {
+    timestamp: i64,
+    #[serde(with = "uuid::serde::simple")]
+    profile_id: Uuid,
+    profile_name: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    signature_required: bool,
+    textures: RawTextureMap,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawTextureMap {
+    #[serde(rename = "SKIN", default, skip_serializing_if = "Option::is_none")]
+    skin: Option<RawTexture>,
+    #[serde(rename = "CAPE", default, skip_serializing_if = "Option::is_none")]
+    cape: Option<RawTexture>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawTexture {
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<RawTextureMetadata>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawTextureMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+}
+
+/// Mojang's key used to sign profile properties.
+///
+/// It ships with the vanilla client as `yggdrasil_session_pubkey.der`.
+#[derive(Debug, Clone)]
+pub struct YggdrasilKey(RsaPublicKey);
+
+impl YggdrasilKey {
+    /// Loads a `-----BEGIN PUBLIC KEY-----` pem.
+    pub fn from_pem(pem: &str) -> Result<Self, ProfileError> {
+        RsaPublicKey::from_public_key_pem(pem)
+            .map(YggdrasilKey)
+            .map_err(|err| ProfileError::InvalidPublicKey(err.to_string()))
+    }
+
+    pub fn from_pem_file<P: AsRef<Path>>(path: P) -> Result<Self, ProfileError> {
+        Self::from_pem(&fs::read_to_string(path)?)
+    }
+
+    pub fn from_der(der: &[u8]) -> Result<Self, ProfileError> {
+        RsaPublicKey::from_public_key_der(der)
+            .map(YggdrasilKey)
+            .map_err(|err| ProfileError::InvalidPublicKey(err.to_string()))
+    }
+
+    /// Checks the property's signature (SHA1withRSA over the base64 value).
+    pub fn verify(&self, property: &Property) -> Result<(), ProfileError> {
+        let signature = property
+            .signature
+            .as_ref()
+            .ok_or_else(|| ProfileError::MissingSignature(property.name.clone()))?;
+        let signature = STANDARD.decode(signature)?;
+
+        let hashed = Sha1::digest(property.value.as_bytes());
+        self.0
+            .verify(Pkcs1v15Sign::new::<Sha1>(), &hashed, &signature)
+            .map_err(|_| ProfileError::InvalidSignature(property.name.clone()))
+    }
+
+    /// Checks every property of the profile is signed by Mojang.
+    pub fn verify_profile(&self, profile: &GameProfile) -> Result<(), ProfileError> {
+        profile
+            .properties
+            .iter()
+            .try_for_each(|property| self.verify(property))
+    }
+}
+
+//
+// LoginSuccess conversions
+//
+
+// there's no `From<GameProfile>` for v3, the uuid format depends on the protocol version so use
+// `LoginSuccess::new`
+
+impl TryFrom<v3::packets::login::s2c::LoginSuccess> for GameProfile {
+    type Error = ProfileError;
+
+    fn try_from(login_success: v3::packets::login::s2c::LoginSuccess) -> Result<Self, Self::Error> {
+        Ok(GameProfile::new(
+            login_success.parse_uuid()?,
+            login_success.username,
+        ))
+    }
+}
+
+impl From<GameProfile> for v735::packets::login::s2c::LoginSuccess {
+    fn from(profile: GameProfile) -> Self {
+        Self {
+            uuid: profile.id,
+            username: profile.name,
+        }
+    }
+}
+
+impl From<v735::packets::login::s2c::LoginSuccess> for GameProfile {
+    fn from(login_success: v735::packets::login::s2c::LoginSuccess) -> Self {
+        GameProfile::new(login_success.uuid, login_success.username)
+    }
+}
+
+impl From<Property> for v759::packets::login::s2c::login_success::Property {
+    fn from(property: Property) -> Self {
+        Self {
+            name: property.name,
+            value: property.value,
+            signature: property.signature,
+        }
+    }
+}
+
+impl From<v759::packets::login::s2c::login_success::Property> for Property {
+    fn from(property: v759::packets::login::s2c::login_success::Property) -> Self {
+        Property {
+            name: property.name,
+            value: property.value,
+            signature: property.signature,
+        }
+    }
+}
+
+impl From<GameProfile> for v759::packets::login::s2c::LoginSuccess {
+    fn from(profile: GameProfile) -> Self {
+        Self {
+            uuid: profile.id,
+            username: profile.name,
+            properties: profile.properties.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<v759::packets::login::s2c::LoginSuccess> for GameProfile {
+    fn from(login_success: v759::packets::login::s2c::LoginSuccess) -> Self {
+        GameProfile {
+            id: login_success.uuid,
+            name: login_success.username,
+            properties: login_success
+                .properties
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+}
+
+/// Strict error handling is left off, like vanilla.
+impl From<GameProfile> for v766::packets::login::s2c::LoginSuccess {
+    fn from(profile: GameProfile) -> Self {
+        Self {
+            uuid: profile.id,
+            username: profile.name,
+            properties: profile.properties.into_iter().map(Into::into).collect(),
+            strict_error_handling: false,
+        }
+    }
+}
+
+impl From<v766::packets::login::s2c::LoginSuccess> for GameProfile {
+    fn from(login_success: v766::packets::login::s2c::LoginSuccess) -> Self {
+        GameProfile {
+            id: login_success.uuid,
+            name: login_success.username,
+            properties: login_success
+                .properties
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a throwaway key, with `SIGNED_TEXTURES` signed by its private half
+    const PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDMQApOwaRU4XIvhuhZ19iVBHa2
+LXK3Q8171z+5YPNq30CSgp5f5RoVdW00vSic9whhv5NhTpFMfjOeaalW1OJObRv3
+nX5ae7zkVVM+4VSsK2d5szphy/UF+l6Nr37K5xhCIFcW2rWi+jnb8dm4uPQA4vfj
+m+MKme8lJSA3IMTJQwIDAQAB
+-----END PUBLIC KEY-----
+";
+    const SIGNED_TEXTURES: &str = "eyJ0aW1lc3RhbXAiOjE3MDAwMDAwMDAwMDAsInByb2ZpbGVJZCI6IjA2OWE3OWY0NDRlOTQ3MjZhNWJlZmNhOTBlMzhhYWY1IiwicHJvZmlsZU5hbWUiOiJOb3RjaCIsInNpZ25hdHVyZVJlcXVpcmVkIjp0cnVlLCJ0ZXh0dXJlcyI6eyJTS0lOIjp7InVybCI6Imh0dHA6Ly90ZXh0dXJlcy5taW5lY3JhZnQubmV0L3RleHR1cmUvc2tpbiIsIm1ldGFkYXRhIjp7Im1vZGVsIjoic2xpbSJ9fSwiQ0FQRSI6eyJ1cmwiOiJodHRwOi8vdGV4dHVyZXMubWluZWNyYWZ0Lm5ldC90ZXh0dXJlL2NhcGUifX19";
+    const SIGNATURE: &str = "c8SJGKRiuBLNYhQl1/WKRNzgn8Qwcl8J2A3xZS+MF3sKzl+Afv1I7w5ctnUdiux1Nn7Kc1cI3SH9mJkvLyNuREJABII+M1G+P1coqKyEUUyQTNht7kjPGDFRGoZ1GMzfA/R9mOd6xUL+WE9C23DO90oYO7NPL8elgDiS+VEMcjc=";
+
+    fn id() -> Uuid {
+        Uuid::try_parse("069a79f444e94726a5befca90e38aaf5").unwrap()
+    }
+
+    fn signed_textures() -> Property {
+        Property {
+            name: TEXTURES_PROPERTY.to_owned(),
+            value: SIGNED_TEXTURES.to_owned(),
+            signature: Some(SIGNATURE.to_owned()),
+        }
+    }
+
+    fn profile() -> GameProfile {
+        let mut profile = GameProfile::new(id(), "Notch");
+        profile.set_property(signed_textures());
+        profile
+    }
+
+    #[test]
+    fn decode_textures() {
+        let textures = profile().textures().unwrap().unwrap();
+
+        assert_eq!(
+            textures,
+            Textures {
+                timestamp: 1700000000000,
+                profile_id: id(),
+                profile_name: "Notch".to_owned(),
+                signature_required: true,
+                skin: Some(Skin {
+                    url: "http://textures.minecraft.net/texture/skin".to_owned(),
+                    slim: true,
+                }),
+                cape: Some(Cape {
+                    url: "http://textures.minecraft.net/texture/cape".to_owned(),
+                }),
+            }
+        );
+        assert_eq!(textures.to_property().textures().unwrap(), textures);
+
+        assert!(GameProfile::new(id(), "Notch")
+            .textures()
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn classic_skin_without_cape() {
+        let json = r#"{"timestamp":0,"profileId":"069a79f444e94726a5befca90e38aaf5","profileName":"Notch","textures":{"SKIN":{"url":"skin"}}}"#;
+        let property = Property {
+            name: TEXTURES_PROPERTY.to_owned(),
+            value: STANDARD.encode(json),
+            signature: None,
+        };
+
+        let textures = property.textures().unwrap();
+        assert_eq!(
+            textures.skin,
+            Some(Skin {
+                url: "skin".to_owned(),
+                slim: false,
+            })
+        );
+        assert!(textures.cape.is_none());
+        assert!(!textures.signature_required);
+    }
+
+    #[test]
+    fn invalid_textures() {
+        let mut property = signed_textures();
+
+        property.value = "not base64!".to_owned();
+        assert!(matches!(property.textures(), Err(ProfileError::Base64(_))));
+
+        property.value = STANDARD.encode("{\"timestamp\":");
+        assert!(matches!(
+            property.textures(),
+            Err(ProfileError::InvalidTextures(_))
+        ));
+    }
+
+    #[test]
+    fn verify_signed_property() {
+        let key = YggdrasilKey::from_pem(PUBLIC_KEY).unwrap();
+
+        key.verify(&signed_textures()).unwrap();
+        key.verify_profile(&profile()).unwrap();
+        key.verify_profile(&GameProfile::new(id(), "Notch"))
+            .unwrap();
+    }
+
+    #[test]
+    fn reject_tampered_property() {
+        let key = YggdrasilKey::from_pem(PUBLIC_KEY).unwrap();
+
+        let mut tampered = signed_textures();
+        tampered.value = Textures {
+            profile_name: "jeb_".to_owned(),
+            ..tampered.textures().unwrap()
+        }
+        .to_property()
+        .value;
+        assert!(matches!(
+            key.verify(&tampered),
+            Err(ProfileError::InvalidSignature(_))
+        ));
+
+        let mut profile = profile();
+        profile.set_property(tampered);
+        assert!(matches!(
+            key.verify_profile(&profile),
+            Err(ProfileError::InvalidSignature(_))
+        ));
+
+        let mut unsigned = signed_textures();
+        unsigned.signature = None;
+        assert!(matches!(
+            key.verify(&unsigned),
+            Err(ProfileError::MissingSignature(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_public_key() {
+        assert!(matches!(
+            YggdrasilKey::from_pem("-----BEGIN PUBLIC KEY-----\n-----END PUBLIC KEY-----\n"),
+            Err(ProfileError::InvalidPublicKey(_))
+        ));
+    }
+
+    #[test]
+    fn v3_login_success() {
+        use crate::versions::v5;
+
+        let old = v3::packets::login::s2c::LoginSuccess::new(id(), "Notch".to_owned(), 4);
+        assert_eq!(old.uuid, "069a79f444e94726a5befca90e38aaf5");
+        assert_eq!(
+            GameProfile::try_from(old).unwrap(),
+            GameProfile::new(id(), "Notch")
+        );
+
+        let new = v3::packets::login::s2c::LoginSuccess::new(
+            id(),
+            "Notch".to_owned(),
+            v5::PROTOCOL_VERSION,
+        );
+        assert_eq!(new.uuid, "069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        assert_eq!(
+            GameProfile::try_from(new).unwrap(),
+            GameProfile::new(id(), "Notch")
+        );
+
+        let invalid = v3::packets::login::s2c::LoginSuccess {
+            uuid: "Notch".to_owned(),
+            username: "Notch".to_owned(),
+        };
+        assert!(matches!(
+            GameProfile::try_from(invalid),
+            Err(ProfileError::InvalidUuid(_))
+        ));
+    }
+
+    #[test]
+    fn v735_login_success() {
+        let login_success = v735::packets::login::s2c::LoginSuccess::from(profile());
+        assert_eq!(login_success.uuid, id());
+        assert_eq!(login_success.username, "Notch");
+
+        // no properties before 1.19
+        assert_eq!(
+            GameProfile::from(login_success),
+            GameProfile::new(id(), "Notch")
+        );
+    }
+
+    #[test]
+    fn v759_login_success() {
+        let login_success = v759::packets::login::s2c::LoginSuccess::from(profile());
+        assert_eq!(login_success.properties.len(), 1);
+        assert_eq!(
+            login_success.properties[0].signature.as_deref(),
+            Some(SIGNATURE)
+        );
+
+        assert_eq!(GameProfile::from(login_success), profile());
+    }
+
+    #[test]
+    fn v766_login_success() {
+        let login_success = v766::packets::login::s2c::LoginSuccess::from(profile());
+        assert!(!login_success.strict_error_handling);
+        assert_eq!(login_success.properties.len(), 1);
+
+        assert_eq!(GameProfile::from(login_success), profile());
+    }
+}
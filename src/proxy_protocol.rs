@@ -0,0 +1,777 @@
+// https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt
+
+use std::{
+    convert::TryInto,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str,
+};
+
+use bytes::BufMut;
+
+pub const V1_SIGNATURE: &[u8] = b"PROXY ";
+pub const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Longest possible v1 header, including the trailing CRLF.
+pub const V1_MAX_LENGTH: usize = 107;
+
+const V2_HEADER_LENGTH: usize = 16;
+const V2_UNIX_ADDRESS_LENGTH: usize = 108;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProxyHeaderError {
+    #[error("v1 header is longer than {} bytes", V1_MAX_LENGTH)]
+    V1TooLong,
+
+    #[error("malformed v1 header: {0:?}")]
+    MalformedV1(String),
+
+    #[error("unsupported v2 version: {0}")]
+    UnsupportedV2Version(u8),
+
+    #[error("unknown v2 command: {0}")]
+    UnknownV2Command(u8),
+
+    #[error("v2 address block too short for address family {0:#x}")]
+    V2AddressTooShort(u8),
+
+    #[error("v2 tlv overruns header")]
+    MalformedTlv,
+
+    #[error("v2 tlv value is {0} bytes, over the 65535 byte limit")]
+    TlvTooLong(usize),
+
+    #[error("unix socket path is {0} bytes, over the 108 byte limit")]
+    UnixPathTooLong(usize),
+
+    #[error("v2 address block is {0} bytes, over the 65535 byte limit")]
+    V2TooLong(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyVersion {
+    V1,
+    V2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyCommand {
+    /// Connection was made by the proxy itself (e.g. a health check), addresses should be ignored.
+    Local,
+    Proxy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProxyAddresses {
+    Unspecified,
+    Inet {
+        source: SocketAddr,
+        destination: SocketAddr,
+    },
+    Unix {
+        source: Vec<u8>,
+        destination: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlv {
+    pub kind: u8,
+    pub value: Vec<u8>,
+}
+
+impl Tlv {
+    pub const ALPN: u8 = 0x01;
+    pub const AUTHORITY: u8 = 0x02;
+    pub const CRC32C: u8 = 0x03;
+    pub const NOOP: u8 = 0x04;
+    pub const UNIQUE_ID: u8 = 0x05;
+    pub const SSL: u8 = 0x20;
+    pub const NETNS: u8 = 0x30;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+    pub version: ProxyVersion,
+    pub command: ProxyCommand,
+    pub addresses: ProxyAddresses,
+
+    /// Always empty for v1 headers.
+    pub tlvs: Vec<Tlv>,
+}
+
+#[derive(Debug)]
+pub enum ParsedProxyHeader {
+    /// The data doesn't start with a proxy header signature.
+    None,
+
+    /// The data could be a proxy header, but more is needed to tell.
+    Incomplete,
+
+    /// A complete header and the number of bytes it took up.
+    Header(ProxyHeader, usize),
+}
+
+impl ProxyHeader {
+    pub fn new(version: ProxyVersion, source: SocketAddr, destination: SocketAddr) -> Self {
+        ProxyHeader {
+            version,
+            command: ProxyCommand::Proxy,
+            addresses: ProxyAddresses::Inet {
+                source,
+                destination,
+            },
+            tlvs: Vec::new(),
+        }
+    }
+
+    pub fn source(&self) -> Option<SocketAddr> {
+        match (&self.command, &self.addresses) {
+            (ProxyCommand::Proxy, ProxyAddresses::Inet { source, .. }) => Some(*source),
+            _ => None,
+        }
+    }
+
+    pub fn destination(&self) -> Option<SocketAddr> {
+        match (&self.command, &self.addresses) {
+            (ProxyCommand::Proxy, ProxyAddresses::Inet { destination, .. }) => Some(*destination),
+            _ => None,
+        }
+    }
+
+    pub fn tlv(&self, kind: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find(|tlv| tlv.kind == kind)
+            .map(|tlv| tlv.value.as_slice())
+    }
+
+    /// Parses a v1 or v2 header from the start of `data`.
+    pub fn parse(data: &[u8]) -> Result<ParsedProxyHeader, ProxyHeaderError> {
+        if starts_with_signature(data, V2_SIGNATURE) {
+            if data.len() < V2_SIGNATURE.len() {
+                return Ok(ParsedProxyHeader::Incomplete);
+            }
+
+            Self::parse_v2(data)
+        } else if starts_with_signature(data, V1_SIGNATURE) {
+            if data.len() < V1_SIGNATURE.len() {
+                return Ok(ParsedProxyHeader::Incomplete);
+            }
+
+            Self::parse_v1(data)
+        } else {
+            Ok(ParsedProxyHeader::None)
+        }
+    }
+
+    fn parse_v1(data: &[u8]) -> Result<ParsedProxyHeader, ProxyHeaderError> {
+        let end = match data.windows(2).position(|window| window == b"\r\n") {
+            Some(end) if end + 2 > V1_MAX_LENGTH => return Err(ProxyHeaderError::V1TooLong),
+            Some(end) => end,
+            None if data.len() >= V1_MAX_LENGTH => return Err(ProxyHeaderError::V1TooLong),
+            None => return Ok(ParsedProxyHeader::Incomplete),
+        };
+
+        let line = str::from_utf8(&data[..end]).map_err(|_| {
+            ProxyHeaderError::MalformedV1(String::from_utf8_lossy(&data[..end]).into())
+        })?;
+        let malformed = || ProxyHeaderError::MalformedV1(line.to_owned());
+
+        let mut parts = line.split(' ').skip(1);
+        let addresses = match parts.next() {
+            Some("UNKNOWN") => ProxyAddresses::Unspecified,
+            Some(protocol @ ("TCP4" | "TCP6")) => {
+                let mut next = || parts.next().ok_or_else(malformed);
+                let (source_ip, destination_ip) = (next()?, next()?);
+                let (source_port, destination_port) = (next()?, next()?);
+
+                let parse_ip = |ip: &str| -> Result<IpAddr, ProxyHeaderError> {
+                    let ip = if protocol == "TCP4" {
+                        ip.parse::<Ipv4Addr>().map(IpAddr::from)
+                    } else {
+                        ip.parse::<Ipv6Addr>().map(IpAddr::from)
+                    };
+
+                    ip.map_err(|_| malformed())
+                };
+                let parse_port = |port: &str| port.parse::<u16>().map_err(|_| malformed());
+
+                if parts.next().is_some() {
+                    return Err(malformed());
+                }
+
+                ProxyAddresses::Inet {
+                    source: SocketAddr::new(parse_ip(source_ip)?, parse_port(source_port)?),
+                    destination: SocketAddr::new(
+                        parse_ip(destination_ip)?,
+                        parse_port(destination_port)?,
+                    ),
+                }
+            }
+            _ => return Err(malformed()),
+        };
+
+        let header = ProxyHeader {
+            version: ProxyVersion::V1,
+            command: ProxyCommand::Proxy,
+            addresses,
+            tlvs: Vec::new(),
+        };
+
+        Ok(ParsedProxyHeader::Header(header, end + 2))
+    }
+
+    fn parse_v2(data: &[u8]) -> Result<ParsedProxyHeader, ProxyHeaderError> {
+        if data.len() < V2_HEADER_LENGTH {
+            return Ok(ParsedProxyHeader::Incomplete);
+        }
+
+        let version_command = data[12];
+        let family_protocol = data[13];
+        let length = u16::from_be_bytes([data[14], data[15]]) as usize;
+
+        if version_command >> 4 != 2 {
+            return Err(ProxyHeaderError::UnsupportedV2Version(version_command >> 4));
+        }
+
+        let command = match version_command & 0x0F {
+            0 => ProxyCommand::Local,
+            1 => ProxyCommand::Proxy,
+            other => return Err(ProxyHeaderError::UnknownV2Command(other)),
+        };
+
+        let total_length = V2_HEADER_LENGTH + length;
+        if data.len() < total_length {
+            return Ok(ParsedProxyHeader::Incomplete);
+        }
+
+        let block = &data[V2_HEADER_LENGTH..total_length];
+        let family = family_protocol >> 4;
+        let too_short = || ProxyHeaderError::V2AddressTooShort(family);
+
+        let (addresses, address_length) = match family {
+            // AF_INET
+            0x1 => {
+                let block: &[u8; 12] = block.get(..12).ok_or_else(too_short)?.try_into().unwrap();
+                let source = Ipv4Addr::from([block[0], block[1], block[2], block[3]]);
+                let destination = Ipv4Addr::from([block[4], block[5], block[6], block[7]]);
+
+                let addresses = ProxyAddresses::Inet {
+                    source: SocketAddr::new(
+                        source.into(),
+                        u16::from_be_bytes([block[8], block[9]]),
+                    ),
+                    destination: SocketAddr::new(
+                        destination.into(),
+                        u16::from_be_bytes([block[10], block[11]]),
+                    ),
+                };
+
+                (addresses, 12)
+            }
+
+            // AF_INET6
+            0x2 => {
+                let block = block.get(..36).ok_or_else(too_short)?;
+                let source: [u8; 16] = block[0..16].try_into().unwrap();
+                let destination: [u8; 16] = block[16..32].try_into().unwrap();
+
+                let addresses = ProxyAddresses::Inet {
+                    source: SocketAddr::new(
+                        Ipv6Addr::from(source).into(),
+                        u16::from_be_bytes([block[32], block[33]]),
+                    ),
+                    destination: SocketAddr::new(
+                        Ipv6Addr::from(destination).into(),
+                        u16::from_be_bytes([block[34], block[35]]),
+                    ),
+                };
+
+                (addresses, 36)
+            }
+
+            // AF_UNIX
+            0x3 => {
+                let block = block
+                    .get(..V2_UNIX_ADDRESS_LENGTH * 2)
+                    .ok_or_else(too_short)?;
+                let trim = |path: &[u8]| {
+                    let end = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+                    path[..end].to_vec()
+                };
+
+                let addresses = ProxyAddresses::Unix {
+                    source: trim(&block[..V2_UNIX_ADDRESS_LENGTH]),
+                    destination: trim(&block[V2_UNIX_ADDRESS_LENGTH..]),
+                };
+
+                (addresses, V2_UNIX_ADDRESS_LENGTH * 2)
+            }
+
+            _ => (ProxyAddresses::Unspecified, 0),
+        };
+
+        // anything after the addresses (if the family is known) are tlvs
+        let mut tlvs = Vec::new();
+        let mut rest = if address_length > 0 || family == 0 {
+            &block[address_length..]
+        } else {
+            &[][..]
+        };
+
+        while !rest.is_empty() {
+            if rest.len() < 3 {
+                return Err(ProxyHeaderError::MalformedTlv);
+            }
+
+            let kind = rest[0];
+            let length = u16::from_be_bytes([rest[1], rest[2]]) as usize;
+            let value = rest
+                .get(3..3 + length)
+                .ok_or(ProxyHeaderError::MalformedTlv)?;
+
+            tlvs.push(Tlv {
+                kind,
+                value: value.to_vec(),
+            });
+            rest = &rest[3 + length..];
+        }
+
+        let header = ProxyHeader {
+            version: ProxyVersion::V2,
+            command,
+            addresses,
+            tlvs,
+        };
+
+        Ok(ParsedProxyHeader::Header(header, total_length))
+    }
+
+    /// Writes the header using `self.version`.
+    pub fn write<B: BufMut>(&self, buf: &mut B) -> Result<(), ProxyHeaderError> {
+        match self.version {
+            ProxyVersion::V1 => {
+                self.write_v1(buf);
+                Ok(())
+            }
+            ProxyVersion::V2 => self.write_v2(buf),
+        }
+    }
+
+    /// Writes a v1 header.
+    ///
+    /// v1 can't represent local connections, unix sockets or tlvs, those are written as `UNKNOWN`
+    /// and tlvs are dropped.
+    pub fn write_v1<B: BufMut>(&self, buf: &mut B) {
+        let line = match (&self.command, &self.addresses) {
+            (
+                ProxyCommand::Proxy,
+                ProxyAddresses::Inet {
+                    source,
+                    destination,
+                },
+            ) => {
+                let (source_ip, destination_ip) = same_family(source.ip(), destination.ip());
+                let protocol = if source_ip.is_ipv4() { "TCP4" } else { "TCP6" };
+
+                format!(
+                    "PROXY {} {} {} {} {}\r\n",
+                    protocol,
+                    source_ip,
+                    destination_ip,
+                    source.port(),
+                    destination.port()
+                )
+            }
+            _ => "PROXY UNKNOWN\r\n".to_owned(),
+        };
+
+        buf.put_slice(line.as_bytes());
+    }
+
+    /// Writes a v2 header.
+    ///
+    /// Nothing is written if a length doesn't fit its field.
+    pub fn write_v2<B: BufMut>(&self, buf: &mut B) -> Result<(), ProxyHeaderError> {
+        let mut block = Vec::new();
+
+        let family = match &self.addresses {
+            ProxyAddresses::Unspecified => 0x0,
+            ProxyAddresses::Inet {
+                source,
+                destination,
+            } => match same_family(source.ip(), destination.ip()) {
+                (IpAddr::V4(source_ip), IpAddr::V4(destination_ip)) => {
+                    block.put_slice(&source_ip.octets());
+                    block.put_slice(&destination_ip.octets());
+                    block.put_u16(source.port());
+                    block.put_u16(destination.port());
+                    0x1
+                }
+                (source_ip, destination_ip) => {
+                    block.put_slice(&to_ipv6(source_ip).octets());
+                    block.put_slice(&to_ipv6(destination_ip).octets());
+                    block.put_u16(source.port());
+                    block.put_u16(destination.port());
+                    0x2
+                }
+            },
+            ProxyAddresses::Unix {
+                source,
+                destination,
+            } => {
+                for path in [source, destination] {
+                    if path.len() > V2_UNIX_ADDRESS_LENGTH {
+                        return Err(ProxyHeaderError::UnixPathTooLong(path.len()));
+                    }
+
+                    let mut address = [0; V2_UNIX_ADDRESS_LENGTH];
+                    address[..path.len()].copy_from_slice(path);
+                    block.put_slice(&address);
+                }
+                0x3
+            }
+        };
+
+        for tlv in &self.tlvs {
+            let length: u16 = tlv
+                .value
+                .len()
+                .try_into()
+                .map_err(|_| ProxyHeaderError::TlvTooLong(tlv.value.len()))?;
+
+            block.put_u8(tlv.kind);
+            block.put_u16(length);
+            block.put_slice(&tlv.value);
+        }
+        let length: u16 = block
+            .len()
+            .try_into()
+            .map_err(|_| ProxyHeaderError::V2TooLong(block.len()))?;
+
+        let command = match self.command {
+            ProxyCommand::Local => 0x0,
+            ProxyCommand::Proxy => 0x1,
+        };
+        // only stream (tcp) connections are supported
+        let protocol = if family == 0x0 { 0x0 } else { 0x1 };
+
+        buf.put_slice(V2_SIGNATURE);
+        buf.put_u8(0x20 | command);
+        buf.put_u8(family << 4 | protocol);
+        buf.put_u16(length);
+        buf.put_slice(&block);
+
+        Ok(())
+    }
+}
+
+fn starts_with_signature(data: &[u8], signature: &[u8]) -> bool {
+    let len = data.len().min(signature.len());
+    data[..len] == signature[..len]
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+/// Both addresses need to be in the same family, map to ipv6 if they aren't.
+fn same_family(source: IpAddr, destination: IpAddr) -> (IpAddr, IpAddr) {
+    if source.is_ipv4() == destination.is_ipv4() {
+        (source, destination)
+    } else {
+        (to_ipv6(source).into(), to_ipv6(destination).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_header(data: &[u8]) -> (ProxyHeader, usize) {
+        match ProxyHeader::parse(data).unwrap() {
+            ParsedProxyHeader::Header(header, length) => (header, length),
+            other => panic!("expected a header, got {:?}", other),
+        }
+    }
+
+    fn v2(command: u8, family_protocol: u8, block: &[u8]) -> Vec<u8> {
+        let mut data = V2_SIGNATURE.to_vec();
+        data.push(command);
+        data.push(family_protocol);
+        data.extend_from_slice(&(block.len() as u16).to_be_bytes());
+        data.extend_from_slice(block);
+        data
+    }
+
+    #[test]
+    fn empty_and_unrelated_data() {
+        // could still be either signature
+        assert!(matches!(
+            ProxyHeader::parse(&[]),
+            Ok(ParsedProxyHeader::Incomplete)
+        ));
+        assert!(matches!(
+            ProxyHeader::parse(b"\x10\x00"),
+            Ok(ParsedProxyHeader::None)
+        ));
+        assert!(matches!(
+            ProxyHeader::parse(b"PROXX"),
+            Ok(ParsedProxyHeader::None)
+        ));
+    }
+
+    #[test]
+    fn v1_tcp4() {
+        // examples from the spec
+        let data = b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET /";
+        let (header, length) = parse_header(data);
+
+        assert_eq!(length, data.len() - 5);
+        assert_eq!(header.version, ProxyVersion::V1);
+        assert_eq!(header.source(), Some("192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(
+            header.destination(),
+            Some("192.168.0.11:443".parse().unwrap())
+        );
+
+        let mut written = Vec::new();
+        header.write(&mut written).unwrap();
+        assert_eq!(written, &data[..length]);
+    }
+
+    #[test]
+    fn v1_tcp6_and_unknown() {
+        let (header, _) = parse_header(b"PROXY TCP6 ::1 2001:db8::1 1 65535\r\n");
+        assert_eq!(header.source(), Some("[::1]:1".parse().unwrap()));
+        assert_eq!(
+            header.destination(),
+            Some("[2001:db8::1]:65535".parse().unwrap())
+        );
+
+        let (header, length) = parse_header(b"PROXY UNKNOWN\r\n");
+        assert_eq!(length, 15);
+        assert_eq!(header.addresses, ProxyAddresses::Unspecified);
+        assert_eq!(header.source(), None);
+    }
+
+    #[test]
+    fn v1_length_cap() {
+        // the worst case line from the spec is exactly the limit
+        let worst = format!(
+            "PROXY UNKNOWN {0} {0} 65535 65535\r\n",
+            "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"
+        );
+        assert_eq!(worst.len(), V1_MAX_LENGTH);
+        assert_eq!(parse_header(worst.as_bytes()).1, V1_MAX_LENGTH);
+
+        let longer = worst.replace("UNKNOWN", "UNKNOWN ");
+        assert!(matches!(
+            ProxyHeader::parse(longer.as_bytes()),
+            Err(ProxyHeaderError::V1TooLong)
+        ));
+
+        let no_end = [b"PROXY ".as_slice(), &[b'a'; V1_MAX_LENGTH]].concat();
+        assert!(matches!(
+            ProxyHeader::parse(&no_end[..V1_MAX_LENGTH - 1]),
+            Ok(ParsedProxyHeader::Incomplete)
+        ));
+        assert!(matches!(
+            ProxyHeader::parse(&no_end),
+            Err(ProxyHeaderError::V1TooLong)
+        ));
+    }
+
+    #[test]
+    fn v1_malformed() {
+        for line in [
+            "PROXY TCP4 192.168.0.1 192.168.0.11 56324\r\n",
+            "PROXY TCP4 ::1 ::1 1 1\r\n",
+            "PROXY TCP4 1.1.1.1 1.1.1.1 1 65536\r\n",
+            "PROXY TCP4 1.1.1.1 1.1.1.1 1 1 extra\r\n",
+            "PROXY UDP4 1.1.1.1 1.1.1.1 1 1\r\n",
+        ] {
+            assert!(
+                matches!(
+                    ProxyHeader::parse(line.as_bytes()),
+                    Err(ProxyHeaderError::MalformedV1(_))
+                ),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn v2_tcp4() {
+        let block = [127, 0, 0, 1, 10, 0, 0, 2, 0x1f, 0x90, 0x63, 0xdd];
+        let data = v2(0x21, 0x11, &block);
+        let (header, length) = parse_header(&[&data[..], b"rest"].concat());
+
+        assert_eq!(length, 28);
+        assert_eq!(header.version, ProxyVersion::V2);
+        assert_eq!(header.command, ProxyCommand::Proxy);
+        assert_eq!(header.source(), Some("127.0.0.1:8080".parse().unwrap()));
+        assert_eq!(
+            header.destination(),
+            Some("10.0.0.2:25565".parse().unwrap())
+        );
+
+        let mut written = Vec::new();
+        header.write(&mut written).unwrap();
+        assert_eq!(written, data);
+    }
+
+    #[test]
+    fn v2_tcp6_and_unix() {
+        let mut block = Ipv6Addr::LOCALHOST.octets().to_vec();
+        block.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        block.extend_from_slice(&[0, 1, 0, 2]);
+        let (header, _) = parse_header(&v2(0x21, 0x21, &block));
+        assert_eq!(header.source(), Some("[::1]:1".parse().unwrap()));
+
+        let mut block = vec![0; V2_UNIX_ADDRESS_LENGTH * 2];
+        block[..4].copy_from_slice(b"/src");
+        block[V2_UNIX_ADDRESS_LENGTH..][..4].copy_from_slice(b"/dst");
+        let (header, _) = parse_header(&v2(0x21, 0x31, &block));
+        assert_eq!(
+            header.addresses,
+            ProxyAddresses::Unix {
+                source: b"/src".to_vec(),
+                destination: b"/dst".to_vec()
+            }
+        );
+        assert_eq!(header.source(), None);
+    }
+
+    #[test]
+    fn v2_local_ignores_addresses() {
+        let block = [127, 0, 0, 1, 127, 0, 0, 1, 0, 1, 0, 2];
+        let (header, _) = parse_header(&v2(0x20, 0x11, &block));
+
+        assert_eq!(header.command, ProxyCommand::Local);
+        assert!(matches!(header.addresses, ProxyAddresses::Inet { .. }));
+        assert_eq!(header.source(), None);
+        assert_eq!(header.destination(), None);
+
+        let (header, length) = parse_header(&v2(0x20, 0x00, &[]));
+        assert_eq!(length, V2_HEADER_LENGTH);
+        assert_eq!(header.addresses, ProxyAddresses::Unspecified);
+    }
+
+    #[test]
+    fn v2_truncated() {
+        let data = v2(0x21, 0x11, &[127, 0, 0, 1, 10, 0, 0, 2, 0, 1, 0, 2]);
+
+        for length in 0..data.len() {
+            assert!(
+                matches!(
+                    ProxyHeader::parse(&data[..length]),
+                    Ok(ParsedProxyHeader::Incomplete)
+                ),
+                "{}",
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn v2_bad_header() {
+        let mut bad_signature = v2(0x21, 0x11, &[]);
+        bad_signature[11] = b'X';
+        assert!(matches!(
+            ProxyHeader::parse(&bad_signature),
+            Ok(ParsedProxyHeader::None)
+        ));
+
+        assert!(matches!(
+            ProxyHeader::parse(&v2(0x11, 0x00, &[])),
+            Err(ProxyHeaderError::UnsupportedV2Version(1))
+        ));
+        assert!(matches!(
+            ProxyHeader::parse(&v2(0x22, 0x00, &[])),
+            Err(ProxyHeaderError::UnknownV2Command(2))
+        ));
+        assert!(matches!(
+            ProxyHeader::parse(&v2(0x21, 0x11, &[0; 11])),
+            Err(ProxyHeaderError::V2AddressTooShort(1))
+        ));
+    }
+
+    #[test]
+    fn v2_tlvs() {
+        let mut block = vec![127, 0, 0, 1, 127, 0, 0, 1, 0, 1, 0, 2];
+        block.extend_from_slice(&[Tlv::AUTHORITY, 0, 4]);
+        block.extend_from_slice(b"host");
+        block.extend_from_slice(&[Tlv::NOOP, 0, 0]);
+        let data = v2(0x21, 0x11, &block);
+
+        let (header, _) = parse_header(&data);
+        assert_eq!(header.tlv(Tlv::AUTHORITY), Some(&b"host"[..]));
+        assert_eq!(header.tlv(Tlv::NOOP), Some(&[][..]));
+        assert_eq!(header.tlv(Tlv::ALPN), None);
+
+        let mut written = Vec::new();
+        header.write(&mut written).unwrap();
+        assert_eq!(written, data);
+
+        // a tlv header cut short, and one whose value runs past the block
+        for tail in [&[Tlv::NOOP, 0][..], &[Tlv::NOOP, 0, 5, 1, 2]] {
+            let mut block = block.clone();
+            block.extend_from_slice(tail);
+
+            assert!(matches!(
+                ProxyHeader::parse(&v2(0x21, 0x11, &block)),
+                Err(ProxyHeaderError::MalformedTlv)
+            ));
+        }
+    }
+
+    #[test]
+    fn v2_write_limits() {
+        let address = "127.0.0.1:1".parse().unwrap();
+        let mut header = ProxyHeader::new(ProxyVersion::V2, address, address);
+        header.tlvs.push(Tlv {
+            kind: Tlv::NOOP,
+            value: vec![0; 65536],
+        });
+
+        let mut written = Vec::new();
+        assert!(matches!(
+            header.write(&mut written),
+            Err(ProxyHeaderError::TlvTooLong(65536))
+        ));
+        assert!(written.is_empty());
+
+        header.tlvs[0].value.truncate(65535);
+        assert!(matches!(
+            header.write(&mut written),
+            Err(ProxyHeaderError::V2TooLong(_))
+        ));
+
+        header.addresses = ProxyAddresses::Unix {
+            source: vec![b'a'; 109],
+            destination: Vec::new(),
+        };
+        assert!(matches!(
+            header.write(&mut written),
+            Err(ProxyHeaderError::UnixPathTooLong(109))
+        ));
+    }
+
+    #[test]
+    fn mixed_families_are_mapped() {
+        let header = ProxyHeader::new(
+            ProxyVersion::V1,
+            "127.0.0.1:1".parse().unwrap(),
+            "[::1]:2".parse().unwrap(),
+        );
+
+        let mut written = Vec::new();
+        header.write(&mut written).unwrap();
+        assert_eq!(written, b"PROXY TCP6 ::ffff:127.0.0.1 ::1 1 2\r\n");
+    }
+}
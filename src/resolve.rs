@@ -0,0 +1,420 @@
+//! Resolving server addresses the way the vanilla client does.
+//!
+//! `_minecraft._tcp.<host>` SRV records are looked up first, falling back to the host's own
+//! A/AAAA records. The handshake should still use the address the player typed in.
+
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    str::FromStr,
+    sync::Arc,
+};
+
+use crate::handshake::{Handshake, NextState};
+
+pub const DEFAULT_PORT: u16 = 25565;
+pub const SRV_PREFIX: &str = "_minecraft._tcp.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+}
+
+pub trait Resolver {
+    /// SRV records for `name`, empty if there aren't any.
+    fn srv(&self, name: &str) -> io::Result<Vec<SrvRecord>>;
+
+    /// A and AAAA records for `host`.
+    fn ips(&self, host: &str) -> io::Result<Vec<IpAddr>>;
+}
+
+impl<R: Resolver + ?Sized> Resolver for &R {
+    fn srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+        (**self).srv(name)
+    }
+
+    fn ips(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        (**self).ips(host)
+    }
+}
+
+impl<R: Resolver + ?Sized> Resolver for Arc<R> {
+    fn srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+        (**self).srv(name)
+    }
+
+    fn ips(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        (**self).ips(host)
+    }
+}
+
+/// The os resolver, std can't look up SRV records so they're always skipped.
+///
+/// Enable the `srv` feature for [`DnsResolver`], which does.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn srv(&self, _name: &str) -> io::Result<Vec<SrvRecord>> {
+        Ok(Vec::new())
+    }
+
+    fn ips(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        Ok((host, 0).to_socket_addrs()?.map(|addr| addr.ip()).collect())
+    }
+}
+
+/// Resolver answering from fixed records, for tests and overrides.
+#[derive(Debug, Clone, Default)]
+pub struct StaticResolver {
+    srv: HashMap<String, Vec<SrvRecord>>,
+    ips: HashMap<String, Vec<IpAddr>>,
+}
+
+impl StaticResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `name` is the full record name, including [`SRV_PREFIX`].
+    pub fn with_srv<S: Into<String>>(mut self, name: S, record: SrvRecord) -> Self {
+        self.srv.entry(name.into()).or_default().push(record);
+        self
+    }
+
+    pub fn with_ip<S: Into<String>>(mut self, host: S, ip: IpAddr) -> Self {
+        self.ips.entry(host.into()).or_default().push(ip);
+        self
+    }
+}
+
+impl Resolver for StaticResolver {
+    fn srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+        Ok(self.srv.get(name).cloned().unwrap_or_default())
+    }
+
+    fn ips(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        match self.ips.get(host) {
+            Some(ips) => Ok(ips.clone()),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no records for {}", host),
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "srv")]
+pub use dns::DnsResolver;
+
+#[cfg(feature = "srv")]
+mod dns {
+    use std::{io, net::IpAddr};
+
+    use hickory_resolver::error::{ResolveError, ResolveErrorKind};
+
+    use super::{Resolver, SrvRecord};
+
+    /// Resolver using the system's dns config, with SRV support.
+    pub struct DnsResolver(hickory_resolver::Resolver);
+
+    impl DnsResolver {
+        pub fn from_system_conf() -> io::Result<Self> {
+            hickory_resolver::Resolver::from_system_conf().map(DnsResolver)
+        }
+
+        pub fn from_resolver(resolver: hickory_resolver::Resolver) -> Self {
+            DnsResolver(resolver)
+        }
+    }
+
+    fn to_io_error(err: ResolveError) -> io::Error {
+        io::Error::other(err)
+    }
+
+    impl Resolver for DnsResolver {
+        fn srv(&self, name: &str) -> io::Result<Vec<SrvRecord>> {
+            let lookup = match self.0.srv_lookup(name) {
+                Ok(lookup) => lookup,
+                Err(err) if matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                    return Ok(Vec::new())
+                }
+                Err(err) => return Err(to_io_error(err)),
+            };
+
+            Ok(lookup
+                .iter()
+                .map(|srv| SrvRecord {
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                    port: srv.port(),
+                    target: srv.target().to_utf8().trim_end_matches('.').to_owned(),
+                })
+                .collect())
+        }
+
+        fn ips(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+            let lookup = self.0.lookup_ip(host).map_err(to_io_error)?;
+            Ok(lookup.iter().collect())
+        }
+    }
+}
+
+/// `host[:port]` as typed into the server list, ipv6 addresses need brackets to have a port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerAddress {
+    pub host: String,
+    pub port: u16,
+}
+
+impl ServerAddress {
+    pub fn new<S: Into<String>>(host: S, port: u16) -> Self {
+        ServerAddress {
+            host: host.into(),
+            port,
+        }
+    }
+}
+
+impl FromStr for ServerAddress {
+    type Err = io::Error;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid server address: {:?}", address),
+            )
+        };
+        let parse_port = |port: &str| port.parse::<u16>().map_err(|_| invalid());
+
+        let address = address.trim();
+        let (host, port) = if let Some(rest) = address.strip_prefix('[') {
+            let (host, rest) = rest.split_once(']').ok_or_else(invalid)?;
+            let port = match rest.strip_prefix(':') {
+                Some(port) => parse_port(port)?,
+                None if rest.is_empty() => DEFAULT_PORT,
+                None => return Err(invalid()),
+            };
+            (host, port)
+        } else if address.matches(':').count() > 1 {
+            // a bare ipv6 address
+            (address, DEFAULT_PORT)
+        } else {
+            match address.split_once(':') {
+                Some((host, port)) => (host, parse_port(port)?),
+                None => (address, DEFAULT_PORT),
+            }
+        };
+
+        if host.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(ServerAddress::new(host, port))
+    }
+}
+
+impl fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvedAddress {
+    /// What the player typed in, sent in the handshake.
+    pub address: ServerAddress,
+    /// Where to connect, in the order they should be tried.
+    pub socket_addresses: Vec<SocketAddr>,
+}
+
+impl ResolvedAddress {
+    /// Handshake with the original host and port, like vanilla sends.
+    pub fn handshake(&self, protocol_version: i32, next_state: NextState) -> Handshake {
+        Handshake {
+            protocol_version,
+            server_address: self.address.host.clone(),
+            server_port: self.address.port,
+            next_state,
+            forge: None,
+            bungee_forwarding: None,
+        }
+    }
+}
+
+/// Resolves like vanilla, SRV records are only looked up for hostnames on the default port.
+pub fn resolve<R: Resolver>(resolver: &R, address: ServerAddress) -> io::Result<ResolvedAddress> {
+    if let Ok(ip) = address.host.parse::<IpAddr>() {
+        return Ok(ResolvedAddress {
+            socket_addresses: vec![SocketAddr::new(ip, address.port)],
+            address,
+        });
+    }
+
+    let mut socket_addresses = Vec::new();
+    if address.port == DEFAULT_PORT {
+        // a failed SRV lookup just falls back to the host
+        let mut records = resolver
+            .srv(&format!("{}{}", SRV_PREFIX, address.host))
+            .unwrap_or_default();
+        records.sort_by_key(|record| (record.priority, std::cmp::Reverse(record.weight)));
+
+        for record in records {
+            if let Ok(ips) = resolver.ips(&record.target) {
+                socket_addresses.extend(ips.into_iter().map(|ip| SocketAddr::new(ip, record.port)));
+            }
+        }
+    }
+
+    if socket_addresses.is_empty() {
+        socket_addresses = resolver
+            .ips(&address.host)?
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, address.port))
+            .collect();
+    }
+
+    if socket_addresses.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} has no addresses", address.host),
+        ));
+    }
+
+    Ok(ResolvedAddress {
+        address,
+        socket_addresses,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    const HOST_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    const SRV_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    fn resolver() -> StaticResolver {
+        StaticResolver::new()
+            .with_ip("example.com", HOST_IP)
+            .with_ip("mc.example.net", SRV_IP)
+            .with_srv(
+                "_minecraft._tcp.example.com",
+                SrvRecord {
+                    priority: 0,
+                    weight: 5,
+                    port: 25570,
+                    target: "mc.example.net".to_owned(),
+                },
+            )
+    }
+
+    fn resolve_str(address: &str) -> io::Result<ResolvedAddress> {
+        resolve(&resolver(), address.parse()?)
+    }
+
+    #[test]
+    fn default_port() {
+        let address: ServerAddress = "localhost".parse().unwrap();
+        assert_eq!(address, ServerAddress::new("localhost", DEFAULT_PORT));
+
+        let resolved = resolve(
+            &StaticResolver::new().with_ip("localhost", HOST_IP),
+            address,
+        )
+        .unwrap();
+        assert_eq!(
+            resolved.socket_addresses,
+            [SocketAddr::new(HOST_IP, DEFAULT_PORT)]
+        );
+    }
+
+    #[test]
+    fn explicit_port() {
+        let resolved = resolve_str("example.com:25570").unwrap();
+        assert_eq!(resolved.address, ServerAddress::new("example.com", 25570));
+        // SRV records are only used on the default port
+        assert_eq!(resolved.socket_addresses, [SocketAddr::new(HOST_IP, 25570)]);
+    }
+
+    #[test]
+    fn srv_on_default_port() {
+        let resolved = resolve_str("example.com").unwrap();
+        assert_eq!(resolved.socket_addresses, [SocketAddr::new(SRV_IP, 25570)]);
+
+        // the handshake keeps what was typed in
+        let handshake = resolved.handshake(767, NextState::Login);
+        assert_eq!(handshake.server_address, "example.com");
+        assert_eq!(handshake.server_port, DEFAULT_PORT);
+
+        let resolved = resolve_str("example.com:25565").unwrap();
+        assert_eq!(resolved.socket_addresses, [SocketAddr::new(SRV_IP, 25570)]);
+    }
+
+    #[test]
+    fn srv_order_and_fallback() {
+        let record = |priority, weight, target: &str| SrvRecord {
+            priority,
+            weight,
+            port: 1,
+            target: target.to_owned(),
+        };
+        let resolver = StaticResolver::new()
+            .with_ip("a", IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)))
+            .with_ip("b", IpAddr::V4(Ipv4Addr::new(2, 2, 2, 2)))
+            .with_ip("c", IpAddr::V4(Ipv4Addr::new(3, 3, 3, 3)))
+            .with_srv("_minecraft._tcp.host", record(1, 0, "c"))
+            .with_srv("_minecraft._tcp.host", record(0, 1, "a"))
+            .with_srv("_minecraft._tcp.host", record(0, 9, "b"))
+            .with_srv("_minecraft._tcp.host", record(0, 0, "missing"));
+
+        let resolved = resolve(&resolver, ServerAddress::new("host", DEFAULT_PORT)).unwrap();
+        let ips: Vec<_> = resolved
+            .socket_addresses
+            .iter()
+            .map(|address| address.ip().to_string())
+            .collect();
+        assert_eq!(ips, ["2.2.2.2", "1.1.1.1", "3.3.3.3"]);
+
+        // no SRV records and no ips
+        assert!(resolve_str("unknown.com").is_err());
+    }
+
+    #[test]
+    fn ipv6_literals() {
+        let resolver = StaticResolver::new();
+
+        for (address, port) in [
+            ("::1", DEFAULT_PORT),
+            ("[::1]", DEFAULT_PORT),
+            ("[::1]:25570", 25570),
+        ]
+        .iter()
+        {
+            let address: ServerAddress = address.parse().unwrap();
+            assert_eq!(address, ServerAddress::new("::1", *port));
+            assert_eq!(address.to_string(), format!("[::1]:{}", port));
+
+            // literals are never looked up
+            let resolved = resolve(&resolver, address).unwrap();
+            assert_eq!(
+                resolved.socket_addresses,
+                [SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), *port)]
+            );
+        }
+
+        assert!("[::1".parse::<ServerAddress>().is_err());
+        assert!("[::1]25565".parse::<ServerAddress>().is_err());
+    }
+}
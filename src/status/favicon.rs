@@ -0,0 +1,210 @@
+//! Status favicons, vanilla only shows 64x64 pngs sent as a data uri.
+
+use std::{convert::TryInto, fs, io, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+pub const DATA_URI_PREFIX: &str = "data:image/png;base64,";
+pub const SIZE: u32 = 64;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+#[derive(Debug, thiserror::Error)]
+pub enum FaviconError {
+    #[error("favicon doesn't start with {DATA_URI_PREFIX:?}")]
+    NotDataUri,
+
+    #[error("invalid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+
+    #[error("favicon isn't a png")]
+    NotPng,
+
+    #[error("favicon is {width}x{height}, it must be {SIZE}x{SIZE}")]
+    WrongSize { width: u32, height: u32 },
+
+    #[error("couldn't read favicon: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// A png the client will accept as a favicon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Favicon {
+    png: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl Favicon {
+    pub fn from_png(png: Vec<u8>) -> Result<Self, FaviconError> {
+        let (width, height) = png_dimensions(&png).ok_or(FaviconError::NotPng)?;
+        if width != SIZE || height != SIZE {
+            return Err(FaviconError::WrongSize { width, height });
+        }
+
+        Ok(Favicon { png, width, height })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, FaviconError> {
+        Self::from_png(fs::read(path)?)
+    }
+
+    /// Decodes the `favicon` field of a status response.
+    pub fn decode(data_uri: &str) -> Result<Self, FaviconError> {
+        let base64 = data_uri
+            .strip_prefix(DATA_URI_PREFIX)
+            .ok_or(FaviconError::NotDataUri)?;
+
+        // old servers wrap the base64 every 76 characters, vanilla doesn't mind
+        let base64: String = base64
+            .chars()
+            .filter(|c| !matches!(c, '\r' | '\n'))
+            .collect();
+
+        Self::from_png(STANDARD.decode(base64)?)
+    }
+
+    /// The data uri to put in the `favicon` field of a status response.
+    pub fn encode(&self) -> String {
+        format!("{}{}", DATA_URI_PREFIX, STANDARD.encode(&self.png))
+    }
+
+    pub fn png(&self) -> &[u8] {
+        &self.png
+    }
+
+    pub fn into_png(self) -> Vec<u8> {
+        self.png
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+/// Reads the size from the IHDR chunk, which must come first.
+fn png_dimensions(png: &[u8]) -> Option<(u32, u32)> {
+    let rest = png.strip_prefix(&PNG_SIGNATURE)?;
+
+    // length, type, then width & height
+    if rest.len() < 16 || &rest[4..8] != b"IHDR" {
+        return None;
+    }
+
+    let width = u32::from_be_bytes(rest[8..12].try_into().ok()?);
+    let height = u32::from_be_bytes(rest[12..16].try_into().ok()?);
+    Some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fully transparent 64x64 png
+    const DATA_URI: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAEAAAABACAYAAACqaXHeAAAAJ0lEQVR42u3BAQ0AAADCoPdPbQ43oAAAAAAAAAAAAAAAAAAAAIB3A0BAAAGveg7oAAAAAElFTkSuQmCC";
+
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut png = PNG_SIGNATURE.to_vec();
+        png.extend_from_slice(&13u32.to_be_bytes());
+        png.extend_from_slice(b"IHDR");
+        png.extend_from_slice(&width.to_be_bytes());
+        png.extend_from_slice(&height.to_be_bytes());
+        png
+    }
+
+    #[test]
+    fn decode_valid() {
+        let favicon = Favicon::decode(DATA_URI).unwrap();
+
+        assert_eq!((favicon.width(), favicon.height()), (SIZE, SIZE));
+        assert!(favicon.png().starts_with(&PNG_SIGNATURE));
+        assert_eq!(favicon.encode(), DATA_URI);
+    }
+
+    #[test]
+    fn decode_wrapped_base64() {
+        let (prefix, base64) = DATA_URI.split_at(DATA_URI_PREFIX.len());
+        let (start, end) = base64.split_at(76);
+        let wrapped = format!("{}{}\r\n{}", prefix, start, end);
+
+        assert_eq!(
+            Favicon::decode(&wrapped).unwrap(),
+            Favicon::decode(DATA_URI).unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let favicon = Favicon::from_png(png_header(SIZE, SIZE)).unwrap();
+        let decoded = Favicon::decode(&favicon.encode()).unwrap();
+
+        assert_eq!(decoded, favicon);
+        assert_eq!(decoded.into_png(), png_header(SIZE, SIZE));
+    }
+
+    #[test]
+    fn wrong_prefix() {
+        let jpeg = DATA_URI.replace("image/png", "image/jpeg");
+        assert!(matches!(
+            Favicon::decode(&jpeg),
+            Err(FaviconError::NotDataUri)
+        ));
+        assert!(matches!(
+            Favicon::decode(&DATA_URI[DATA_URI_PREFIX.len()..]),
+            Err(FaviconError::NotDataUri)
+        ));
+    }
+
+    #[test]
+    fn bad_base64() {
+        let uri = format!("{}not base64!", DATA_URI_PREFIX);
+        assert!(matches!(
+            Favicon::decode(&uri),
+            Err(FaviconError::Base64(_))
+        ));
+    }
+
+    #[test]
+    fn not_png() {
+        let uri = format!("{}{}", DATA_URI_PREFIX, STANDARD.encode(b"GIF89a"));
+        assert!(matches!(Favicon::decode(&uri), Err(FaviconError::NotPng)));
+
+        let mut png = png_header(SIZE, SIZE);
+        png[0] = 0;
+        assert!(matches!(Favicon::from_png(png), Err(FaviconError::NotPng)));
+    }
+
+    #[test]
+    fn truncated_ihdr() {
+        let mut png = png_header(SIZE, SIZE);
+        png.truncate(png.len() - 1);
+        assert!(matches!(Favicon::from_png(png), Err(FaviconError::NotPng)));
+
+        // another chunk where IHDR should be
+        let mut png = png_header(SIZE, SIZE);
+        png[12..16].copy_from_slice(b"IDAT");
+        assert!(matches!(Favicon::from_png(png), Err(FaviconError::NotPng)));
+    }
+
+    #[test]
+    fn wrong_size() {
+        assert!(matches!(
+            Favicon::from_png(png_header(128, 64)),
+            Err(FaviconError::WrongSize {
+                width: 128,
+                height: 64
+            })
+        ));
+        assert!(matches!(
+            Favicon::from_png(png_header(64, 32)),
+            Err(FaviconError::WrongSize {
+                width: 64,
+                height: 32
+            })
+        ));
+    }
+}
@@ -0,0 +1,597 @@
+//! Typed status (server list ping) response.
+// https://wiki.vg/Server_List_Ping#Status_Response
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use crate::versions::{v3, v759};
+
+pub mod favicon;
+
+use favicon::{Favicon, FaviconError};
+
+/// Parsing is lenient, fields that are missing or have the wrong type end up as `None`/defaults
+/// instead of failing, real-world servers (and plugins) send all sorts of things.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStatus {
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub version: Option<Version>,
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub players: Option<Players>,
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<Description>,
+    /// `data:image/png;base64,...`
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub favicon: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub enforces_secure_chat: Option<bool>,
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub previews_chat: Option<bool>,
+    /// Sent by forge 1.13+.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub forge_data: Option<ForgeData>,
+    /// Sent by forge 1.7 - 1.12.
+    #[serde(
+        rename = "modinfo",
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mod_info: Option<ModInfo>,
+
+    /// Anything else the server sent.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Version {
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub name: String,
+    #[serde(default, deserialize_with = "lenient_i32")]
+    pub protocol: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Players {
+    #[serde(default, deserialize_with = "lenient_i32")]
+    pub max: i32,
+    #[serde(default, deserialize_with = "lenient_i32")]
+    pub online: i32,
+    #[serde(
+        default,
+        deserialize_with = "lenient_vec",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub sample: Vec<PlayerSample>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerSample {
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub name: String,
+    /// Nil if it's invalid, servers often use the sample for extra lines of text.
+    #[serde(default, deserialize_with = "lenient_uuid")]
+    pub id: Uuid,
+}
+
+/// The motd, either a text component or a plain (legacy formatted) string.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Description {
+    Legacy(String),
+    Component(Value),
+}
+
+impl Description {
+    /// The text without any json formatting, legacy `§` codes are kept.
+    pub fn to_plain(&self) -> String {
+        fn push_component(component: &Value, plain: &mut String) {
+            match component {
+                Value::String(text) => plain.push_str(text),
+                Value::Array(components) => components
+                    .iter()
+                    .for_each(|component| push_component(component, plain)),
+                Value::Object(object) => {
+                    if let Some(text) = object.get("text") {
+                        push_component(text, plain);
+                    }
+                    if let Some(extra) = object.get("extra") {
+                        push_component(extra, plain);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match self {
+            Self::Legacy(text) => text.clone(),
+            Self::Component(component) => {
+                let mut plain = String::new();
+                push_component(component, &mut plain);
+                plain
+            }
+        }
+    }
+}
+
+impl From<String> for Description {
+    fn from(text: String) -> Self {
+        Self::Legacy(text)
+    }
+}
+
+impl From<&str> for Description {
+    fn from(text: &str) -> Self {
+        Self::Legacy(text.to_owned())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForgeData {
+    #[serde(default, deserialize_with = "lenient_vec")]
+    pub channels: Vec<ForgeChannel>,
+    #[serde(default, deserialize_with = "lenient_vec")]
+    pub mods: Vec<ForgeMod>,
+    #[serde(default, deserialize_with = "lenient_i32")]
+    pub fml_network_version: i32,
+    /// Forge 1.18+ cuts the lists short if they're too long, `d` holds the full data.
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub truncated: bool,
+    /// Forge 1.18+ compressed channel and mod data.
+    #[serde(
+        default,
+        deserialize_with = "lenient",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub d: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForgeChannel {
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub res: String,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub version: String,
+    #[serde(default, deserialize_with = "lenient_bool")]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForgeMod {
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub mod_id: String,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub modmarker: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModInfo {
+    #[serde(rename = "type", default, deserialize_with = "lenient_string")]
+    pub kind: String,
+    #[serde(default, deserialize_with = "lenient_vec")]
+    pub mod_list: Vec<ModInfoEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModInfoEntry {
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub modid: String,
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub version: String,
+}
+
+impl ServerStatus {
+    pub fn builder() -> ServerStatusBuilder {
+        ServerStatusBuilder::default()
+    }
+
+    /// Only fails if `json` isn't a json object.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// `None` if there's no favicon.
+    pub fn decode_favicon(&self) -> Option<Result<Favicon, FaviconError>> {
+        self.favicon.as_deref().map(Favicon::decode)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("status always serializes")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ServerStatusBuilder {
+    status: ServerStatus,
+}
+
+impl ServerStatusBuilder {
+    pub fn version<S: Into<String>>(mut self, name: S, protocol: i32) -> Self {
+        self.status.version = Some(Version {
+            name: name.into(),
+            protocol,
+        });
+        self
+    }
+
+    pub fn players(mut self, online: i32, max: i32) -> Self {
+        let players = self.status.players.get_or_insert_with(Players::default);
+        players.online = online;
+        players.max = max;
+        self
+    }
+
+    /// Adds a player to the hover sample.
+    pub fn sample<S: Into<String>>(mut self, name: S, id: Uuid) -> Self {
+        let players = self.status.players.get_or_insert_with(Players::default);
+        players.sample.push(PlayerSample {
+            name: name.into(),
+            id,
+        });
+        self
+    }
+
+    pub fn description<D: Into<Description>>(mut self, description: D) -> Self {
+        self.status.description = Some(description.into());
+        self
+    }
+
+    pub fn favicon(mut self, favicon: &Favicon) -> Self {
+        self.status.favicon = Some(favicon.encode());
+        self
+    }
+
+    pub fn enforces_secure_chat(mut self, enforces_secure_chat: bool) -> Self {
+        self.status.enforces_secure_chat = Some(enforces_secure_chat);
+        self
+    }
+
+    pub fn previews_chat(mut self, previews_chat: bool) -> Self {
+        self.status.previews_chat = Some(previews_chat);
+        self
+    }
+
+    pub fn forge_data(mut self, forge_data: ForgeData) -> Self {
+        self.status.forge_data = Some(forge_data);
+        self
+    }
+
+    pub fn mod_info(mut self, mod_info: ModInfo) -> Self {
+        self.status.mod_info = Some(mod_info);
+        self
+    }
+
+    pub fn build(self) -> ServerStatus {
+        self.status
+    }
+}
+
+//
+// lenient deserializers
+//
+
+fn lenient<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(T::deserialize(value).ok())
+}
+
+fn lenient_vec<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let values = match Value::deserialize(deserializer)? {
+        Value::Array(values) => values,
+        _ => return Ok(Vec::new()),
+    };
+
+    // skip invalid entries rather than dropping the whole list
+    Ok(values
+        .into_iter()
+        .filter_map(|value| T::deserialize(value).ok())
+        .collect())
+}
+
+fn lenient_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(string) => string,
+        Value::Null => String::new(),
+        other => other.to_string(),
+    })
+}
+
+fn lenient_i32<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+    let number = match Value::deserialize(deserializer)? {
+        Value::Number(number) => number.as_f64(),
+        Value::String(string) => string.trim().parse().ok(),
+        _ => None,
+    };
+
+    // `as` saturates, and turns NaN into 0
+    Ok(number.map_or(0, |number| number as i32))
+}
+
+fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Bool(value) => value,
+        Value::String(string) => string == "true",
+        _ => false,
+    })
+}
+
+fn lenient_uuid<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Uuid, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(string) => Uuid::try_parse(&string).unwrap_or_default(),
+        _ => Uuid::nil(),
+    })
+}
+
+//
+// packet conversions
+//
+
+impl v3::packets::status::s2c::Response {
+    pub fn status(&self) -> Result<ServerStatus, serde_json::Error> {
+        ServerStatus::from_json(&self.response)
+    }
+}
+
+impl From<&ServerStatus> for v3::packets::status::s2c::Response {
+    fn from(status: &ServerStatus) -> Self {
+        Self {
+            response: status.to_json(),
+        }
+    }
+}
+
+impl v759::packets::status::s2c::StatusResponse {
+    pub fn status(&self) -> Result<ServerStatus, serde_json::Error> {
+        ServerStatus::from_json(&self.response)
+    }
+}
+
+impl From<&ServerStatus> for v759::packets::status::s2c::StatusResponse {
+    fn from(status: &ServerStatus) -> Self {
+        Self {
+            response: status.to_json(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn vanilla_status() {
+        let status = ServerStatus::from_json(
+            r#"{
+                "version": {"name": "1.21.1", "protocol": 767},
+                "players": {
+                    "max": 20,
+                    "online": 1,
+                    "sample": [{"name": "Notch", "id": "069a79f4-44e9-4726-a5be-fca90e38aaf5"}]
+                },
+                "description": {"text": "A ", "extra": [{"text": "Minecraft", "bold": true}, " Server"]},
+                "enforcesSecureChat": true,
+                "previewsChat": false
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            status.version,
+            Some(Version {
+                name: "1.21.1".to_owned(),
+                protocol: 767,
+            })
+        );
+        let players = status.players.unwrap();
+        assert_eq!((players.online, players.max), (1, 20));
+        assert_eq!(
+            players.sample[0].id,
+            Uuid::try_parse("069a79f444e94726a5befca90e38aaf5").unwrap()
+        );
+        assert!(matches!(
+            status.description,
+            Some(Description::Component(_))
+        ));
+        assert_eq!(status.description.unwrap().to_plain(), "A Minecraft Server");
+        assert_eq!(status.enforces_secure_chat, Some(true));
+        assert_eq!(status.previews_chat, Some(false));
+        assert!(status.extra.is_empty());
+    }
+
+    #[test]
+    fn legacy_description() {
+        let status = ServerStatus::from_json(r#"{"description": "§aA Minecraft Server"}"#).unwrap();
+
+        assert_eq!(
+            status.description,
+            Some(Description::Legacy("§aA Minecraft Server".to_owned()))
+        );
+        assert_eq!(
+            status.description.unwrap().to_plain(),
+            "§aA Minecraft Server"
+        );
+    }
+
+    #[test]
+    fn missing_fields() {
+        let status = ServerStatus::from_json(r#"{"description": {"text": ""}}"#).unwrap();
+
+        assert!(status.version.is_none());
+        assert!(status.players.is_none());
+        assert!(status.favicon.is_none());
+        assert!(status.enforces_secure_chat.is_none());
+
+        let status =
+            ServerStatus::from_json(r#"{"players": {"online": 3}, "version": {}}"#).unwrap();
+        assert_eq!(status.players.unwrap().max, 0);
+        assert_eq!(status.version.unwrap().name, "");
+
+        assert!(ServerStatus::from_json("[]").is_err());
+    }
+
+    #[test]
+    fn wrong_types() {
+        let status = ServerStatus::from_json(
+            r#"{
+                "version": {"name": 1.8, "protocol": "47"},
+                "players": {
+                    "max": 1e10,
+                    "online": null,
+                    "sample": [{"name": "§cline of text", "id": ""}, 5, {"name": "Notch", "id": 7}]
+                },
+                "favicon": 12,
+                "enforcesSecureChat": "yes",
+                "previewsChat": [],
+                "custom": {"a": 1}
+            }"#,
+        )
+        .unwrap();
+
+        let version = status.version.unwrap();
+        assert_eq!(version.name, "1.8");
+        assert_eq!(version.protocol, 47);
+
+        let players = status.players.unwrap();
+        assert_eq!(players.max, i32::MAX);
+        assert_eq!(players.online, 0);
+        assert_eq!(players.sample.len(), 2);
+        assert_eq!(players.sample[0].name, "§cline of text");
+        assert!(players.sample[0].id.is_nil());
+        assert!(players.sample[1].id.is_nil());
+
+        assert!(status.favicon.is_none());
+        assert!(status.enforces_secure_chat.is_none());
+        assert!(status.previews_chat.is_none());
+        assert_eq!(status.extra["custom"], json!({"a": 1}));
+    }
+
+    #[test]
+    fn forge_data() {
+        let status = ServerStatus::from_json(
+            r#"{
+                "description": "A Forge Server",
+                "forgeData": {
+                    "channels": [{"res": "forge:tier_sorting", "version": "1.0", "required": false}],
+                    "mods": [{"modId": "forge", "modmarker": "ANY"}, {"modId": 3}],
+                    "fmlNetworkVersion": 3,
+                    "truncated": "true",
+                    "d": "ȳ"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let forge_data = status.forge_data.unwrap();
+        assert_eq!(forge_data.channels[0].res, "forge:tier_sorting");
+        assert!(!forge_data.channels[0].required);
+        assert_eq!(forge_data.mods.len(), 2);
+        assert_eq!(forge_data.mods[0].mod_id, "forge");
+        assert_eq!(forge_data.mods[1].mod_id, "3");
+        assert_eq!(forge_data.fml_network_version, 3);
+        assert!(forge_data.truncated);
+        assert_eq!(forge_data.d.as_deref(), Some("ȳ"));
+    }
+
+    #[test]
+    fn mod_info() {
+        let status = ServerStatus::from_json(
+            r#"{
+                "description": {"text": "A Forge Server"},
+                "modinfo": {
+                    "type": "FML",
+                    "modList": [{"modid": "mcp", "version": "9.42"}, {"modid": "FML", "version": "8.0.99.99"}]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mod_info = status.mod_info.unwrap();
+        assert_eq!(mod_info.kind, "FML");
+        assert_eq!(
+            mod_info.mod_list[1],
+            ModInfoEntry {
+                modid: "FML".to_owned(),
+                version: "8.0.99.99".to_owned(),
+            }
+        );
+
+        let status = ServerStatus::from_json(r#"{"modinfo": "FML"}"#).unwrap();
+        assert!(status.mod_info.is_none());
+    }
+
+    #[test]
+    fn builder_round_trip() {
+        let status = ServerStatus::builder()
+            .version("1.21.1", 767)
+            .players(1, 20)
+            .sample("Notch", Uuid::from_u128(1))
+            .description(Description::Component(
+                json!({"text": "A Minecraft Server", "color": "green"}),
+            ))
+            .enforces_secure_chat(true)
+            .previews_chat(false)
+            .forge_data(ForgeData {
+                fml_network_version: 3,
+                ..ForgeData::default()
+            })
+            .mod_info(ModInfo {
+                kind: "FML".to_owned(),
+                mod_list: Vec::new(),
+            })
+            .build();
+
+        let json = status.to_json();
+        assert_eq!(ServerStatus::from_json(&json).unwrap(), status);
+
+        let response = v759::packets::status::s2c::StatusResponse::from(&status);
+        assert_eq!(response.status().unwrap(), status);
+
+        let legacy = ServerStatus::builder().description("motd").build();
+        assert_eq!(legacy.to_json(), r#"{"description":"motd"}"#);
+        assert_eq!(ServerStatus::from_json(&legacy.to_json()).unwrap(), legacy);
+    }
+}
@@ -2,11 +2,27 @@ use std::{
     convert::TryInto,
     io::{Read, Write},
     net::{TcpStream, ToSocketAddrs},
+    time::Instant,
 };
 
 use bytes::Bytes;
 
-use crate::{connection, error, packet, role, state};
+mod ping;
+mod probe;
+
+pub use ping::ping;
+pub use probe::probe_version;
+
+use crate::{
+    auth::{
+        server::{ServerEncryption, SharedSecret},
+        EncryptionRequestPacket, EncryptionResponsePacket,
+    },
+    connection, error, packet,
+    proxy_protocol::{ParsedProxyHeader, ProxyHeader},
+    resolve::{self, ResolvedAddress, Resolver, ServerAddress},
+    role, state,
+};
 
 pub struct StdIoConnection<Role, State>
 where
@@ -15,6 +31,7 @@ where
 {
     stream: TcpStream,
     connection: connection::Connection<Role, State>,
+    write_queue: connection::WriteQueue,
 }
 
 pub fn accept_stdio_stream<Role: role::ConnectionRole, State: state::ProtocolState>(
@@ -25,9 +42,47 @@ pub fn accept_stdio_stream<Role: role::ConnectionRole, State: state::ProtocolSta
     Ok(StdIoConnection {
         stream,
         connection: connection::create_connection(),
+        write_queue: connection::WriteQueue::default(),
     })
 }
 
+/// Accepts a stream that may start with a PROXY protocol (v1 or v2) header.
+///
+/// Any data read past the header is kept for the connection.
+/// If the listener is only reachable through a proxy, callers should reject connections
+/// without a header, otherwise clients can spoof their address.
+pub fn accept_stdio_stream_with_proxy_header<
+    Role: role::ConnectionRole,
+    State: state::ProtocolState,
+>(
+    stream: TcpStream,
+) -> Result<(StdIoConnection<Role, State>, Option<ProxyHeader>), error::Error> {
+    let mut connection = accept_stdio_stream(stream)?;
+
+    let mut data = Vec::new();
+    let header = loop {
+        let (header, header_length) = match ProxyHeader::parse(&data)? {
+            ParsedProxyHeader::None => (None, 0),
+            ParsedProxyHeader::Header(header, length) => (Some(header), length),
+            ParsedProxyHeader::Incomplete => {
+                let mut chunk = [0; 512];
+                let len = connection.stream.read(&mut chunk)?;
+                if len == 0 {
+                    return Err(error::Error::StreamShutdown);
+                }
+
+                data.extend_from_slice(&chunk[..len]);
+                continue;
+            }
+        };
+
+        connection.connection.recieve_data(&data[header_length..]);
+        break header;
+    };
+
+    Ok((connection, header))
+}
+
 pub fn connect_stdio_stream<
     Addr: ToSocketAddrs,
     Role: role::ConnectionRole,
@@ -38,13 +93,61 @@ pub fn connect_stdio_stream<
     accept_stdio_stream(TcpStream::connect(addr)?)
 }
 
+/// Resolves the address like vanilla (SRV, then A/AAAA) and connects to the first that works.
+///
+/// Handshake with [`ResolvedAddress::handshake`], so the server sees the address that was typed in
+/// rather than the SRV target.
+pub fn connect_stdio_address<
+    R: Resolver,
+    Role: role::ConnectionRole,
+    State: state::ProtocolState,
+>(
+    resolver: &R,
+    address: ServerAddress,
+) -> Result<(StdIoConnection<Role, State>, ResolvedAddress), error::Error> {
+    let resolved = resolve::resolve(resolver, address)?;
+    let connection = connect_stdio_stream(&resolved.socket_addresses[..])?;
+
+    Ok((connection, resolved))
+}
+
 impl<Role, State> StdIoConnection<Role, State>
 where
     Role: role::ConnectionRole,
     State: state::ProtocolState,
 {
-    // TODO: timeout
     pub fn next_packet(&mut self) -> Result<State::RecvPacket, error::Error>
+    where
+        State: state::RoleStatePackets<Role>,
+        State::RecvPacket: packet::PacketFromIdBody,
+    {
+        self.read_next_packet(None)
+    }
+
+    /// Like [`Self::next_packet`], but fails with [`error::Error::Timeout`] once `deadline` passes.
+    ///
+    /// The stream's read timeout is put back how it was afterwards.
+    pub fn next_packet_before(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<State::RecvPacket, error::Error>
+    where
+        State: state::RoleStatePackets<Role>,
+        State::RecvPacket: packet::PacketFromIdBody,
+    {
+        let read_timeout = self.stream.read_timeout()?;
+        let result = self
+            .read_next_packet(Some(deadline))
+            .map_err(ping::map_timeout);
+        self.stream.set_read_timeout(read_timeout)?;
+
+        result
+    }
+
+    fn read_next_packet(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> Result<State::RecvPacket, error::Error>
     where
         State: state::RoleStatePackets<Role>,
         State::RecvPacket: packet::PacketFromIdBody,
@@ -53,12 +156,24 @@ where
             let event = self.connection.next_event()?;
             match event {
                 connection::Event::NeedMoreData => {
-                    let mut data = [0; 512];
-                    let len = self.stream.read(&mut data)?;
+                    // each read only gets what's left, so a slow server can't stretch the deadline
+                    if let Some(deadline) = deadline {
+                        ping::set_read_timeout(&self.stream, deadline)?;
+                    }
+
+                    if self.connection.recv_buffer_released() {
+                        // block until there's something to read before taking a pooled buffer
+                        self.stream.peek(&mut [0])?;
+
+                        if let Some(deadline) = deadline {
+                            ping::set_read_timeout(&self.stream, deadline)?;
+                        }
+                    }
+
+                    let len = self.connection.recieve_from(&mut self.stream)?;
                     if len == 0 {
                         return Err(error::Error::StreamShutdown);
                     }
-                    self.connection.recieve_data(&data[0..len]);
                 }
                 connection::Event::Packet(packet) => return Ok(packet),
             }
@@ -76,17 +191,96 @@ where
         self.next_packet()?.try_into()
     }
 
+    pub fn expect_next_packet_before<Packet>(
+        &mut self,
+        deadline: Instant,
+    ) -> Result<Packet, error::Error>
+    where
+        Packet: state::RoleStateReadPacket<Role, State>,
+
+        State: state::RoleStatePackets<Role>,
+        State::RecvPacket: packet::PacketFromIdBody,
+        State::RecvPacket: TryInto<Packet, Error = error::Error>,
+    {
+        self.next_packet_before(deadline)?.try_into()
+    }
+
+    /// Writes a PROXY protocol header, this must be done before any packets are sent.
+    pub fn write_proxy_header(&mut self, header: &ProxyHeader) -> Result<(), error::Error> {
+        let mut data = Vec::new();
+        header.write(&mut data)?;
+
+        self.write_queue.push(&data);
+        self.flush()
+    }
+
+    /// Writes the packet immediately, along with anything already queued.
     pub fn write_packet<Packet: state::RoleStateWritePacket<Role, State>>(
         &mut self,
         packet: Packet,
     ) -> Result<(), error::Error> {
-        Ok(self.stream.write_all(&self.connection.send(packet))?)
+        let connection = &mut self.connection;
+        self.write_queue
+            .push_with(|buffer| connection.send_into(packet, buffer))?;
+        self.flush()
     }
 
     pub fn write_id_body(&mut self, id: i32, body: &mut Bytes) -> Result<(), error::Error> {
-        Ok(self
-            .stream
-            .write_all(&self.connection.send_id_body(id, body))?)
+        let connection = &mut self.connection;
+        self.write_queue
+            .push_with(|buffer| connection.send_id_body_into(id, body, buffer))?;
+        self.flush()
+    }
+
+    /// Queues the packet to be written on the next flush.
+    ///
+    /// The queue is flushed straight away if the flush policy's thresholds are reached.
+    pub fn queue_packet<Packet: state::RoleStateWritePacket<Role, State>>(
+        &mut self,
+        packet: Packet,
+    ) -> Result<(), error::Error> {
+        let connection = &mut self.connection;
+        self.write_queue
+            .push_with(|buffer| connection.send_into(packet, buffer))?;
+        self.flush_if_due()
+    }
+
+    pub fn queue_id_body(&mut self, id: i32, body: &mut Bytes) -> Result<(), error::Error> {
+        let connection = &mut self.connection;
+        self.write_queue
+            .push_with(|buffer| connection.send_id_body_into(id, body, buffer))?;
+        self.flush_if_due()
+    }
+
+    /// Writes all queued packets in a single write.
+    pub fn flush(&mut self) -> Result<(), error::Error> {
+        if !self.write_queue.is_empty() {
+            self.stream.write_all(self.write_queue.as_bytes())?;
+            self.write_queue.clear();
+        }
+
+        Ok(self.stream.flush()?)
+    }
+
+    /// Flushes only if the flush policy's thresholds have been reached.
+    ///
+    /// There's no timer here, `max_delay` is only acted on when this is called or something is
+    /// queued, so call it periodically.
+    pub fn flush_if_due(&mut self) -> Result<(), error::Error> {
+        if self.write_queue.should_flush() {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_flush_policy(&mut self, policy: connection::FlushPolicy) {
+        self.write_queue.set_policy(policy)
+    }
+
+    /// Number of bytes waiting to be flushed.
+    pub fn queued_len(&self) -> usize {
+        self.write_queue.len()
     }
 
     pub fn next_state<NextState>(self) -> StdIoConnection<Role, NextState>
@@ -97,6 +291,7 @@ where
         StdIoConnection {
             stream: self.stream,
             connection: self.connection.next_state(),
+            write_queue: self.write_queue,
         }
     }
 
@@ -104,8 +299,11 @@ where
         Ok(self.stream.shutdown(how)?)
     }
 
-    pub fn into_bytes_stream(self) -> (Bytes, TcpStream) {
-        (self.connection.into_bytes(), self.stream)
+    /// Flushes any queued packets first, so none are lost.
+    pub fn into_bytes_stream(mut self) -> Result<(Bytes, TcpStream), error::Error> {
+        self.flush()?;
+
+        Ok((self.connection.into_bytes(), self.stream))
     }
 
     pub fn set_compression_threshold<T: Into<Option<usize>>>(&mut self, threshold: T) {
@@ -115,4 +313,92 @@ where
     pub fn set_encryption_secret(&mut self, secret: &[u8]) {
         self.connection.set_encryption_secret(secret)
     }
+
+    pub fn set_read_size(&mut self, size: usize) {
+        self.connection.set_read_size(size)
+    }
+
+    pub fn set_buffer_pool<T: Into<Option<connection::BufferPool>>>(&mut self, pool: T) {
+        self.connection.set_buffer_pool(pool)
+    }
+}
+
+impl<State> StdIoConnection<role::Server, State>
+where
+    State: state::ProtocolState,
+{
+    /// Sends `EncryptionRequest`, decrypts the client's `EncryptionResponse` and enables encryption.
+    ///
+    /// The returned server hash should be checked with `SessionServer::has_joined`.
+    pub fn exchange_encryption<Request, Response>(
+        &mut self,
+        encryption: &ServerEncryption<'_>,
+    ) -> Result<SharedSecret, error::Error>
+    where
+        Request: EncryptionRequestPacket + state::RoleStateWritePacket<role::Server, State>,
+        Response: EncryptionResponsePacket + state::RoleStateReadPacket<role::Server, State>,
+
+        State: state::RoleStatePackets<role::Server>,
+        State::RecvPacket: packet::PacketFromIdBody,
+        State::RecvPacket: TryInto<Response, Error = error::Error>,
+    {
+        self.write_packet(encryption.request::<Request>())?;
+        let response: Response = self.expect_next_packet()?;
+
+        let shared_secret = encryption.decrypt_response(&response)?;
+        self.set_encryption_secret(&shared_secret.shared_secret);
+
+        Ok(shared_secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::versions::latest::{packets::status::s2c::StatusResponse, states::StatusState};
+
+    #[test]
+    fn next_packet_before_restores_read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut connection: StdIoConnection<role::Server, StatusState> =
+                accept_stdio_stream(stream).unwrap();
+            connection
+                .write_packet(StatusResponse {
+                    response: "{}".to_owned(),
+                })
+                .unwrap();
+
+            // hold the connection open until the client is done
+            let _ = connection.next_packet();
+        });
+
+        let stream = TcpStream::connect(address).unwrap();
+        let read_timeout = Some(Duration::from_secs(7));
+        stream.set_read_timeout(read_timeout).unwrap();
+        let mut connection: StdIoConnection<role::Client, StatusState> =
+            accept_stdio_stream(stream).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        connection
+            .expect_next_packet_before::<StatusResponse>(deadline)
+            .unwrap();
+        assert_eq!(connection.stream.read_timeout().unwrap(), read_timeout);
+
+        let deadline = Instant::now() + Duration::from_millis(100);
+        assert!(matches!(
+            connection.next_packet_before(deadline),
+            Err(error::Error::Timeout)
+        ));
+        assert_eq!(connection.stream.read_timeout().unwrap(), read_timeout);
+    }
 }
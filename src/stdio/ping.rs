@@ -0,0 +1,265 @@
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error, handshake,
+    ping::{self, Ping, PingError},
+    role,
+    versions::latest::{
+        self,
+        packets::status::{
+            c2s::{PingRequest, StatusRequest},
+            s2c::{PingResponse, StatusResponse},
+        },
+        states,
+    },
+};
+
+use super::{accept_stdio_stream, StdIoConnection};
+
+/// Pings the server, falling back to the legacy ping if it doesn't speak the modern status.
+///
+/// `protocol_version` defaults to the latest, `timeout` covers the whole ping, resolving the host
+/// included.
+pub fn ping(
+    host: &str,
+    port: u16,
+    protocol_version: Option<i32>,
+    timeout: Duration,
+) -> Result<Ping, error::Error> {
+    let deadline = Instant::now() + timeout;
+    let addresses = resolve(host, port, deadline)?;
+    let protocol_version = protocol_version.unwrap_or(latest::PROTOCOL_VERSION);
+
+    let stream = connect(&addresses, deadline)?;
+    match modern_ping(stream, host, port, protocol_version, deadline) {
+        Ok(ping) => Ok(ping),
+        Err(error::Error::Timeout) => Err(error::Error::Timeout),
+        Err(err) => {
+            let stream = connect(&addresses, deadline)?;
+            // the modern error says more about what went wrong
+            legacy_ping(stream, host, port, deadline).map_err(|_| err)
+        }
+    }
+}
+
+pub(super) fn remaining(deadline: Instant) -> Result<Duration, error::Error> {
+    match deadline.checked_duration_since(Instant::now()) {
+        Some(remaining) if remaining > Duration::ZERO => Ok(remaining),
+        _ => Err(error::Error::Timeout),
+    }
+}
+
+/// Read & write timeouts show up as `WouldBlock` or `TimedOut` depending on the platform.
+pub(super) fn map_timeout(err: error::Error) -> error::Error {
+    match &err {
+        error::Error::UnexpectedDisconect(io_err) | error::Error::OtherIo(io_err)
+            if matches!(
+                io_err.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            error::Error::Timeout
+        }
+        _ => err,
+    }
+}
+
+/// Only bounds a single read, it has to be set again before each one.
+pub(super) fn set_read_timeout(stream: &TcpStream, deadline: Instant) -> Result<(), error::Error> {
+    stream.set_read_timeout(Some(remaining(deadline)?))?;
+    Ok(())
+}
+
+/// Only bounds a single write, the packets sent while pinging are small enough to go in one.
+pub(super) fn set_write_timeout(stream: &TcpStream, deadline: Instant) -> Result<(), error::Error> {
+    stream.set_write_timeout(Some(remaining(deadline)?))?;
+    Ok(())
+}
+
+/// Resolves on another thread, the system resolver can't be given a timeout.
+///
+/// The thread is left to finish on its own if the deadline passes.
+pub(super) fn resolve(
+    host: &str,
+    port: u16,
+    deadline: Instant,
+) -> Result<Vec<SocketAddr>, error::Error> {
+    let remaining = remaining(deadline)?;
+
+    let (sender, receiver) = mpsc::channel();
+    let host = host.to_owned();
+    thread::spawn(move || {
+        let addresses = (host.as_str(), port)
+            .to_socket_addrs()
+            .map(|addresses| addresses.collect());
+        // the receiver is gone if it timed out
+        let _ = sender.send(addresses);
+    });
+
+    match receiver.recv_timeout(remaining) {
+        Ok(addresses) => Ok(addresses?),
+        Err(_) => Err(error::Error::Timeout),
+    }
+}
+
+pub(super) fn connect(
+    addresses: &[SocketAddr],
+    deadline: Instant,
+) -> Result<TcpStream, error::Error> {
+    let mut last_err = None;
+    for address in addresses {
+        match TcpStream::connect_timeout(address, remaining(deadline)?) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    Err(map_timeout(
+        last_err
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host has no addresses"))
+            .into(),
+    ))
+}
+
+fn modern_ping(
+    stream: TcpStream,
+    host: &str,
+    port: u16,
+    protocol_version: i32,
+    deadline: Instant,
+) -> Result<Ping, error::Error> {
+    let mut connection: StdIoConnection<role::Client, handshake::HandshakingState> =
+        accept_stdio_stream(stream)?;
+
+    set_write_timeout(&connection.stream, deadline)?;
+    connection
+        .write_packet(ping::status_handshake(host, port, protocol_version))
+        .map_err(map_timeout)?;
+
+    let mut connection = connection.next_state::<states::StatusState>();
+
+    let start = Instant::now();
+    set_write_timeout(&connection.stream, deadline)?;
+    connection
+        .write_packet(StatusRequest)
+        .map_err(map_timeout)?;
+    let response: StatusResponse = connection.expect_next_packet_before(deadline)?;
+    let status_latency = start.elapsed();
+
+    let status = response.status().map_err(PingError::from)?;
+
+    // some servers close the connection instead of answering the ping, or answer it wrong
+    let payload = ping::ping_payload();
+    let start = Instant::now();
+    let latency = set_write_timeout(&connection.stream, deadline)
+        .and_then(|_| connection.write_packet(PingRequest { payload }))
+        .and_then(|_| connection.expect_next_packet_before::<PingResponse>(deadline))
+        .ok()
+        .filter(|pong| pong.payload == payload)
+        .map_or(status_latency, |_| start.elapsed());
+
+    Ok(Ping {
+        status,
+        raw_status: Some(response.response),
+        latency,
+        legacy: false,
+    })
+}
+
+fn legacy_ping(
+    mut stream: TcpStream,
+    host: &str,
+    port: u16,
+    deadline: Instant,
+) -> Result<Ping, error::Error> {
+    set_write_timeout(&stream, deadline)?;
+
+    let start = Instant::now();
+    stream
+        .write_all(&ping::legacy_request(host, port))
+        .map_err(|err| map_timeout(err.into()))?;
+
+    let mut data = Vec::new();
+    loop {
+        if let Some(status) = ping::parse_legacy_response(&data)? {
+            return Ok(Ping {
+                status,
+                raw_status: None,
+                latency: start.elapsed(),
+                legacy: true,
+            });
+        }
+
+        let mut chunk = [0; 512];
+        set_read_timeout(&stream, deadline)?;
+        let len = stream
+            .read(&mut chunk)
+            .map_err(|err| map_timeout(err.into()))?;
+        if len == 0 {
+            return Err(error::Error::StreamShutdown);
+        }
+
+        data.extend_from_slice(&chunk[..len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ping::test_servers::{legacy_server, slow_server, status_server, LEGACY_MOTD},
+        status::Description,
+    };
+
+    #[test]
+    fn keeps_raw_status() {
+        let response = r#"{ "version": {"name": "1.21.1", "protocol": 767}, "extra": 1 }"#;
+        let port = status_server(response, 0);
+
+        let ping = ping("127.0.0.1", port, None, Duration::from_secs(5)).unwrap();
+        assert_eq!(ping.raw_status.as_deref(), Some(response));
+        assert_eq!(ping.status.version.unwrap().protocol, 767);
+        assert!(ping.latency >= Duration::from_millis(200));
+        assert!(!ping.legacy);
+    }
+
+    #[test]
+    fn wrong_pong_is_ignored() {
+        let port = status_server(r#"{"description": "hi"}"#, 1);
+
+        // the latency is the status request's instead
+        let ping = ping("127.0.0.1", port, None, Duration::from_secs(5)).unwrap();
+        assert!(ping.latency < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn slow_server_times_out() {
+        let port = slow_server();
+
+        let start = Instant::now();
+        let result = ping("127.0.0.1", port, None, Duration::from_millis(300));
+        assert!(matches!(result, Err(error::Error::Timeout)), "{:?}", result);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn falls_back_to_legacy() {
+        let port = legacy_server();
+
+        let ping = ping("127.0.0.1", port, None, Duration::from_secs(5)).unwrap();
+        assert!(ping.legacy);
+        assert!(ping.raw_status.is_none());
+        assert_eq!(ping.status.version.unwrap().protocol, 127);
+        assert_eq!(
+            ping.status.description,
+            Some(Description::Legacy(LEGACY_MOTD.to_owned()))
+        );
+        assert_eq!(ping.status.players.unwrap().max, 20);
+    }
+}
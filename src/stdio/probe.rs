@@ -0,0 +1,66 @@
+use std::{
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error, handshake,
+    probe::{self, Probe},
+    role,
+    versions::v3::{packets::login::s2c, states},
+};
+
+use super::{
+    accept_stdio_stream,
+    ping::{self, connect, map_timeout, resolve, set_write_timeout},
+    StdIoConnection,
+};
+
+/// Works out which protocol version the server speaks, `timeout` covers the whole probe.
+///
+/// Tries the status first, then a login probe if that fails or the version can't be used.
+/// The status gets half the timeout, so a server ignoring it leaves time for the login probe.
+pub fn probe_version(host: &str, port: u16, timeout: Duration) -> Result<Probe, error::Error> {
+    let deadline = Instant::now() + timeout;
+
+    let status_probe = ping::ping(host, port, None, timeout / 2)
+        .map(|ping| Probe::from_status(&ping.status, ping.legacy));
+
+    match status_probe {
+        Ok(probe) if probe.protocol_version.is_some() => Ok(probe),
+        Ok(mut probe) => {
+            // keep what the status said, only the version is taken from the login probe
+            if let Ok(Probe {
+                protocol_version: Some(protocol_version),
+                ..
+            }) = login_probe(host, port, deadline)
+            {
+                probe.method = probe::ProbeMethod::Login;
+                probe.protocol_version = Some(protocol_version);
+            }
+
+            Ok(probe)
+        }
+        Err(err) => login_probe(host, port, deadline).map_err(|_| err),
+    }
+}
+
+fn login_probe(host: &str, port: u16, deadline: Instant) -> Result<Probe, error::Error> {
+    let addresses = resolve(host, port, deadline)?;
+    let stream: TcpStream = connect(&addresses, deadline)?;
+
+    let mut connection: StdIoConnection<role::Client, handshake::HandshakingState> =
+        accept_stdio_stream(stream)?;
+    set_write_timeout(&connection.stream, deadline)?;
+    connection
+        .write_packet(probe::login_probe_handshake(host, port))
+        .map_err(map_timeout)?;
+
+    let mut connection = connection.next_state::<states::LoginState>();
+    match connection.next_packet_before(deadline)? {
+        s2c::Packets::Disconnect(disconnect) => {
+            Ok(Probe::from_disconnect_reason(&disconnect.reason))
+        }
+        _ => Err(error::Error::IncorectPacket),
+    }
+}
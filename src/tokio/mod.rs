@@ -1,12 +1,28 @@
-use std::convert::TryInto;
+use std::{convert::TryInto, io};
 
-use bytes::Bytes;
+use bytes::{BufMut as _, Bytes};
 
-use crate::{connection, error, packet, role, state};
+mod ping;
+mod probe;
+
+pub use ping::ping;
+pub use probe::probe_version;
+
+use crate::{
+    auth::{
+        server::{ServerEncryption, SharedSecret},
+        EncryptionRequestPacket, EncryptionResponsePacket,
+    },
+    connection, error, packet,
+    proxy_protocol::{ParsedProxyHeader, ProxyHeader},
+    resolve::{self, ResolvedAddress, Resolver, ServerAddress},
+    role, state,
+};
 
 use tokio::{
     io::{AsyncReadExt as _, AsyncWriteExt as _},
     net::{TcpStream, ToSocketAddrs},
+    task, time,
 };
 use tracing::{trace_span, Instrument};
 
@@ -17,6 +33,7 @@ where
 {
     stream: TcpStream,
     connection: connection::Connection<Role, State>,
+    write_queue: connection::WriteQueue,
 }
 
 pub fn accept_tokio_stream<Role: role::ConnectionRole, State: state::ProtocolState>(
@@ -27,9 +44,51 @@ pub fn accept_tokio_stream<Role: role::ConnectionRole, State: state::ProtocolSta
     Ok(TokioConnection {
         stream,
         connection: connection::create_connection(),
+        write_queue: connection::WriteQueue::default(),
     })
 }
 
+/// Accepts a stream that may start with a PROXY protocol (v1 or v2) header.
+///
+/// Any data read past the header is kept for the connection.
+/// If the listener is only reachable through a proxy, callers should reject connections
+/// without a header, otherwise clients can spoof their address.
+pub async fn accept_tokio_stream_with_proxy_header<
+    Role: role::ConnectionRole,
+    State: state::ProtocolState,
+>(
+    stream: TcpStream,
+) -> Result<(TokioConnection<Role, State>, Option<ProxyHeader>), error::Error> {
+    let mut connection = accept_tokio_stream(stream)?;
+
+    let mut data = Vec::new();
+    let header = loop {
+        let (header, header_length) = match ProxyHeader::parse(&data)? {
+            ParsedProxyHeader::None => (None, 0),
+            ParsedProxyHeader::Header(header, length) => (Some(header), length),
+            ParsedProxyHeader::Incomplete => {
+                let mut chunk = [0; 512];
+                let len = connection
+                    .stream
+                    .read(&mut chunk)
+                    .instrument(trace_span!("connection.stream.read"))
+                    .await?;
+                if len == 0 {
+                    return Err(error::Error::StreamShutdown);
+                }
+
+                data.extend_from_slice(&chunk[..len]);
+                continue;
+            }
+        };
+
+        connection.connection.recieve_data(&data[header_length..]);
+        break header;
+    };
+
+    Ok((connection, header))
+}
+
 pub async fn connect_tokio_stream<
     Addr: ToSocketAddrs,
     Role: role::ConnectionRole,
@@ -40,6 +99,27 @@ pub async fn connect_tokio_stream<
     accept_tokio_stream(TcpStream::connect(addr).await?)
 }
 
+/// Resolves the address like vanilla (SRV, then A/AAAA) and connects to the first that works.
+///
+/// Handshake with [`ResolvedAddress::handshake`], so the server sees the address that was typed in
+/// rather than the SRV target.
+/// Resolving runs on tokio's blocking pool, pass an `Arc` to share the resolver.
+pub async fn connect_tokio_address<
+    R: Resolver + Send + 'static,
+    Role: role::ConnectionRole,
+    State: state::ProtocolState,
+>(
+    resolver: R,
+    address: ServerAddress,
+) -> Result<(TokioConnection<Role, State>, ResolvedAddress), error::Error> {
+    let resolved = task::spawn_blocking(move || resolve::resolve(&resolver, address))
+        .await
+        .map_err(io::Error::other)??;
+    let connection = connect_tokio_stream(&resolved.socket_addresses[..]).await?;
+
+    Ok((connection, resolved))
+}
+
 impl<Role, State> TokioConnection<Role, State>
 where
     Role: role::ConnectionRole,
@@ -56,17 +136,39 @@ where
                 trace_span!("connection.next_event").in_scope(|| self.connection.next_event())?;
             match event {
                 connection::Event::NeedMoreData => {
-                    let mut data = [0; 512];
-                    let len = self
+                    // wait until there's something to read before taking a pooled buffer,
+                    // flushing queued packets if they're due first
+                    let flush_deadline = self.write_queue.flush_deadline();
+                    if flush_deadline.is_some() || self.connection.recv_buffer_released() {
+                        let readable = self.stream.readable();
+                        match flush_deadline {
+                            Some(deadline) => {
+                                match time::timeout_at(deadline.into(), readable).await {
+                                    Ok(result) => result?,
+                                    Err(_) => {
+                                        self.flush().await?;
+                                        continue;
+                                    }
+                                }
+                            }
+                            None => readable.await?,
+                        }
+                    }
+
+                    let read_size = self.connection.read_size();
+                    let buffer = self.connection.reserve_recv_buffer(read_size);
+                    let start = buffer.len();
+
+                    let result = self
                         .stream
-                        .read(&mut data)
+                        .read_buf(&mut buffer.limit(read_size))
                         .instrument(trace_span!("connection.stream.read"))
-                        .await?;
-                    if len == 0 {
+                        .await;
+                    trace_span!("connection.recieved").in_scope(|| self.connection.recieved(start));
+
+                    if result? == 0 {
                         return Err(error::Error::StreamShutdown);
                     }
-                    trace_span!("connection.recieve_data")
-                        .in_scope(|| self.connection.recieve_data(&data[0..len]));
                 }
                 connection::Event::Packet(packet) => return Ok(packet),
             }
@@ -84,24 +186,85 @@ where
         self.next_packet().await?.try_into()
     }
 
+    /// Writes a PROXY protocol header, this must be done before any packets are sent.
+    pub async fn write_proxy_header(&mut self, header: &ProxyHeader) -> Result<(), error::Error> {
+        let mut data = Vec::new();
+        header.write(&mut data)?;
+
+        self.write_queue.push(&data);
+        self.flush().await
+    }
+
+    /// Writes the packet immediately, along with anything already queued.
     pub async fn write_packet<Packet: state::RoleStateWritePacket<Role, State>>(
         &mut self,
         packet: Packet,
     ) -> Result<(), error::Error> {
-        let data = trace_span!("connection.send").in_scope(|| self.connection.send(packet));
-
-        Ok(self
-            .stream
-            .write_all(&data)
-            .instrument(trace_span!("connection.stream.write_all"))
-            .await?)
+        let (connection, write_queue) = (&mut self.connection, &mut self.write_queue);
+        trace_span!("connection.send")
+            .in_scope(|| write_queue.push_with(|buffer| connection.send_into(packet, buffer)))?;
+        self.flush().await
     }
 
     pub async fn write_id_body(&mut self, id: i32, body: &mut Bytes) -> Result<(), error::Error> {
-        Ok(self
-            .stream
-            .write_all(&self.connection.send_id_body(id, body))
-            .await?)
+        let connection = &mut self.connection;
+        self.write_queue
+            .push_with(|buffer| connection.send_id_body_into(id, body, buffer))?;
+        self.flush().await
+    }
+
+    /// Queues the packet to be written on the next flush.
+    ///
+    /// The queue is flushed straight away if the flush policy's thresholds are reached.
+    pub async fn queue_packet<Packet: state::RoleStateWritePacket<Role, State>>(
+        &mut self,
+        packet: Packet,
+    ) -> Result<(), error::Error> {
+        let (connection, write_queue) = (&mut self.connection, &mut self.write_queue);
+        trace_span!("connection.send")
+            .in_scope(|| write_queue.push_with(|buffer| connection.send_into(packet, buffer)))?;
+        self.flush_if_due().await
+    }
+
+    pub async fn queue_id_body(&mut self, id: i32, body: &mut Bytes) -> Result<(), error::Error> {
+        let connection = &mut self.connection;
+        self.write_queue
+            .push_with(|buffer| connection.send_id_body_into(id, body, buffer))?;
+        self.flush_if_due().await
+    }
+
+    /// Writes all queued packets in a single write.
+    pub async fn flush(&mut self) -> Result<(), error::Error> {
+        if !self.write_queue.is_empty() {
+            self.stream
+                .write_all(self.write_queue.as_bytes())
+                .instrument(trace_span!("connection.stream.write_all"))
+                .await?;
+            self.write_queue.clear();
+        }
+
+        Ok(self.stream.flush().await?)
+    }
+
+    /// Flushes only if the flush policy's thresholds have been reached.
+    ///
+    /// `next_packet` already flushes on `max_delay` while it waits, this is for when nothing's
+    /// being read.
+    pub async fn flush_if_due(&mut self) -> Result<(), error::Error> {
+        if self.write_queue.should_flush() {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    pub fn set_flush_policy(&mut self, policy: connection::FlushPolicy) {
+        self.write_queue.set_policy(policy)
+    }
+
+    /// Number of bytes waiting to be flushed.
+    pub fn queued_len(&self) -> usize {
+        self.write_queue.len()
     }
 
     pub fn next_state<NextState>(self) -> TokioConnection<Role, NextState>
@@ -112,6 +275,7 @@ where
         TokioConnection {
             stream: self.stream,
             connection: self.connection.next_state(),
+            write_queue: self.write_queue,
         }
     }
 
@@ -119,8 +283,11 @@ where
         Ok(self.stream.shutdown().await?)
     }
 
-    pub fn into_bytes_stream(self) -> (Bytes, TcpStream) {
-        (self.connection.into_bytes(), self.stream)
+    /// Flushes any queued packets first, so none are lost.
+    pub async fn into_bytes_stream(mut self) -> Result<(Bytes, TcpStream), error::Error> {
+        self.flush().await?;
+
+        Ok((self.connection.into_bytes(), self.stream))
     }
 
     pub fn set_compression_threshold<T: Into<Option<usize>>>(&mut self, threshold: T) {
@@ -130,4 +297,109 @@ where
     pub fn set_encryption_secret(&mut self, secret: &[u8]) {
         self.connection.set_encryption_secret(secret)
     }
+
+    pub fn set_read_size(&mut self, size: usize) {
+        self.connection.set_read_size(size)
+    }
+
+    pub fn set_buffer_pool<T: Into<Option<connection::BufferPool>>>(&mut self, pool: T) {
+        self.connection.set_buffer_pool(pool)
+    }
+}
+
+impl<State> TokioConnection<role::Server, State>
+where
+    State: state::ProtocolState,
+{
+    /// Sends `EncryptionRequest`, decrypts the client's `EncryptionResponse` and enables encryption.
+    ///
+    /// The returned server hash should be checked with `SessionServer::has_joined`.
+    pub async fn exchange_encryption<Request, Response>(
+        &mut self,
+        encryption: &ServerEncryption<'_>,
+    ) -> Result<SharedSecret, error::Error>
+    where
+        Request: EncryptionRequestPacket + state::RoleStateWritePacket<role::Server, State>,
+        Response: EncryptionResponsePacket + state::RoleStateReadPacket<role::Server, State>,
+
+        State: state::RoleStatePackets<role::Server>,
+        State::RecvPacket: packet::PacketFromIdBody,
+        State::RecvPacket: TryInto<Response, Error = error::Error>,
+    {
+        self.write_packet(encryption.request::<Request>()).await?;
+        let response: Response = self.expect_next_packet().await?;
+
+        let shared_secret = encryption.decrypt_response(&response)?;
+        self.set_encryption_secret(&shared_secret.shared_secret);
+
+        Ok(shared_secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::TcpListener,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+    use crate::{
+        handshake, stdio,
+        versions::latest::{
+            packets::status::{c2s::StatusRequest, s2c::StatusResponse},
+            states::StatusState,
+        },
+    };
+
+    #[test]
+    fn next_packet_flushes_on_max_delay() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let connection: stdio::StdIoConnection<role::Server, handshake::HandshakingState> =
+                stdio::accept_stdio_stream(stream).unwrap();
+            let mut connection = connection.next_state::<StatusState>();
+
+            connection.expect_next_packet::<StatusRequest>().unwrap();
+            connection
+                .write_packet(StatusResponse {
+                    response: "{}".to_owned(),
+                })
+                .unwrap();
+        });
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let connection: TokioConnection<role::Client, handshake::HandshakingState> =
+                connect_tokio_stream(address).await.unwrap();
+            let mut connection = connection.next_state::<StatusState>();
+            connection.set_flush_policy(connection::FlushPolicy {
+                max_bytes: None,
+                max_delay: Some(Duration::from_millis(50)),
+            });
+
+            // nothing else is queued, so only the timer sends the request
+            let start = Instant::now();
+            connection.queue_packet(StatusRequest).await.unwrap();
+            assert_eq!(connection.queued_len(), 2);
+
+            let response = time::timeout(
+                Duration::from_secs(5),
+                connection.expect_next_packet::<StatusResponse>(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            assert_eq!(response.response, "{}");
+            assert!(start.elapsed() >= Duration::from_millis(50));
+            assert_eq!(connection.queued_len(), 0);
+        });
+    }
 }
@@ -0,0 +1,217 @@
+use std::{
+    future::Future,
+    io,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    net::{lookup_host, TcpStream},
+    time,
+};
+
+use crate::{
+    error, handshake,
+    ping::{self, Ping, PingError},
+    role,
+    versions::latest::{
+        self,
+        packets::status::{
+            c2s::{PingRequest, StatusRequest},
+            s2c::{PingResponse, StatusResponse},
+        },
+        states,
+    },
+};
+
+use super::{accept_tokio_stream, TokioConnection};
+
+/// Pings the server, falling back to the legacy ping if it doesn't speak the modern status.
+///
+/// `protocol_version` defaults to the latest, `timeout` covers the whole ping.
+pub async fn ping(
+    host: &str,
+    port: u16,
+    protocol_version: Option<i32>,
+    timeout: Duration,
+) -> Result<Ping, error::Error> {
+    let deadline = Instant::now() + timeout;
+    let protocol_version = protocol_version.unwrap_or(latest::PROTOCOL_VERSION);
+    let addresses: Vec<_> = within(deadline, lookup_host((host, port)))
+        .await??
+        .collect();
+
+    let stream = connect(&addresses, deadline).await?;
+    match modern_ping(stream, host, port, protocol_version, deadline).await {
+        Ok(ping) => Ok(ping),
+        Err(error::Error::Timeout) => Err(error::Error::Timeout),
+        Err(err) => {
+            let stream = connect(&addresses, deadline).await?;
+            // the modern error says more about what went wrong
+            match within(deadline, legacy_ping(stream, host, port)).await? {
+                Ok(ping) => Ok(ping),
+                Err(_) => Err(err),
+            }
+        }
+    }
+}
+
+pub(super) async fn within<F: Future>(
+    deadline: Instant,
+    future: F,
+) -> Result<F::Output, error::Error> {
+    time::timeout_at(deadline.into(), future)
+        .await
+        .map_err(|_| error::Error::Timeout)
+}
+
+pub(super) async fn connect(
+    addresses: &[SocketAddr],
+    deadline: Instant,
+) -> Result<TcpStream, error::Error> {
+    let mut last_err = None;
+    for address in addresses {
+        match within(deadline, TcpStream::connect(address)).await? {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    Err(last_err
+        .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host has no addresses"))
+        .into())
+}
+
+async fn modern_ping(
+    stream: TcpStream,
+    host: &str,
+    port: u16,
+    protocol_version: i32,
+    deadline: Instant,
+) -> Result<Ping, error::Error> {
+    let mut connection: TokioConnection<role::Client, handshake::HandshakingState> =
+        accept_tokio_stream(stream)?;
+
+    within(
+        deadline,
+        connection.write_packet(ping::status_handshake(host, port, protocol_version)),
+    )
+    .await??;
+
+    let mut connection = connection.next_state::<states::StatusState>();
+
+    let start = Instant::now();
+    let response = async {
+        connection.write_packet(StatusRequest).await?;
+        connection.expect_next_packet::<StatusResponse>().await
+    };
+    let response = within(deadline, response).await??;
+    let status_latency = start.elapsed();
+
+    let status = response.status().map_err(PingError::from)?;
+
+    // some servers close the connection instead of answering the ping, or answer it wrong
+    let payload = ping::ping_payload();
+    let start = Instant::now();
+    let pong = async {
+        connection.write_packet(PingRequest { payload }).await?;
+        connection.expect_next_packet::<PingResponse>().await
+    };
+    let latency = match within(deadline, pong).await {
+        Ok(Ok(pong)) if pong.payload == payload => start.elapsed(),
+        _ => status_latency,
+    };
+
+    Ok(Ping {
+        status,
+        raw_status: Some(response.response),
+        latency,
+        legacy: false,
+    })
+}
+
+async fn legacy_ping(mut stream: TcpStream, host: &str, port: u16) -> Result<Ping, error::Error> {
+    let start = Instant::now();
+    stream.write_all(&ping::legacy_request(host, port)).await?;
+
+    let mut data = Vec::new();
+    loop {
+        if let Some(status) = ping::parse_legacy_response(&data)? {
+            return Ok(Ping {
+                status,
+                raw_status: None,
+                latency: start.elapsed(),
+                legacy: true,
+            });
+        }
+
+        let len = stream.read_buf(&mut data).await?;
+        if len == 0 {
+            return Err(error::Error::StreamShutdown);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ping::test_servers::{legacy_server, slow_server, status_server, LEGACY_MOTD},
+        status::Description,
+    };
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn keeps_raw_status() {
+        let response = r#"{ "version": {"name": "1.21.1", "protocol": 767}, "extra": 1 }"#;
+        let port = status_server(response, 0);
+
+        let ping = block_on(ping("127.0.0.1", port, None, Duration::from_secs(5))).unwrap();
+        assert_eq!(ping.raw_status.as_deref(), Some(response));
+        assert_eq!(ping.status.version.unwrap().protocol, 767);
+        assert!(ping.latency >= Duration::from_millis(200));
+        assert!(!ping.legacy);
+    }
+
+    #[test]
+    fn wrong_pong_is_ignored() {
+        let port = status_server(r#"{"description": "hi"}"#, 1);
+
+        // the latency is the status request's instead
+        let ping = block_on(ping("127.0.0.1", port, None, Duration::from_secs(5))).unwrap();
+        assert!(ping.latency < Duration::from_millis(200));
+    }
+
+    #[test]
+    fn slow_server_times_out() {
+        let port = slow_server();
+
+        let start = Instant::now();
+        let result = block_on(ping("127.0.0.1", port, None, Duration::from_millis(300)));
+        assert!(matches!(result, Err(error::Error::Timeout)), "{:?}", result);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn falls_back_to_legacy() {
+        let port = legacy_server();
+
+        let ping = block_on(ping("127.0.0.1", port, None, Duration::from_secs(5))).unwrap();
+        assert!(ping.legacy);
+        assert!(ping.raw_status.is_none());
+        assert_eq!(ping.status.version.unwrap().protocol, 127);
+        assert_eq!(
+            ping.status.description,
+            Some(Description::Legacy(LEGACY_MOTD.to_owned()))
+        );
+        assert_eq!(ping.status.players.unwrap().max, 20);
+    }
+}
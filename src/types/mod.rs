@@ -1,10 +1,18 @@
-use std::ops::Deref;
-
-use bytes::{Buf, BufMut};
+use std::{
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str::FromStr,
+};
+
+use bytes::{Buf, BufMut, Bytes};
 use uuid::Uuid;
 
 pub mod proxy;
 
+pub use packet_derive::BufType;
+
 use self::proxy::length_prefix_bytes;
 
 #[derive(Debug, thiserror::Error)]
@@ -15,8 +23,23 @@ pub enum ReadError {
     #[error("utf8 error: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
 
+    #[error("utf8 error: {0}")]
+    StrUtf8Error(#[from] std::str::Utf8Error),
+
     #[error("varint too large")]
     VarIntTooLarge,
+
+    #[error("varlong too large")]
+    VarLongTooLarge,
+
+    #[error("unknown discriminant for {0}: {1}")]
+    UnknownDiscriminant(&'static str, String),
+
+    #[error("{0}")]
+    InvalidIdentifier(#[from] InvalidIdentifier),
+
+    #[error("{0}")]
+    BungeeForwarding(#[from] crate::handshake::BungeeForwardingError),
 }
 
 type Result<T> = std::result::Result<T, ReadError>;
@@ -29,6 +52,13 @@ pub trait BufType: Sized {
     fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize)>;
 
     fn buf_write<B: BufMut>(&self, buf: &mut B);
+
+    /// Exact number of bytes `buf_write` writes, without writing them.
+    fn encoded_len(&self) -> usize {
+        let mut buf = Vec::new();
+        self.buf_write(&mut buf);
+        buf.len()
+    }
 }
 
 macro_rules! impl_primitive {
@@ -44,6 +74,10 @@ macro_rules! impl_primitive {
             fn buf_write<B: BufMut>(&self, buf: &mut B) {
                 buf.$put_fn(*self)
             }
+
+            fn encoded_len(&self) -> usize {
+                core::mem::size_of::<$self>()
+            }
         }
     };
 }
@@ -66,6 +100,10 @@ impl BufType for bool {
     fn buf_write<B: BufMut>(&self, buf: &mut B) {
         buf.put_u8(*self as _);
     }
+
+    fn encoded_len(&self) -> usize {
+        1
+    }
 }
 
 impl_primitive!(i8, get_i8, put_i8);
@@ -105,6 +143,110 @@ impl BufType for String {
 
         buf.put(self.as_bytes());
     }
+
+    fn encoded_len(&self) -> usize {
+        length_prefix_bytes::encoded_len(self)
+    }
+}
+
+/// A `String` that's a view into the packet body when read from `Bytes`, rather than a copy.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BytesString(Bytes);
+
+impl BytesString {
+    pub const fn from_static(string: &'static str) -> Self {
+        BytesString(Bytes::from_static(string.as_bytes()))
+    }
+
+    pub fn from_utf8(bytes: Bytes) -> std::result::Result<Self, std::str::Utf8Error> {
+        std::str::from_utf8(&bytes)?;
+        Ok(BytesString(bytes))
+    }
+
+    pub fn as_str(&self) -> &str {
+        // only ever made from valid utf8
+        unsafe { std::str::from_utf8_unchecked(&self.0) }
+    }
+
+    pub fn as_bytes(&self) -> &Bytes {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+impl Deref for BytesString {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for BytesString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<String> for BytesString {
+    fn from(string: String) -> Self {
+        BytesString(string.into())
+    }
+}
+
+impl From<&'static str> for BytesString {
+    fn from(string: &'static str) -> Self {
+        BytesString::from_static(string)
+    }
+}
+
+impl From<BytesString> for String {
+    fn from(string: BytesString) -> Self {
+        string.as_str().to_owned()
+    }
+}
+
+impl PartialEq<str> for BytesString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for BytesString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for BytesString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for BytesString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl BufType for BytesString {
+    fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize)> {
+        let (bytes, len) = length_prefix_bytes::buf_read_len(buf)?;
+
+        Ok((BytesString::from_utf8(bytes)?, len))
+    }
+
+    fn buf_write<B: BufMut>(&self, buf: &mut B) {
+        length_prefix_bytes::buf_write(&self.0, buf)
+    }
+
+    fn encoded_len(&self) -> usize {
+        length_prefix_bytes::encoded_len(&self.0)
+    }
 }
 
 // Text Component
@@ -127,9 +269,168 @@ impl BufType for TextComponent {
 // Chat
 
 // Identifier
-// TODO: make sure it's a valid ident?
-#[derive(Debug, Hash, PartialEq, Eq)]
-pub struct Identifier(pub String);
+/// A `namespace:path` resource location, the namespace defaults to `minecraft`.
+///
+/// Kept as it was given so it's written back unchanged, but compared by namespace and path,
+/// `stone` equals `minecraft:stone`. Reading doesn't validate, use the `strict_identifier` proxy
+/// to reject invalid ones.
+#[derive(Debug, Clone)]
+pub struct Identifier(String);
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid identifier: {0:?}")]
+pub struct InvalidIdentifier(pub String);
+
+impl Identifier {
+    pub const DEFAULT_NAMESPACE: &'static str = "minecraft";
+
+    pub fn new(identifier: impl Into<String>) -> std::result::Result<Self, InvalidIdentifier> {
+        let identifier = identifier.into();
+
+        if Self::is_valid(&identifier) {
+            Ok(Identifier(identifier))
+        } else {
+            Err(InvalidIdentifier(identifier))
+        }
+    }
+
+    /// Skips validation, for identifiers read leniently or checked by [`identifier!`].
+    pub fn new_unchecked(identifier: impl Into<String>) -> Self {
+        Identifier(identifier.into())
+    }
+
+    /// Namespaces may have `a-z0-9_.-`, paths also `/`.
+    pub const fn is_valid(identifier: &str) -> bool {
+        let bytes = identifier.as_bytes();
+
+        let mut colon = bytes.len();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b':' {
+                colon = i;
+                break;
+            }
+            i += 1;
+        }
+
+        let mut i = 0;
+        while i < bytes.len() {
+            let valid = match bytes[i] {
+                b'a'..=b'z' | b'0'..=b'9' | b'_' | b'.' | b'-' => true,
+                b'/' => i > colon || colon == bytes.len(),
+                b':' => i == colon,
+                _ => false,
+            };
+            if !valid {
+                return false;
+            }
+            i += 1;
+        }
+
+        true
+    }
+
+    pub fn namespace(&self) -> &str {
+        match self.0.split_once(':') {
+            Some((namespace, _)) if !namespace.is_empty() => namespace,
+            _ => Self::DEFAULT_NAMESPACE,
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        self.0.split_once(':').map_or(&self.0, |(_, path)| path)
+    }
+
+    /// The identifier as it was given, the namespace may be left out.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    fn eq_str(&self, other: &str) -> bool {
+        let (namespace, path) = match other.split_once(':') {
+            Some((namespace, path)) if !namespace.is_empty() => (namespace, path),
+            Some((_, path)) => (Self::DEFAULT_NAMESPACE, path),
+            None => (Self::DEFAULT_NAMESPACE, other),
+        };
+
+        self.namespace() == namespace && self.path() == path
+    }
+}
+
+/// An [`Identifier`] checked at compile time, `identifier!("stone")`. Takes any `&'static str`
+/// constant expression.
+#[macro_export]
+macro_rules! identifier {
+    ($identifier:expr) => {{
+        const IDENTIFIER: &str = $identifier;
+        const _: () = ::core::assert!(
+            $crate::types::Identifier::is_valid(IDENTIFIER),
+            "invalid identifier"
+        );
+
+        $crate::types::Identifier::new_unchecked(IDENTIFIER)
+    }};
+}
+
+impl PartialEq for Identifier {
+    fn eq(&self, other: &Self) -> bool {
+        self.namespace() == other.namespace() && self.path() == other.path()
+    }
+}
+
+impl Eq for Identifier {}
+
+impl PartialEq<str> for Identifier {
+    fn eq(&self, other: &str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl PartialEq<&str> for Identifier {
+    fn eq(&self, other: &&str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl Hash for Identifier {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // consistent with `Eq`, the namespace is always there
+        self.namespace().hash(state);
+        self.path().hash(state);
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace(), self.path())
+    }
+}
+
+impl FromStr for Identifier {
+    type Err = InvalidIdentifier;
+
+    fn from_str(identifier: &str) -> std::result::Result<Self, Self::Err> {
+        Identifier::new(identifier)
+    }
+}
+
+impl TryFrom<String> for Identifier {
+    type Error = InvalidIdentifier;
+
+    fn try_from(identifier: String) -> std::result::Result<Self, Self::Error> {
+        Identifier::new(identifier)
+    }
+}
+
+impl From<Identifier> for String {
+    fn from(identifier: Identifier) -> Self {
+        identifier.0
+    }
+}
 
 impl BufType for Identifier {
     fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize)> {
@@ -141,6 +442,10 @@ impl BufType for Identifier {
     fn buf_write<B: BufMut>(&self, buf: &mut B) {
         self.0.buf_write(buf)
     }
+
+    fn encoded_len(&self) -> usize {
+        self.0.encoded_len()
+    }
 }
 
 // VarInt
@@ -210,9 +515,87 @@ impl BufType for v32 {
 
         (input as u8).buf_write(buf)
     }
+
+    fn encoded_len(&self) -> usize {
+        match self.0 as u32 {
+            0..=0x7F => 1,
+            0x80..=0x3FFF => 2,
+            0x4000..=0x1F_FFFF => 3,
+            0x20_0000..=0xFFF_FFFF => 4,
+            _ => 5,
+        }
+    }
 }
 
 // VarLong
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(non_camel_case_types)]
+pub struct v64(pub i64);
+
+impl From<i64> for v64 {
+    fn from(inner: i64) -> Self {
+        v64(inner)
+    }
+}
+
+impl From<v64> for i64 {
+    fn from(value: v64) -> Self {
+        value.0
+    }
+}
+
+impl Deref for v64 {
+    type Target = i64;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl BufType for v64 {
+    fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize)> {
+        let mut acc = 0;
+        let mut i = 0;
+
+        loop {
+            // checked before shifting, the 11th byte would overflow the shift
+            if i == 10 {
+                return Err(ReadError::VarLongTooLarge);
+            }
+
+            let byte = u8::buf_read(buf)? as i64;
+            acc |= (byte & 0x7F) << (i * 7);
+
+            i += 1;
+            if (byte & 0b10000000) == 0 {
+                break;
+            }
+        }
+
+        Ok((v64(acc), i))
+    }
+
+    fn buf_write<B: BufMut>(&self, buf: &mut B) {
+        let mut input = self.0 as u64;
+
+        loop {
+            if (input & !0x7F) == 0 {
+                break;
+            }
+
+            ((input & 0x7F | 0x80) as u8).buf_write(buf);
+            input >>= 7;
+        }
+
+        (input as u8).buf_write(buf)
+    }
+
+    fn encoded_len(&self) -> usize {
+        // 7 bits per byte, at least one byte
+        let bits = 64 - (self.0 as u64).leading_zeros() as usize;
+        bits.div_ceil(7).max(1)
+    }
+}
 
 // Entity Metadata
 
@@ -221,8 +604,235 @@ impl BufType for v32 {
 // NBT Tag
 
 // Position
+/// A block position, packed into a long.
+///
+/// Written as x/z/y (26/26/12 bits) since 1.14, use the `legacy_position` proxy for the x/y/z
+/// (26/12/26 bits) layout before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Position { x, y, z }
+    }
+
+    pub const fn to_packed(self) -> i64 {
+        ((self.x as i64 & 0x3FF_FFFF) << 38)
+            | ((self.z as i64 & 0x3FF_FFFF) << 12)
+            | (self.y as i64 & 0xFFF)
+    }
+
+    pub const fn from_packed(packed: i64) -> Self {
+        // shifting left then right sign extends each part
+        Position {
+            x: (packed >> 38) as i32,
+            y: (packed << 52 >> 52) as i32,
+            z: (packed << 26 >> 38) as i32,
+        }
+    }
+
+    /// The packing before 1.14.
+    pub const fn to_legacy_packed(self) -> i64 {
+        ((self.x as i64 & 0x3FF_FFFF) << 38)
+            | ((self.y as i64 & 0xFFF) << 26)
+            | (self.z as i64 & 0x3FF_FFFF)
+    }
+
+    /// The packing before 1.14.
+    pub const fn from_legacy_packed(packed: i64) -> Self {
+        Position {
+            x: (packed >> 38) as i32,
+            y: (packed << 26 >> 52) as i32,
+            z: (packed << 38 >> 38) as i32,
+        }
+    }
+}
+
+impl BufType for Position {
+    fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize)> {
+        let (packed, len) = i64::buf_read_len(buf)?;
+
+        Ok((Position::from_packed(packed), len))
+    }
+
+    fn buf_write<B: BufMut>(&self, buf: &mut B) {
+        self.to_packed().buf_write(buf)
+    }
+
+    fn encoded_len(&self) -> usize {
+        8
+    }
+}
 
 // Angle
+/// A rotation in steps of 1/256 of a full turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    /// Rounds to the nearest step, wrapping around outside 0..360.
+    pub fn from_degrees(degrees: f32) -> Self {
+        Angle((degrees / 360.0 * 256.0).round().rem_euclid(256.0) as u8)
+    }
+
+    pub fn to_degrees(self) -> f32 {
+        self.0 as f32 * 360.0 / 256.0
+    }
+}
+
+impl BufType for Angle {
+    fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize)> {
+        u8::buf_read_len(buf).map(|(value, len)| (Angle(value), len))
+    }
+
+    fn buf_write<B: BufMut>(&self, buf: &mut B) {
+        self.0.buf_write(buf)
+    }
+
+    fn encoded_len(&self) -> usize {
+        1
+    }
+}
+
+// BitSet
+/// A varint length prefixed array of longs, bit `i` is bit `i % 64` of long `i / 64`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct BitSet(pub Vec<i64>);
+
+impl BitSet {
+    pub fn new() -> Self {
+        BitSet(Vec::new())
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        self.0
+            .get(i / 64)
+            .is_some_and(|long| long & (1 << (i % 64)) != 0)
+    }
+
+    /// Grows the set to fit `i` when needed.
+    pub fn set(&mut self, i: usize, value: bool) {
+        if i / 64 >= self.0.len() {
+            if !value {
+                return;
+            }
+            self.0.resize(i / 64 + 1, 0);
+        }
+
+        if value {
+            self.0[i / 64] |= 1 << (i % 64);
+        } else {
+            self.0[i / 64] &= !(1 << (i % 64));
+        }
+    }
+}
+
+impl BufType for BitSet {
+    fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize)> {
+        proxy::length_prefix_array::buf_read_len(buf).map(|(longs, len)| (BitSet(longs), len))
+    }
+
+    fn buf_write<B: BufMut>(&self, buf: &mut B) {
+        proxy::length_prefix_array::buf_write(&self.0, buf)
+    }
+
+    fn encoded_len(&self) -> usize {
+        proxy::length_prefix_array::encoded_len(&self.0)
+    }
+}
+
+// Fixed BitSet
+/// A bitset of a size known to both sides, `BYTES` is the number of bits divided by 8, rounded
+/// up. Bit `i` is bit `i % 8` of byte `i / 8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedBitSet<const BYTES: usize>(pub [u8; BYTES]);
+
+impl<const BYTES: usize> FixedBitSet<BYTES> {
+    pub const fn new() -> Self {
+        FixedBitSet([0; BYTES])
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        self.0[i / 8] & (1 << (i % 8)) != 0
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        if value {
+            self.0[i / 8] |= 1 << (i % 8);
+        } else {
+            self.0[i / 8] &= !(1 << (i % 8));
+        }
+    }
+}
+
+impl<const BYTES: usize> Default for FixedBitSet<BYTES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BYTES: usize> BufType for FixedBitSet<BYTES> {
+    fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize)> {
+        ensure_remaining(buf, BYTES)?;
+        let mut bytes = [0; BYTES];
+        buf.copy_to_slice(&mut bytes);
+
+        Ok((FixedBitSet(bytes), BYTES))
+    }
+
+    fn buf_write<B: BufMut>(&self, buf: &mut B) {
+        buf.put_slice(&self.0);
+    }
+
+    fn encoded_len(&self) -> usize {
+        BYTES
+    }
+}
+
+// Fixed-point number
+/// A coordinate with 5 fractional bits, as used for entity positions up to 1.8. `i32` for
+/// absolute and `i8` for relative positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FixedPoint<T>(pub T);
+
+macro_rules! impl_fixed_point {
+    ($($inner:ty),*) => {
+        $(
+            impl FixedPoint<$inner> {
+                pub const FRACTION_BITS: u32 = 5;
+
+                /// Rounds towards zero, saturating at the bounds.
+                pub fn from_f64(value: f64) -> Self {
+                    FixedPoint((value * (1 << Self::FRACTION_BITS) as f64) as $inner)
+                }
+
+                pub fn to_f64(self) -> f64 {
+                    self.0 as f64 / (1 << Self::FRACTION_BITS) as f64
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_point!(i8, i32);
+
+impl<T: BufType> BufType for FixedPoint<T> {
+    fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Self, usize)> {
+        T::buf_read_len(buf).map(|(value, len)| (FixedPoint(value), len))
+    }
+
+    fn buf_write<B: BufMut>(&self, buf: &mut B) {
+        self.0.buf_write(buf)
+    }
+
+    fn encoded_len(&self) -> usize {
+        self.0.encoded_len()
+    }
+}
 
 // UUID
 impl BufType for Uuid {
@@ -237,6 +847,10 @@ impl BufType for Uuid {
     fn buf_write<B: BufMut>(&self, buf: &mut B) {
         buf.put_slice(self.as_bytes());
     }
+
+    fn encoded_len(&self) -> usize {
+        16
+    }
 }
 
 // Optional X
@@ -262,6 +876,10 @@ impl<T: BufType> BufType for Option<T> {
             value.buf_write(buf);
         }
     }
+
+    fn encoded_len(&self) -> usize {
+        1 + self.as_ref().map_or(0, T::encoded_len)
+    }
 }
 
 // Array of X
@@ -290,8 +908,271 @@ macro_rules! v32_prefix_enum {
 
                 crate::types::proxy::i32_as_v32::buf_write(&value, buf)
             }
+
+            fn encoded_len(&self) -> usize {
+                let value = match self {
+                    $(Self::$variant => $val,)*
+                    Self::$unknown(unknown) => *unknown,
+                };
+
+                crate::types::proxy::i32_as_v32::encoded_len(&value)
+            }
         }
     };
 }
 
 pub(crate) use v32_prefix_enum;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write<T: BufType>(value: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        value.buf_write(&mut buf);
+        assert_eq!(value.encoded_len(), buf.len());
+
+        buf
+    }
+
+    fn read<T: BufType>(bytes: &[u8]) -> T {
+        let (value, len) = T::buf_read_len(&mut &bytes[..]).unwrap();
+        assert_eq!(len, bytes.len());
+
+        value
+    }
+
+    #[test]
+    fn v64_boundaries() {
+        let cases: &[(i64, &[u8])] = &[
+            (0, &[0x00]),
+            (1, &[0x01]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (i32::MAX as i64, &[0xff, 0xff, 0xff, 0xff, 0x07]),
+            (
+                i64::MAX,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f],
+            ),
+            (
+                -1,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            ),
+            (
+                i64::MIN,
+                &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01],
+            ),
+        ];
+
+        for &(value, bytes) in cases {
+            assert_eq!(write(&v64(value)), bytes, "{}", value);
+            assert_eq!(read::<v64>(bytes), v64(value));
+        }
+    }
+
+    #[test]
+    fn v64_too_large() {
+        let bytes = [0x80; 11];
+
+        assert!(matches!(
+            v64::buf_read(&mut &bytes[..]),
+            Err(ReadError::VarLongTooLarge)
+        ));
+    }
+
+    #[test]
+    fn position_packing() {
+        // example from the protocol docs
+        let position = Position::new(18357644, 831, -20882616);
+        let bytes = 0b01000110_00000111_01100011_00101100_00010101_10110100_10000011_00111111_u64
+            .to_be_bytes();
+
+        assert_eq!(write(&position), bytes);
+        assert_eq!(read::<Position>(&bytes), position);
+    }
+
+    #[test]
+    fn position_boundaries() {
+        let corners = [
+            Position::new(-33554432, -2048, -33554432),
+            Position::new(33554431, 2047, 33554431),
+            Position::new(-1, -1, -1),
+            Position::new(0, 0, 0),
+            Position::new(33554431, -2048, -1),
+        ];
+
+        for position in corners {
+            assert_eq!(Position::from_packed(position.to_packed()), position);
+            assert_eq!(
+                Position::from_legacy_packed(position.to_legacy_packed()),
+                position
+            );
+        }
+
+        assert_eq!(Position::new(0, 1, 0).to_packed(), 1);
+        assert_eq!(Position::new(0, 1, 0).to_legacy_packed(), 1 << 26);
+        assert_eq!(Position::new(0, 0, 1).to_legacy_packed(), 1);
+    }
+
+    #[test]
+    fn legacy_position() {
+        let position = Position::new(-33554432, 2047, 1);
+        let mut buf = Vec::new();
+        proxy::legacy_position::buf_write(&position, &mut buf);
+
+        assert_eq!(buf, ((1i64 << 63) | (0x7FF << 26) | 1).to_be_bytes());
+        assert_eq!(
+            proxy::legacy_position::buf_read(&mut &buf[..]).unwrap(),
+            position
+        );
+    }
+
+    #[test]
+    fn angle() {
+        assert_eq!(Angle::from_degrees(0.0), Angle(0));
+        assert_eq!(Angle::from_degrees(90.0), Angle(64));
+        assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+        assert_eq!(Angle::from_degrees(359.9), Angle(0));
+        assert_eq!(Angle::from_degrees(720.0 + 180.0), Angle(128));
+        assert_eq!(Angle(255).to_degrees(), 358.59375);
+
+        assert_eq!(write(&Angle(255)), [0xff]);
+        assert_eq!(read::<Angle>(&[0x80]), Angle(128));
+    }
+
+    #[test]
+    fn bit_set() {
+        let mut bit_set = BitSet::new();
+        bit_set.set(0, true);
+        bit_set.set(63, true);
+        bit_set.set(64, true);
+        bit_set.set(200, false);
+
+        assert_eq!(bit_set.0, [i64::MIN | 1, 1]);
+        assert!(bit_set.get(63) && bit_set.get(64));
+        assert!(!bit_set.get(1) && !bit_set.get(1000));
+
+        let mut bytes = vec![2];
+        bytes.extend((i64::MIN | 1).to_be_bytes());
+        bytes.extend(1i64.to_be_bytes());
+        assert_eq!(write(&bit_set), bytes);
+        assert_eq!(read::<BitSet>(&bytes), bit_set);
+
+        assert_eq!(write(&BitSet::new()), [0]);
+    }
+
+    #[test]
+    fn fixed_bit_set() {
+        // 20 bits
+        let mut bit_set = FixedBitSet::<3>::new();
+        bit_set.set(0, true);
+        bit_set.set(9, true);
+        bit_set.set(19, true);
+        bit_set.set(9, false);
+
+        assert_eq!(write(&bit_set), [0x01, 0x00, 0x08]);
+        assert_eq!(read::<FixedBitSet<3>>(&[0x01, 0x00, 0x08]), bit_set);
+        assert!(FixedBitSet::<3>::buf_read(&mut &[0u8; 2][..]).is_err());
+    }
+
+    #[test]
+    fn fixed_point() {
+        assert_eq!(FixedPoint::<i32>::from_f64(1.5), FixedPoint(48));
+        assert_eq!(FixedPoint::<i32>::from_f64(-0.03125), FixedPoint(-1));
+        assert_eq!(FixedPoint(i32::MIN).to_f64(), -67108864.0);
+        assert_eq!(FixedPoint::<i8>::from_f64(3.96875), FixedPoint(127));
+        assert_eq!(FixedPoint::<i8>::from_f64(-4.0), FixedPoint(-128));
+        assert_eq!(FixedPoint::<i8>::from_f64(100.0), FixedPoint(127));
+
+        assert_eq!(write(&FixedPoint(-32i32)), [0xff, 0xff, 0xff, 0xe0]);
+        assert_eq!(read::<FixedPoint<i8>>(&[0x10]).to_f64(), 0.5);
+    }
+
+    #[test]
+    fn identifier_parts() {
+        let stone: Identifier = "stone".parse().unwrap();
+        assert_eq!(stone.namespace(), "minecraft");
+        assert_eq!(stone.path(), "stone");
+        assert_eq!(stone.as_str(), "stone");
+        assert_eq!(stone.to_string(), "minecraft:stone");
+
+        let custom = Identifier::new("my_mod:blocks/ore.1-b").unwrap();
+        assert_eq!(custom.namespace(), "my_mod");
+        assert_eq!(custom.path(), "blocks/ore.1-b");
+
+        assert_eq!(
+            Identifier::new(":stone").unwrap().to_string(),
+            "minecraft:stone"
+        );
+    }
+
+    #[test]
+    fn identifier_equality() {
+        use std::collections::HashSet;
+
+        let stone = identifier!("stone");
+        assert_eq!(stone, identifier!("minecraft:stone"));
+        assert_eq!(stone, "minecraft:stone");
+        assert_eq!(identifier!("minecraft:stone"), "stone");
+        assert_ne!(stone, identifier!("other:stone"));
+
+        let set: HashSet<_> = vec![stone, identifier!("minecraft:stone")]
+            .into_iter()
+            .collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn identifier_validation() {
+        for valid in ["a", "a:b", "a.b-c_d:e/f.g", "0:9", "minecraft:"] {
+            assert!(Identifier::is_valid(valid), "{}", valid);
+        }
+
+        for invalid in ["A", "a:B", "a/b:c", "a:b:c", "a b", "é", "MC|Brand"] {
+            assert!(Identifier::new(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn identifier_decoding() {
+        let bytes = write(&identifier!("stone"));
+        assert_eq!(bytes, b"\x05stone");
+
+        let lenient: Identifier = read(b"\x08MC|Brand");
+        assert_eq!(lenient.as_str(), "MC|Brand");
+        assert_eq!(write(&lenient), b"\x08MC|Brand");
+
+        assert!(matches!(
+            proxy::strict_identifier::buf_read(&mut &b"\x08MC|Brand"[..]),
+            Err(ReadError::InvalidIdentifier(_))
+        ));
+        assert_eq!(
+            proxy::strict_identifier::buf_read(&mut &bytes[..]).unwrap(),
+            "minecraft:stone"
+        );
+    }
+
+    #[test]
+    fn bytes_string_aliases_source() {
+        let data = Bytes::from_static(b"\x05hello rest");
+        let mut buf = data.clone();
+
+        let string = BytesString::buf_read(&mut buf).unwrap();
+        assert_eq!(string, "hello");
+        assert_eq!(string.as_bytes().as_ptr(), data[1..].as_ptr());
+        assert_eq!(buf, &b" rest"[..]);
+
+        assert_eq!(write(&string), &data[..6]);
+    }
+
+    #[test]
+    fn bytes_string_invalid_utf8() {
+        let mut buf = Bytes::from_static(&[2, 0xc3, 0x28]);
+        assert!(matches!(
+            BytesString::buf_read(&mut buf),
+            Err(ReadError::StrUtf8Error(_))
+        ));
+
+        assert!(BytesString::from_utf8(Bytes::from_static(&[0xff])).is_err());
+    }
+}
@@ -1,7 +1,29 @@
 use super::ensure_remaining;
 use super::BufType;
 use super::Result;
-use bytes::{Buf, BufMut};
+use bytes::{Buf, BufMut, Bytes};
+
+/// What the byte proxies can read into.
+///
+/// `Bytes` is a view into the buffer when reading from `Bytes`, like a packet body from
+/// `Connection::next_event`, instead of a copy.
+pub trait FromBuf: Sized {
+    fn from_buf<B: Buf>(buf: &mut B, len: usize) -> Self;
+}
+
+impl FromBuf for Vec<u8> {
+    fn from_buf<B: Buf>(buf: &mut B, len: usize) -> Self {
+        let mut bytes = vec![0; len];
+        buf.copy_to_slice(&mut bytes);
+        bytes
+    }
+}
+
+impl FromBuf for Bytes {
+    fn from_buf<B: Buf>(buf: &mut B, len: usize) -> Self {
+        buf.copy_to_bytes(len)
+    }
+}
 
 pub mod i32_as_v32 {
     use super::{super::v32, *};
@@ -17,21 +39,88 @@ pub mod i32_as_v32 {
     pub fn buf_write<B: BufMut>(value: &i32, buf: &mut B) {
         v32(*value).buf_write(buf)
     }
+
+    pub fn encoded_len(value: &i32) -> usize {
+        v32(*value).encoded_len()
+    }
+}
+
+pub mod i64_as_v64 {
+    use super::{super::v64, *};
+
+    pub fn buf_read<B: Buf>(buf: &mut B) -> Result<i64> {
+        self::buf_read_len(buf).map(|value| value.0)
+    }
+
+    pub fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(i64, usize)> {
+        v64::buf_read_len(buf).map(|(value, len)| (value.0, len))
+    }
+
+    pub fn buf_write<B: BufMut>(value: &i64, buf: &mut B) {
+        v64(*value).buf_write(buf)
+    }
+
+    pub fn encoded_len(value: &i64) -> usize {
+        v64(*value).encoded_len()
+    }
+}
+
+/// `Position` with the x/y/z packing from before 1.14.
+pub mod legacy_position {
+    use super::{super::Position, *};
+
+    pub fn buf_read<B: Buf>(buf: &mut B) -> Result<Position> {
+        self::buf_read_len(buf).map(|value| value.0)
+    }
+
+    pub fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Position, usize)> {
+        i64::buf_read_len(buf).map(|(packed, len)| (Position::from_legacy_packed(packed), len))
+    }
+
+    pub fn buf_write<B: BufMut>(value: &Position, buf: &mut B) {
+        value.to_legacy_packed().buf_write(buf)
+    }
+
+    pub fn encoded_len(_value: &Position) -> usize {
+        8
+    }
+}
+
+/// `Identifier` that fails to read when it's invalid.
+pub mod strict_identifier {
+    use super::{super::Identifier, *};
+
+    pub fn buf_read<B: Buf>(buf: &mut B) -> Result<Identifier> {
+        self::buf_read_len(buf).map(|value| value.0)
+    }
+
+    pub fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Identifier, usize)> {
+        let (identifier, len) = String::buf_read_len(buf)?;
+
+        Ok((Identifier::new(identifier)?, len))
+    }
+
+    pub fn buf_write<B: BufMut>(value: &Identifier, buf: &mut B) {
+        value.buf_write(buf)
+    }
+
+    pub fn encoded_len(value: &Identifier) -> usize {
+        value.encoded_len()
+    }
 }
 
 pub mod length_prefix_bytes {
     use super::*;
 
-    pub fn buf_read<B: Buf>(buf: &mut B) -> Result<Vec<u8>> {
+    pub fn buf_read<B: Buf, T: FromBuf>(buf: &mut B) -> Result<T> {
         self::buf_read_len(buf).map(|value| value.0)
     }
 
-    pub fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Vec<u8>, usize)> {
+    pub fn buf_read_len<B: Buf, T: FromBuf>(buf: &mut B) -> Result<(T, usize)> {
         let (buf_len, buf_len_len) = i32_as_v32::buf_read_len(buf)?;
         ensure_remaining(buf, buf_len as _)?;
 
-        let mut bytes = vec![0; buf_len as _];
-        buf.copy_to_slice(&mut bytes);
+        let bytes = T::from_buf(buf, buf_len as _);
 
         Ok((bytes, buf_len as usize + buf_len_len))
     }
@@ -42,21 +131,25 @@ pub mod length_prefix_bytes {
         i32_as_v32::buf_write(&(bytes.len() as _), buf);
         buf.put_slice(bytes);
     }
+
+    pub fn encoded_len<BA: AsRef<[u8]>>(bytes: BA) -> usize {
+        let len = bytes.as_ref().len();
+        i32_as_v32::encoded_len(&(len as _)) + len
+    }
 }
 
 pub mod u16_length_prefix_bytes {
     use super::*;
 
-    pub fn buf_read<B: Buf>(buf: &mut B) -> Result<Vec<u8>> {
+    pub fn buf_read<B: Buf, T: FromBuf>(buf: &mut B) -> Result<T> {
         self::buf_read_len(buf).map(|value| value.0)
     }
 
-    pub fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Vec<u8>, usize)> {
+    pub fn buf_read_len<B: Buf, T: FromBuf>(buf: &mut B) -> Result<(T, usize)> {
         let (buf_len, buf_len_len) = u16::buf_read_len(buf)?;
         ensure_remaining(buf, buf_len as _)?;
 
-        let mut bytes = vec![0; buf_len as _];
-        buf.copy_to_slice(&mut bytes);
+        let bytes = T::from_buf(buf, buf_len as _);
 
         Ok((bytes, buf_len as usize + buf_len_len))
     }
@@ -67,6 +160,10 @@ pub mod u16_length_prefix_bytes {
         u16::buf_write(&(bytes.len() as _), buf);
         buf.put_slice(bytes);
     }
+
+    pub fn encoded_len<BA: AsRef<[u8]>>(bytes: BA) -> usize {
+        2 + bytes.as_ref().len()
+    }
 }
 
 pub mod length_prefix_array {
@@ -102,35 +199,44 @@ pub mod length_prefix_array {
             value.buf_write(buf);
         }
     }
+
+    pub fn encoded_len<T: BufType, A: AsRef<[T]>>(value: A) -> usize {
+        let array = value.as_ref();
+
+        i32_as_v32::encoded_len(&(array.len() as _))
+            + array.iter().map(BufType::encoded_len).sum::<usize>()
+    }
 }
 
 pub mod remaining_bytes {
     use super::*;
 
-    pub fn buf_read<B: Buf>(buf: &mut B) -> Result<Vec<u8>> {
+    pub fn buf_read<B: Buf, T: FromBuf>(buf: &mut B) -> Result<T> {
         self::buf_read_len(buf).map(|value| value.0)
     }
 
-    pub fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Vec<u8>, usize)> {
+    pub fn buf_read_len<B: Buf, T: FromBuf>(buf: &mut B) -> Result<(T, usize)> {
         let len = buf.remaining();
-        let mut bytes = vec![0; len];
-        buf.copy_to_slice(&mut bytes);
-        Ok((bytes, len))
+        Ok((T::from_buf(buf, len), len))
     }
 
     pub fn buf_write<B: BufMut, BA: AsRef<[u8]>>(bytes: BA, buf: &mut B) {
         buf.put_slice(bytes.as_ref());
     }
+
+    pub fn encoded_len<BA: AsRef<[u8]>>(bytes: BA) -> usize {
+        bytes.as_ref().len()
+    }
 }
 
 pub mod option_length_prefix_bytes {
     use super::*;
 
-    pub fn buf_read<B: Buf>(buf: &mut B) -> Result<Option<Vec<u8>>> {
+    pub fn buf_read<B: Buf, T: FromBuf>(buf: &mut B) -> Result<Option<T>> {
         self::buf_read_len(buf).map(|value| value.0)
     }
 
-    pub fn buf_read_len<B: Buf>(buf: &mut B) -> Result<(Option<Vec<u8>>, usize)> {
+    pub fn buf_read_len<B: Buf, T: FromBuf>(buf: &mut B) -> Result<(Option<T>, usize)> {
         let (has_value, mut total_value_len) = bool::buf_read_len(buf)?;
 
         let value = if has_value {
@@ -151,4 +257,8 @@ pub mod option_length_prefix_bytes {
             length_prefix_bytes::buf_write(value, buf);
         }
     }
+
+    pub fn encoded_len<BA: AsRef<[u8]>>(value: &Option<BA>) -> usize {
+        1 + value.as_ref().map_or(0, length_prefix_bytes::encoded_len)
+    }
 }
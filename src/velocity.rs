@@ -0,0 +1,503 @@
+// https://github.com/PaperMC/Velocity/blob/dev/3.0.0/proxy/src/main/java/com/velocitypowered/proxy/connection/backend/VelocityServerConnection.java
+// https://github.com/PaperMC/Paper/blob/master/patches/server/0001-Velocity-support.patch
+
+use std::net::IpAddr;
+
+use bytes::{Buf, BufMut};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{
+    packet::prelude::*,
+    versions::v393::packets::login::{c2s::LoginPluginResponse, s2c::LoginPluginRequest},
+};
+
+pub const CHANNEL: &str = "velocity:player_info";
+
+pub const MODERN_DEFAULT: i32 = 1;
+pub const MODERN_WITH_KEY: i32 = 2;
+pub const MODERN_WITH_KEY_V2: i32 = 3;
+pub const MODERN_LAZY_SESSION: i32 = 4;
+pub const MODERN_MAX_VERSION: i32 = MODERN_LAZY_SESSION;
+
+const SIGNATURE_LENGTH: usize = 32;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VelocityError {
+    #[error("response isn't for the player info request")]
+    WrongMessageId,
+
+    #[error("proxy didn't understand the player info request, is modern forwarding enabled?")]
+    NotForwarded,
+
+    #[error("player info is too short to be signed")]
+    MissingSignature,
+
+    #[error("player info signature doesn't match, is the forwarding secret correct?")]
+    InvalidSignature,
+
+    #[error("unsupported forwarding version: {0}")]
+    UnsupportedVersion(i32),
+
+    #[error("invalid client address: {0:?}")]
+    InvalidAddress(String),
+
+    #[error("read error: {0}")]
+    ReadError(#[from] ReadError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct Property {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+/// The player's chat session key, only forwarded in versions 2 & 3.
+#[derive(Debug, Clone, PartialEq, Eq, BufType)]
+pub struct PlayerKey {
+    /// Milliseconds since the unix epoch.
+    pub expires_at: i64,
+    #[buftype(with = "length_prefix_bytes")]
+    pub public_key: Vec<u8>,
+    #[buftype(with = "length_prefix_bytes")]
+    pub key_signature: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerInfo {
+    /// On the proxy side, the newest version the player can be forwarded with:
+    /// `MODERN_LAZY_SESSION` for 1.19.3+ clients, `MODERN_WITH_KEY_V2` for linked (1.19.1+) keys,
+    /// `MODERN_WITH_KEY` for 1.19 keys, otherwise `MODERN_DEFAULT`.
+    pub version: i32,
+    pub client_address: IpAddr,
+    pub uuid: Uuid,
+    pub username: String,
+    pub properties: Vec<Property>,
+
+    pub player_key: Option<PlayerKey>,
+    /// Only forwarded in version 3.
+    pub signer_uuid: Option<Uuid>,
+}
+
+impl PlayerInfo {
+    pub fn new(client_address: IpAddr, uuid: Uuid, username: String) -> Self {
+        PlayerInfo {
+            version: MODERN_DEFAULT,
+            client_address,
+            uuid,
+            username,
+            properties: Vec::new(),
+            player_key: None,
+            signer_uuid: None,
+        }
+    }
+
+    fn has_key(version: i32) -> bool {
+        (MODERN_WITH_KEY..MODERN_LAZY_SESSION).contains(&version)
+    }
+
+    pub fn read<B: Buf>(buf: &mut B) -> Result<Self, VelocityError> {
+        let version = i32_as_v32::buf_read(buf)?;
+        if !(MODERN_DEFAULT..=MODERN_MAX_VERSION).contains(&version) {
+            return Err(VelocityError::UnsupportedVersion(version));
+        }
+
+        let client_address = String::buf_read(buf)?;
+        let client_address = client_address
+            .parse()
+            .map_err(|_| VelocityError::InvalidAddress(client_address))?;
+
+        let uuid = Uuid::buf_read(buf)?;
+        let username = String::buf_read(buf)?;
+        let properties = length_prefix_array::buf_read(buf)?;
+
+        let player_key = if Self::has_key(version) {
+            Some(PlayerKey::buf_read(buf)?)
+        } else {
+            None
+        };
+
+        let signer_uuid = if Self::has_key(version) && version >= MODERN_WITH_KEY_V2 {
+            Option::<Uuid>::buf_read(buf)?
+        } else {
+            None
+        };
+
+        Ok(PlayerInfo {
+            version,
+            client_address,
+            uuid,
+            username,
+            properties,
+            player_key,
+            signer_uuid,
+        })
+    }
+
+    /// Writes the unsigned player info.
+    ///
+    /// # Panics
+    /// If the version requires a player key and none is set.
+    pub fn write<B: BufMut>(&self, buf: &mut B) {
+        i32_as_v32::buf_write(&self.version, buf);
+        self.client_address.to_string().buf_write(buf);
+        self.uuid.buf_write(buf);
+        self.username.buf_write(buf);
+        length_prefix_array::buf_write(&self.properties, buf);
+
+        if Self::has_key(self.version) {
+            self.player_key
+                .as_ref()
+                .expect("forwarding version requires a player key")
+                .buf_write(buf);
+
+            if self.version >= MODERN_WITH_KEY_V2 {
+                self.signer_uuid.buf_write(buf);
+            }
+        }
+    }
+
+    /// Signs the player info with the forwarding secret, this is what the proxy sends.
+    pub fn to_signed(&self, secret: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.write(&mut data);
+
+        let mut signed = Vec::with_capacity(SIGNATURE_LENGTH + data.len());
+        signed.extend_from_slice(&hmac(secret).chain_update(&data).finalize().into_bytes());
+        signed.extend_from_slice(&data);
+
+        signed
+    }
+
+    /// Checks the signature against the forwarding secret before reading the player info.
+    pub fn from_signed(signed: &[u8], secret: &[u8]) -> Result<Self, VelocityError> {
+        if signed.len() < SIGNATURE_LENGTH {
+            return Err(VelocityError::MissingSignature);
+        }
+
+        let (signature, mut data) = signed.split_at(SIGNATURE_LENGTH);
+        hmac(secret)
+            .chain_update(data)
+            .verify_slice(signature)
+            .map_err(|_| VelocityError::InvalidSignature)?;
+
+        Self::read(&mut data)
+    }
+}
+
+fn hmac(secret: &[u8]) -> HmacSha256 {
+    // hmac accepts keys of any length
+    HmacSha256::new_from_slice(secret).unwrap()
+}
+
+//
+// Backend side
+//
+
+/// Request for the proxy to send player info, sent by the backend instead of `EncryptionRequest`.
+pub fn player_info_request(message_id: i32, version: i32) -> LoginPluginRequest {
+    LoginPluginRequest {
+        message_id,
+        channel: crate::identifier!(CHANNEL),
+        data: vec![version as u8].into(),
+    }
+}
+
+/// Reads and verifies the proxy's response to [`player_info_request`].
+pub fn read_player_info_response(
+    response: &LoginPluginResponse,
+    message_id: i32,
+    secret: &[u8],
+) -> Result<PlayerInfo, VelocityError> {
+    if response.message_id != message_id {
+        return Err(VelocityError::WrongMessageId);
+    }
+
+    if !response.successful {
+        return Err(VelocityError::NotForwarded);
+    }
+
+    PlayerInfo::from_signed(&response.data, secret)
+}
+
+//
+// Proxy side
+//
+
+/// The forwarding version the backend asked for, if this is a player info request.
+pub fn requested_version(request: &LoginPluginRequest) -> Option<i32> {
+    if request.channel != CHANNEL {
+        return None;
+    }
+
+    Some(
+        request
+            .data
+            .first()
+            .map_or(MODERN_DEFAULT, |version| *version as i32),
+    )
+}
+
+/// The version to forward the player with, following Velocity's `findForwardingVersion`.
+///
+/// Versions aren't backwards compatible, a backend that asked for less than what the player has
+/// gets `MODERN_DEFAULT` rather than an older key format, and key versions need a key.
+pub fn forwarding_version(requested_version: i32, player_info: &PlayerInfo) -> i32 {
+    let requested_version = requested_version.min(MODERN_MAX_VERSION);
+    let available = match player_info.version {
+        version if PlayerInfo::has_key(version) && player_info.player_key.is_none() => {
+            MODERN_DEFAULT
+        }
+        version => version,
+    };
+
+    match available {
+        MODERN_LAZY_SESSION | MODERN_WITH_KEY_V2 | MODERN_WITH_KEY
+            if requested_version >= available =>
+        {
+            available
+        }
+        _ => MODERN_DEFAULT,
+    }
+}
+
+/// Answers a player info request, `None` if the request isn't one.
+///
+/// The player info is sent with the version from [`forwarding_version`].
+pub fn player_info_response(
+    request: &LoginPluginRequest,
+    player_info: &PlayerInfo,
+    secret: &[u8],
+) -> Option<LoginPluginResponse> {
+    let requested_version = requested_version(request)?;
+
+    let mut player_info = player_info.clone();
+    player_info.version = forwarding_version(requested_version, &player_info);
+
+    Some(LoginPluginResponse {
+        message_id: request.message_id,
+        successful: true,
+        data: player_info.to_signed(secret).into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"s3cr3t";
+
+    // hmac-sha256 of the player info below, generated independently
+    const SIGNATURE: [u8; 32] = [
+        0xea, 0x50, 0x30, 0x36, 0xe7, 0x17, 0x9c, 0x4d, 0xbf, 0x47, 0x96, 0xcc, 0x55, 0x35, 0x2d,
+        0x4e, 0x91, 0x9e, 0x3b, 0xc7, 0x39, 0xb5, 0x9c, 0xaf, 0x18, 0xa5, 0x64, 0x22, 0x7c, 0x90,
+        0x59, 0xa3,
+    ];
+
+    fn player_info() -> PlayerInfo {
+        let mut player_info = PlayerInfo::new(
+            "127.0.0.1".parse().unwrap(),
+            Uuid::parse_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap(),
+            "Notch".to_owned(),
+        );
+
+        player_info.properties.push(Property {
+            name: "textures".to_owned(),
+            value: "abc".to_owned(),
+            signature: Some("sig".to_owned()),
+        });
+
+        player_info
+    }
+
+    #[test]
+    fn signature_matches() {
+        let signed = player_info().to_signed(SECRET);
+
+        assert_eq!(signed[..SIGNATURE_LENGTH], SIGNATURE);
+    }
+
+    #[test]
+    fn signed_round_trip() {
+        let signed = player_info().to_signed(SECRET);
+
+        assert_eq!(
+            PlayerInfo::from_signed(&signed, SECRET).unwrap(),
+            player_info()
+        );
+    }
+
+    #[test]
+    fn wrong_secret_is_rejected() {
+        let signed = player_info().to_signed(SECRET);
+
+        assert!(matches!(
+            PlayerInfo::from_signed(&signed, b"not the secret"),
+            Err(VelocityError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn tampered_data_is_rejected() {
+        let mut signed = player_info().to_signed(SECRET);
+        *signed.last_mut().unwrap() ^= 1;
+
+        assert!(matches!(
+            PlayerInfo::from_signed(&signed, SECRET),
+            Err(VelocityError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn key_round_trip() {
+        let mut player_info = player_info();
+        player_info.version = MODERN_WITH_KEY_V2;
+        player_info.player_key = Some(PlayerKey {
+            expires_at: 1_700_000_000_000,
+            public_key: vec![1, 2, 3],
+            key_signature: vec![4, 5, 6],
+        });
+        player_info.signer_uuid = Some(player_info.uuid);
+
+        let signed = player_info.to_signed(SECRET);
+
+        assert_eq!(
+            PlayerInfo::from_signed(&signed, SECRET).unwrap(),
+            player_info
+        );
+    }
+
+    #[test]
+    fn backend_and_proxy_exchange() {
+        let request = player_info_request(7, MODERN_DEFAULT);
+        assert_eq!(requested_version(&request), Some(MODERN_DEFAULT));
+
+        let response = player_info_response(&request, &player_info(), SECRET).unwrap();
+
+        let received = read_player_info_response(&response, 7, SECRET).unwrap();
+        assert_eq!(received, player_info());
+
+        assert!(matches!(
+            read_player_info_response(&response, 8, SECRET),
+            Err(VelocityError::WrongMessageId)
+        ));
+    }
+
+    #[test]
+    fn proxy_downgrades_to_requested_version() {
+        let mut player_info = player_info();
+        player_info.version = MODERN_LAZY_SESSION;
+
+        let request = player_info_request(0, MODERN_DEFAULT);
+        let response = player_info_response(&request, &player_info, SECRET).unwrap();
+
+        let received = read_player_info_response(&response, 0, SECRET).unwrap();
+        assert_eq!(received.version, MODERN_DEFAULT);
+    }
+
+    #[test]
+    fn forwarding_version_follows_velocity() {
+        let key = PlayerKey {
+            expires_at: 0,
+            public_key: vec![1],
+            key_signature: vec![2],
+        };
+        let with = |version, player_key: Option<&PlayerKey>| {
+            let mut player_info = player_info();
+            player_info.version = version;
+            player_info.player_key = player_key.cloned();
+            player_info
+        };
+
+        let cases = [
+            // requested, player's version, key, forwarded
+            (
+                MODERN_LAZY_SESSION,
+                MODERN_LAZY_SESSION,
+                None,
+                MODERN_LAZY_SESSION,
+            ),
+            (
+                MODERN_MAX_VERSION + 1,
+                MODERN_LAZY_SESSION,
+                None,
+                MODERN_LAZY_SESSION,
+            ),
+            (
+                MODERN_WITH_KEY_V2,
+                MODERN_LAZY_SESSION,
+                None,
+                MODERN_DEFAULT,
+            ),
+            (
+                MODERN_LAZY_SESSION,
+                MODERN_WITH_KEY_V2,
+                Some(&key),
+                MODERN_WITH_KEY_V2,
+            ),
+            // a linked key can't be sent in the version 2 format
+            (
+                MODERN_WITH_KEY,
+                MODERN_WITH_KEY_V2,
+                Some(&key),
+                MODERN_DEFAULT,
+            ),
+            (
+                MODERN_WITH_KEY_V2,
+                MODERN_WITH_KEY,
+                Some(&key),
+                MODERN_WITH_KEY,
+            ),
+            (
+                MODERN_WITH_KEY,
+                MODERN_WITH_KEY,
+                Some(&key),
+                MODERN_WITH_KEY,
+            ),
+            // no key to send
+            (MODERN_WITH_KEY_V2, MODERN_WITH_KEY_V2, None, MODERN_DEFAULT),
+            (MODERN_WITH_KEY, MODERN_WITH_KEY, None, MODERN_DEFAULT),
+            (MODERN_DEFAULT, MODERN_WITH_KEY, Some(&key), MODERN_DEFAULT),
+        ];
+
+        for (requested, version, player_key, forwarded) in cases {
+            assert_eq!(
+                forwarding_version(requested, &with(version, player_key)),
+                forwarded,
+                "requested {} for a version {} player",
+                requested,
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn linked_key_not_downgraded() {
+        let mut player_info = player_info();
+        player_info.version = MODERN_WITH_KEY_V2;
+        player_info.player_key = Some(PlayerKey {
+            expires_at: 1_700_000_000_000,
+            public_key: vec![1, 2, 3],
+            key_signature: vec![4, 5, 6],
+        });
+
+        let request = player_info_request(0, MODERN_WITH_KEY);
+        let response = player_info_response(&request, &player_info, SECRET).unwrap();
+
+        let received = read_player_info_response(&response, 0, SECRET).unwrap();
+        assert_eq!(received.version, MODERN_DEFAULT);
+        assert_eq!(received.player_key, None);
+    }
+
+    #[test]
+    fn other_channels_are_ignored() {
+        let mut request = player_info_request(0, MODERN_DEFAULT);
+        request.channel = crate::identifier!("fml:loginwrapper");
+
+        assert!(player_info_response(&request, &player_info(), SECRET).is_none());
+    }
+}
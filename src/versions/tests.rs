@@ -0,0 +1,58 @@
+use bytes::Bytes;
+
+use crate::packet::PacketFromIdBody;
+
+/// Zeros read as empty or `None` values, ones as single element ones.
+const BODIES: [&[u8]; 2] = [&[0; 256], &[1; 256]];
+
+/// Reads every packet of `Packets` from each body, and checks the ones that could be read
+/// write as many bytes as `body_len` says.
+macro_rules! check_body_len {
+    ($($packets:ty),* $(,)?) => {
+        $(
+            for (id, name) in <$packets>::PACKETS {
+                let mut read = false;
+                for body in BODIES.iter() {
+                    let packet = match <$packets>::from_id_body(*id, Bytes::from_static(body)) {
+                        Ok(packet) => packet,
+                        Err(_) => continue,
+                    };
+                    read = true;
+
+                    let mut body = Vec::new();
+                    packet.write_body(&mut body);
+                    assert_eq!(packet.body_len(), body.len(), "{} in {}", name, stringify!($packets));
+                }
+
+                assert!(read, "{} in {} couldn't be read", name, stringify!($packets));
+            }
+        )*
+    };
+}
+
+macro_rules! versions {
+    ([$($version:ident),*], [$($configuration_version:ident),*]) => {
+        $(
+            check_body_len!(
+                super::$version::packets::status::s2c::Packets,
+                super::$version::packets::status::c2s::Packets,
+                super::$version::packets::login::s2c::Packets,
+                super::$version::packets::login::c2s::Packets,
+            );
+        )*
+        $(
+            check_body_len!(
+                super::$configuration_version::packets::configuration::s2c::Packets,
+                super::$configuration_version::packets::configuration::c2s::Packets,
+            );
+        )*
+    };
+}
+
+#[test]
+fn body_len_matches_written() {
+    versions!(
+        [v3, v4, v5, v47, v107, v108, v109, v110, v210, v315, v316, v335, v338, v340, v393, v401, v404, v477, v480, v485, v490, v498, v573, v575, v578, v735, v736, v751, v753, v754, v755, v756, v757, v758, v759, v760, v761, v762, v763, v764, v765, v766, v767],
+        [v764, v765, v766, v767]
+    );
+}
@@ -3,7 +3,10 @@
 //
 
 pub mod s2c {
-    use crate::packet::prelude::*;
+    use crate::{
+        offline::{parse_uuid, UuidFormat},
+        packet::prelude::*,
+    };
 
     impl_packets_enum![Disconnect, EncryptionRequest, LoginSuccess];
 
@@ -15,7 +18,7 @@ pub mod s2c {
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x01)]
+    #[packet(id = 0x01, until = 5)]
     pub struct EncryptionRequest {
         pub server_id: String,
         #[buftype(with = "u16_length_prefix_bytes")]
@@ -25,11 +28,27 @@ pub mod s2c {
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x02)]
+    #[packet(id = 0x02, until = 578)]
     pub struct LoginSuccess {
         pub uuid: String,
         pub username: String,
     }
+
+    impl LoginSuccess {
+        /// Writes the uuid the way `protocol_version` expects, hyphenless before 1.7.6.
+        pub fn new(uuid: Uuid, username: String, protocol_version: i32) -> Self {
+            let format = UuidFormat::for_protocol(protocol_version).unwrap_or(UuidFormat::Hyphenated);
+
+            LoginSuccess {
+                uuid: format.format(uuid),
+                username,
+            }
+        }
+
+        pub fn parse_uuid(&self) -> Result<Uuid, uuid::Error> {
+            parse_uuid(&self.uuid)
+        }
+    }
 }
 
 //
@@ -42,13 +61,13 @@ pub mod c2s {
     impl_packets_enum![LoginStart, EncryptionResponse];
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x00)]
+    #[packet(id = 0x00, until = 758)]
     pub struct LoginStart {
         pub username: String,
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x01)]
+    #[packet(id = 0x01, until = 5)]
     pub struct EncryptionResponse {
         #[buftype(with = "u16_length_prefix_bytes")]
         pub shared_secret: Vec<u8>,
@@ -8,13 +8,13 @@ pub mod s2c {
     impl_packets_enum![Response, Pong];
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x00)]
+    #[packet(id = 0x00, until = 758)]
     pub struct Response {
         pub response: String,
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x01)]
+    #[packet(id = 0x01, until = 758)]
     pub struct Pong {
         pub payload: i64,
     }
@@ -30,11 +30,11 @@ pub mod c2s {
     impl_packets_enum![Request, Ping];
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x00)]
+    #[packet(id = 0x00, until = 758)]
     pub struct Request;
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x01)]
+    #[packet(id = 0x01, until = 758)]
     pub struct Ping {
         pub payload: i64,
     }
@@ -30,10 +30,11 @@ pub mod s2c {
     #[derive(Debug, Packet, BufType)]
     #[packet(id = 0x04)]
     pub struct LoginPluginRequest {
+        #[buftype(with = "i32_as_v32")]
         pub message_id: i32,
         pub channel: Identifier,
         #[buftype(with = "remaining_bytes")]
-        pub data: Vec<u8>,
+        pub data: Bytes,
     }
 }
 
@@ -59,6 +60,6 @@ pub mod c2s {
         pub message_id: i32,
         pub successful: bool,
         #[buftype(with = "remaining_bytes")]
-        pub data: Vec<u8>,
+        pub data: Bytes,
     }
 }
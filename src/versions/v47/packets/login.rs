@@ -13,7 +13,7 @@ pub mod s2c {
     pub use super::prev::s2c::Disconnect;
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x01)]
+    #[packet(id = 0x01, until = 765)]
     pub struct EncryptionRequest {
         pub server_id: String,
         #[buftype(with = "length_prefix_bytes")]
@@ -46,7 +46,7 @@ pub mod c2s {
     pub use super::prev::c2s::LoginStart;
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x01)]
+    #[packet(id = 0x01, until = 758)]
     pub struct EncryptionResponse {
         #[buftype(with = "length_prefix_bytes")]
         pub shared_secret: Vec<u8>,
@@ -22,7 +22,7 @@ pub mod s2c {
     pub use super::prev::s2c::EncryptionRequest;
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x02)]
+    #[packet(id = 0x02, until = 758)]
     pub struct LoginSuccess {
         pub uuid: Uuid,
         pub username: String,
@@ -22,7 +22,7 @@ pub mod s2c {
     pub use super::prev::s2c::EncryptionRequest;
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x02)]
+    #[packet(id = 0x02, until = 765)]
     pub struct LoginSuccess {
         pub uuid: Uuid,
         pub username: String,
@@ -58,7 +58,7 @@ pub mod c2s {
     impl_packets_enum![LoginStart, EncryptionResponse, LoginPluginResponse];
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x00)]
+    #[packet(id = 0x00, until = 759)]
     pub struct LoginStart {
         pub username: String,
         pub signature_data: Option<login_start::SignatureData>,
@@ -79,7 +79,7 @@ pub mod c2s {
 
     // 0x01
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x01)]
+    #[packet(id = 0x01, until = 760)]
     pub struct EncryptionResponse {
         #[buftype(with = "length_prefix_bytes")]
         pub shared_secret: Vec<u8>,
@@ -89,57 +89,18 @@ pub mod c2s {
     pub mod encryption_response {
         use crate::packet::prelude::*;
 
-        #[derive(Debug)]
+        #[derive(Debug, BufType)]
+        #[buftype(discriminant = "bool")]
         pub enum VerifyTokenOrMessageSignature {
-            VerifyToken(Vec<u8>),
+            #[buftype(value = true)]
+            VerifyToken(#[buftype(with = "length_prefix_bytes")] Vec<u8>),
+            #[buftype(value = false)]
             MessageSignature {
                 salt: i64,
+                #[buftype(with = "length_prefix_bytes")]
                 message_signature: Vec<u8>,
             },
         }
-
-        impl BufType for VerifyTokenOrMessageSignature {
-            fn buf_read_len<B: bytes::Buf>(buf: &mut B) -> Result<(Self, usize), ReadError> {
-                let (has_verify_token, has_verify_token_len) = bool::buf_read_len(buf)?;
-                let (verify_token_or_message_signature, vtms_len) = if has_verify_token {
-                    let (verify_token, verify_token_len) = length_prefix_bytes::buf_read_len(buf)?;
-                    (Self::VerifyToken(verify_token), verify_token_len)
-                } else {
-                    let (salt, salt_len) = i64::buf_read_len(buf)?;
-                    let (message_signature, message_signature_len) =
-                        length_prefix_bytes::buf_read_len(buf)?;
-                    (
-                        Self::MessageSignature {
-                            salt,
-                            message_signature,
-                        },
-                        salt_len + message_signature_len,
-                    )
-                };
-
-                Ok((
-                    verify_token_or_message_signature,
-                    has_verify_token_len + vtms_len,
-                ))
-            }
-
-            fn buf_write<B: bytes::BufMut>(&self, buf: &mut B) {
-                match self {
-                    Self::VerifyToken(verify_token) => {
-                        bool::buf_write(&true, buf);
-                        length_prefix_bytes::buf_write(verify_token, buf);
-                    }
-                    Self::MessageSignature {
-                        salt,
-                        message_signature,
-                    } => {
-                        bool::buf_write(&false, buf);
-                        salt.buf_write(buf);
-                        length_prefix_bytes::buf_write(message_signature, buf);
-                    }
-                }
-            }
-        }
     }
 
     // 0x02
@@ -16,7 +16,7 @@ pub mod c2s {
     impl_packets_enum![LoginStart, EncryptionResponse, LoginPluginResponse];
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x00)]
+    #[packet(id = 0x00, until = 763)]
     pub struct LoginStart {
         pub username: String,
         pub uuid: Option<Uuid>,
@@ -18,15 +18,15 @@ pub mod s2c {
     ];
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x00)]
+    #[packet(id = 0x00, until = 765)]
     pub struct ClientboundPluginMessage {
         pub channel: Identifier,
         #[buftype(with = "remaining_bytes")]
-        pub data: Vec<u8>,
+        pub data: Bytes,
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x01)]
+    #[packet(id = 0x01, until = 765)]
     pub struct Disconnect {
         // Text Component (NBT)
         #[buftype(with = "length_prefix_bytes")]
@@ -34,31 +34,31 @@ pub mod s2c {
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x02)]
+    #[packet(id = 0x02, until = 765)]
     pub struct FinishConfiguration;
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x03)]
+    #[packet(id = 0x03, until = 765)]
     pub struct ClientboundKeepAlive {
         pub keep_alive_id: i64,
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x04)]
+    #[packet(id = 0x04, until = 765)]
     pub struct Ping {
         pub id: i32,
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x05)]
+    #[packet(id = 0x05, until = 765)]
     pub struct RegistryData {
         // TODO: properly decode?
         #[buftype(with = "remaining_bytes")]
-        pub registry_codec: Vec<u8>,
+        pub registry_codec: Bytes,
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x06)]
+    #[packet(id = 0x06, until = 764)]
     pub struct ResourcePack {
         pub url: String,
         pub hash: String,
@@ -69,14 +69,14 @@ pub mod s2c {
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x07)]
+    #[packet(id = 0x07, until = 764)]
     pub struct FeatureFlags {
         #[buftype(with = "length_prefix_array")]
         pub feature_flags: Vec<Identifier>,
     }
 
     #[derive(Debug, Packet)]
-    #[packet(id = 0x08)]
+    #[packet(id = 0x08, until = 764)]
     pub struct UpdateTags {
         pub tags: HashMap<Identifier, Vec<update_tags::Tag>>,
     }
@@ -172,7 +172,7 @@ pub mod c2s {
     }
 
     pub mod client_information {
-        use crate::types::{v32_prefix_enum, BufType, ReadError};
+        use crate::packet::prelude::*;
 
         #[derive(Debug)]
         pub enum ChatMode {
@@ -192,7 +192,8 @@ pub mod c2s {
             }
         );
 
-        #[derive(Debug)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, BufType)]
+        #[buftype(bits = "u8")]
         pub struct DisplayedSkinParts {
             pub cape_enabled: bool,
             pub jacket_enabled: bool,
@@ -203,39 +204,6 @@ pub mod c2s {
             pub hat_enabled: bool,
         }
 
-        impl BufType for DisplayedSkinParts {
-            fn buf_read_len<B: bytes::Buf>(buf: &mut B) -> Result<(Self, usize), ReadError> {
-                let (mask, size) = u8::buf_read_len(buf)?;
-
-                #[rustfmt::skip]
-                let displayed_skin_parts = DisplayedSkinParts {
-                               cape_enabled: mask & 0b0000001 > 0,
-                             jacket_enabled: mask & 0b0000010 > 0,
-                        left_sleeve_enabled: mask & 0b0000100 > 0,
-                       right_sleeve_enabled: mask & 0b0001000 > 0,
-                     left_pants_leg_enabled: mask & 0b0010000 > 0,
-                    right_pants_leg_enabled: mask & 0b0100000 > 0,
-                                hat_enabled: mask & 0b1000000 > 0,
-                };
-
-                Ok((displayed_skin_parts, size))
-            }
-
-            fn buf_write<B: bytes::BufMut>(&self, buf: &mut B) {
-                #[rustfmt::skip]
-                #[allow(clippy::identity_op)]
-                let mask = (self.           cape_enabled as u8) << 0
-                             & (self.         jacket_enabled as u8) << 1
-                             & (self.    left_sleeve_enabled as u8) << 2
-                             & (self.   right_sleeve_enabled as u8) << 3
-                             & (self. left_pants_leg_enabled as u8) << 4
-                             & (self.right_pants_leg_enabled as u8) << 5
-                             & (self.            hat_enabled as u8) << 6;
-
-                mask.buf_write(buf);
-            }
-        }
-
         #[derive(Debug)]
         pub enum MainHand {
             Left,
@@ -253,31 +221,31 @@ pub mod c2s {
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x01)]
+    #[packet(id = 0x01, until = 765)]
     pub struct ServerboundPluginMessage {
         pub channel: Identifier,
         #[buftype(with = "remaining_bytes")]
-        pub data: Vec<u8>,
+        pub data: Bytes,
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x02)]
+    #[packet(id = 0x02, until = 765)]
     pub struct FinishConfiguration;
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x03)]
+    #[packet(id = 0x03, until = 765)]
     pub struct ServerboundKeepAlive {
         pub keep_alive_id: i64,
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x04)]
+    #[packet(id = 0x04, until = 765)]
     pub struct Pong {
         pub id: i32,
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x05)]
+    #[packet(id = 0x05, until = 764)]
     pub struct ResourcePackResponse {
         pub uuid: Uuid,
         pub result: resource_pack_response::Result,
@@ -307,3 +275,39 @@ pub mod c2s {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::c2s::client_information::DisplayedSkinParts;
+    use crate::types::BufType;
+
+    fn skin_parts(bits: [bool; 7]) -> DisplayedSkinParts {
+        DisplayedSkinParts {
+            cape_enabled: bits[0],
+            jacket_enabled: bits[1],
+            left_sleeve_enabled: bits[2],
+            right_sleeve_enabled: bits[3],
+            left_pants_leg_enabled: bits[4],
+            right_pants_leg_enabled: bits[5],
+            hat_enabled: bits[6],
+        }
+    }
+
+    #[test]
+    fn displayed_skin_parts() {
+        for (parts, byte) in [
+            (skin_parts([true; 7]), 0x7f),
+            (skin_parts([false; 7]), 0x00),
+            // cape, left sleeve, left pants leg and hat
+            (skin_parts([true, false, true, false, true, false, true]), 0x55),
+        ]
+        .iter()
+        {
+            let mut data = Vec::new();
+            parts.buf_write(&mut data);
+            assert_eq!(data, [*byte]);
+
+            assert_eq!(DisplayedSkinParts::buf_read(&mut &data[..]).unwrap(), *parts);
+        }
+    }
+}
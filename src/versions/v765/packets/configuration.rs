@@ -39,7 +39,7 @@ pub mod s2c {
     pub use super::prev::s2c::RegistryData;
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x06)]
+    #[packet(id = 0x06, until = 765)]
     pub struct RemoveResourcePack {
         // None = remove all
         // Some(uuid) = remove specific
@@ -47,7 +47,7 @@ pub mod s2c {
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x07)]
+    #[packet(id = 0x07, until = 765)]
     pub struct AddResourcePack {
         pub uuid: Uuid,
         pub url: String,
@@ -59,14 +59,14 @@ pub mod s2c {
     }
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x08)]
+    #[packet(id = 0x08, until = 765)]
     pub struct FeatureFlags {
         #[buftype(with = "length_prefix_array")]
         pub feature_flags: Vec<Identifier>,
     }
 
     #[derive(Debug, Packet)]
-    #[packet(id = 0x09)]
+    #[packet(id = 0x09, until = 765)]
     pub struct UpdateTags {
         pub tags: HashMap<Identifier, Vec<update_tags::Tag>>,
     }
@@ -164,7 +164,7 @@ pub mod c2s {
     pub use super::prev::c2s::Pong;
 
     #[derive(Debug, Packet, BufType)]
-    #[packet(id = 0x06)]
+    #[packet(id = 0x06, until = 765)]
     pub struct ResourcePackResponse {
         pub uuid: Uuid,
         pub result: resource_pack_response::Result,
@@ -36,7 +36,7 @@ pub mod s2c {
     pub struct ClientboundPluginMessage {
         pub channel: Identifier,
         #[buftype(with = "remaining_bytes")]
-        pub data: Vec<u8>,
+        pub data: Bytes,
     }
 
     #[derive(Debug, Packet, BufType)]
@@ -76,7 +76,7 @@ pub mod s2c {
 
         // TODO: properly decode entry data
         #[buftype(with = "remaining_bytes")]
-        pub _entries_data: Vec<u8>,
+        pub _entries_data: Bytes,
     }
 
     pub mod registry_data {
@@ -237,7 +237,7 @@ pub mod c2s {
     pub struct ServerboundPluginMessage {
         pub channel: Identifier,
         #[buftype(with = "remaining_bytes")]
-        pub data: Vec<u8>,
+        pub data: Bytes,
     }
 
     #[derive(Debug, Packet, BufType)]
@@ -99,8 +99,17 @@ pub mod s2c {
     pub mod server_links {
         use crate::packet::prelude::*;
 
-        #[derive(Debug)]
+        #[derive(Debug, BufType)]
+        #[buftype(discriminant = "bool")]
         pub enum ServerLinkLabel {
+            #[buftype(value = true)]
+            BuiltIn(BuiltInServerLinkLabel),
+            #[buftype(value = false)]
+            Custom(String),
+        }
+
+        #[derive(Debug, BufType)]
+        pub enum BuiltInServerLinkLabel {
             BugReport,
             CommunityGuidelines,
             Support,
@@ -111,8 +120,8 @@ pub mod s2c {
             Forums,
             News,
             Announcements,
+            #[buftype(unknown)]
             Unknown(i32),
-            Custom(String),
         }
 
         #[derive(Debug, BufType)]
@@ -120,65 +129,6 @@ pub mod s2c {
             pub label: ServerLinkLabel,
             pub url: String,
         }
-
-        impl BufType for ServerLinkLabel {
-            fn buf_read_len<B: bytes::Buf>(buf: &mut B) -> Result<(Self, usize), ReadError> {
-                let (is_built_in, is_built_in_len) = bool::buf_read_len(buf)?;
-                let (label, label_len) = if is_built_in {
-                    let (label, label_len) = i32_as_v32::buf_read_len(buf)?;
-
-                    let label = match label {
-                        0 => ServerLinkLabel::BugReport,
-                        1 => ServerLinkLabel::CommunityGuidelines,
-                        2 => ServerLinkLabel::Support,
-                        3 => ServerLinkLabel::Status,
-                        4 => ServerLinkLabel::Feedback,
-                        5 => ServerLinkLabel::Community,
-                        6 => ServerLinkLabel::Website,
-                        7 => ServerLinkLabel::Forums,
-                        8 => ServerLinkLabel::News,
-                        9 => ServerLinkLabel::Announcements,
-                        other => ServerLinkLabel::Unknown(other),
-                    };
-
-                    (label, label_len)
-                } else {
-                    let (label, label_len) = String::buf_read_len(buf)?;
-                    (ServerLinkLabel::Custom(label), label_len)
-                };
-
-                Ok((label, is_built_in_len + label_len))
-            }
-
-            fn buf_write<B: bytes::BufMut>(&self, buf: &mut B) {
-                match &self {
-                    ServerLinkLabel::Custom(label) => {
-                        bool::buf_write(&false, buf);
-                        label.buf_write(buf);
-                    }
-
-                    other => {
-                        bool::buf_write(&true, buf);
-
-                        let id = match other {
-                            ServerLinkLabel::BugReport => 0,
-                            ServerLinkLabel::CommunityGuidelines => 1,
-                            ServerLinkLabel::Support => 2,
-                            ServerLinkLabel::Status => 3,
-                            ServerLinkLabel::Feedback => 4,
-                            ServerLinkLabel::Community => 5,
-                            ServerLinkLabel::Website => 6,
-                            ServerLinkLabel::Forums => 7,
-                            ServerLinkLabel::News => 8,
-                            ServerLinkLabel::Announcements => 9,
-                            ServerLinkLabel::Unknown(other) => *other,
-                            ServerLinkLabel::Custom(_) => unreachable!(),
-                        };
-                        i32_as_v32::buf_write(&id, buf);
-                    }
-                }
-            }
-        }
     }
 }
 
@@ -0,0 +1,13 @@
+use mcproto::types::BufType;
+
+#[derive(BufType)]
+#[buftype(bits = "u8")]
+struct Custom {
+    a: bool,
+    #[buftype(bit = 8)]
+    b: bool,
+    #[buftype(count = "a")]
+    c: Vec<bool>,
+}
+
+fn main() {}
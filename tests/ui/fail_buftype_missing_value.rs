@@ -0,0 +1,11 @@
+use mcproto::types::BufType;
+
+#[derive(BufType)]
+#[buftype(discriminant = "bool")]
+enum Custom {
+    #[buftype(value = true)]
+    A,
+    B,
+}
+
+fn main() {}
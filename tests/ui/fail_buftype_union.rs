@@ -0,0 +1,8 @@
+use mcproto::types::BufType;
+
+#[derive(BufType)]
+union Custom {
+    a: u8,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use mcproto::types::BufType;
+
+#[derive(BufType)]
+struct Custom {
+    #[buftype(wth = "i32_as_v32")]
+    value: i32,
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+use mcproto::identifier;
+
+fn main() {
+    let _ = identifier!("minecraft:Stone");
+}
@@ -0,0 +1,7 @@
+use mcproto::packet::Packet;
+
+#[derive(Packet)]
+#[packet(id = 1, direction = "up", state = "play", since = 767)]
+struct Custom;
+
+fn main() {}
@@ -0,0 +1,7 @@
+use mcproto::packet::Packet;
+
+#[derive(Packet)]
+#[packet(direction = "c2s", state = "play", since = 767)]
+struct Custom;
+
+fn main() {}
@@ -0,0 +1,13 @@
+use mcproto::{packet::Packet, packet_derive::impl_packets_enum, types::BufType};
+
+#[derive(Debug, Packet, BufType)]
+#[packet(id = 0x01, direction = "c2s", state = "play", since = 767)]
+struct First;
+
+#[derive(Debug, Packet, BufType)]
+#[packet(id = 0x01, direction = "c2s", state = "play", since = 767)]
+struct Second;
+
+impl_packets_enum![First, Second];
+
+fn main() {}
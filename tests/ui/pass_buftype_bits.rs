@@ -0,0 +1,70 @@
+use mcproto::types::BufType;
+
+#[derive(Debug, Default, PartialEq, BufType)]
+#[buftype(bits = "u8")]
+struct Positional {
+    a: bool,
+    b: bool,
+    c: bool,
+}
+
+#[derive(Debug, Default, PartialEq, BufType)]
+#[buftype(bits = "i32")]
+struct Explicit {
+    #[buftype(bit = 31)]
+    high: bool,
+    #[buftype(skip)]
+    skipped: bool,
+    // still the third field's position
+    low: bool,
+}
+
+#[derive(Debug, Default, PartialEq, BufType)]
+#[buftype(bits = "varint")]
+struct Tuple(bool, #[buftype(bit = 7)] bool);
+
+fn round_trip<T: BufType + std::fmt::Debug + PartialEq>(value: &T, expected: &[u8]) {
+    let mut data = Vec::new();
+    value.buf_write(&mut data);
+    assert_eq!(data, expected);
+    assert_eq!(value.encoded_len(), data.len());
+
+    let (read, len) = T::buf_read_len(&mut &data[..]).unwrap();
+    assert_eq!((&read, len), (value, data.len()));
+}
+
+fn main() {
+    round_trip(&Positional::default(), &[0]);
+    round_trip(
+        &Positional {
+            a: true,
+            b: false,
+            c: true,
+        },
+        &[0b101],
+    );
+
+    round_trip(
+        &Explicit {
+            high: true,
+            skipped: false,
+            low: true,
+        },
+        &[0x80, 0, 0, 0b100],
+    );
+
+    round_trip(&Tuple(true, false), &[1]);
+    // 0x81 takes two bytes as a varint
+    round_trip(&Tuple(true, true), &[0x81, 0x01]);
+
+    // unknown bits are ignored
+    let (read, _) = Positional::buf_read_len(&mut &[0xff][..]).unwrap();
+    assert_eq!(
+        read,
+        Positional {
+            a: true,
+            b: true,
+            c: true
+        }
+    );
+}
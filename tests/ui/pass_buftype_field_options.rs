@@ -0,0 +1,106 @@
+use mcproto::types::BufType;
+
+#[derive(Debug, PartialEq, BufType)]
+struct Options {
+    flag: bool,
+    #[buftype(if = "flag")]
+    value: Option<i32>,
+    len: u8,
+    #[buftype(count = "len")]
+    values: Vec<u16>,
+    #[buftype(if = "len > 1", count = "len")]
+    extra: Option<Vec<bool>>,
+    #[buftype(skip, default = "cached")]
+    cached: u32,
+    #[buftype(skip)]
+    defaulted: String,
+}
+
+#[derive(Debug, PartialEq, BufType)]
+struct Counted(u8, #[buftype(count = "value0")] Vec<u8>);
+
+#[derive(Debug, PartialEq, BufType)]
+#[buftype(discriminant = "u8")]
+enum Variant {
+    Counted {
+        len: i32,
+        #[buftype(count = "len")]
+        values: Vec<u8>,
+    },
+}
+
+fn cached() -> u32 {
+    7
+}
+
+fn round_trip<T: BufType + std::fmt::Debug + PartialEq>(value: &T, expected: &[u8]) {
+    let mut data = Vec::new();
+    value.buf_write(&mut data);
+    assert_eq!(data, expected);
+    assert_eq!(value.encoded_len(), data.len());
+
+    let (read, len) = T::buf_read_len(&mut &data[..]).unwrap();
+    assert_eq!((&read, len), (value, data.len()));
+}
+
+fn main() {
+    let options = Options {
+        flag: true,
+        value: Some(1),
+        len: 2,
+        values: vec![3, 4],
+        extra: Some(vec![true, false]),
+        cached: cached(),
+        defaulted: String::new(),
+    };
+    round_trip(&options, &[1, 0, 0, 0, 1, 2, 0, 3, 0, 4, 1, 0]);
+
+    let options = Options {
+        flag: false,
+        value: None,
+        len: 1,
+        values: vec![3],
+        extra: None,
+        cached: cached(),
+        defaulted: String::new(),
+    };
+    round_trip(&options, &[0, 1, 0, 3]);
+
+    round_trip(&Counted(2, vec![5, 6]), &[2, 5, 6]);
+    round_trip(
+        &Variant::Counted {
+            len: 1,
+            values: vec![8],
+        },
+        &[0, 0, 0, 0, 1, 8],
+    );
+
+    // only read, the skipped fields aren't written
+    let (read, _) = Options::buf_read_len(&mut &[0, 0][..]).unwrap();
+    assert_eq!(read.cached, 7);
+    assert_eq!(read.defaulted, "");
+
+    // a count that doesn't match would write a malformed packet
+    std::panic::set_hook(Box::new(|_| {}));
+    let mismatched = std::panic::catch_unwind(|| {
+        let mut data = Vec::new();
+        Counted(3, vec![5]).buf_write(&mut data);
+    });
+    assert!(mismatched.is_err());
+
+    // so would a value the condition says isn't there
+    let unexpected = std::panic::catch_unwind(|| {
+        let mut data = Vec::new();
+        Options {
+            flag: false,
+            value: Some(1),
+            len: 0,
+            values: Vec::new(),
+            extra: None,
+            cached: cached(),
+            defaulted: String::new(),
+        }
+        .buf_write(&mut data);
+    });
+    assert!(unexpected.is_err());
+}
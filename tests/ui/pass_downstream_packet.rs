@@ -0,0 +1,92 @@
+use mcproto::{
+    bytes::{Buf, BufMut},
+    packet::{Direction, Packet, PacketRead, PacketState, PacketWrite},
+    packet_derive::impl_packets_enum,
+    types::{proxy::i32_as_v32, BufType, ReadError},
+};
+
+/// A proxy module without `encoded_len`, as written before it was needed.
+mod u8_prefix {
+    use super::*;
+
+    pub fn buf_read_len<T: BufType, B: Buf>(buf: &mut B) -> Result<(Vec<T>, usize), ReadError> {
+        let (len, mut length) = u8::buf_read_len(buf)?;
+        let mut values = Vec::new();
+        for _ in 0..len {
+            let (value, value_length) = T::buf_read_len(buf)?;
+            values.push(value);
+            length += value_length;
+        }
+
+        Ok((values, length))
+    }
+
+    pub fn buf_write<T: BufType, B: BufMut>(values: &[T], buf: &mut B) {
+        (values.len() as u8).buf_write(buf);
+        values.iter().for_each(|value| value.buf_write(buf));
+    }
+}
+
+#[derive(Debug, PartialEq, BufType)]
+struct Proxied {
+    #[buftype(with = "u8_prefix")]
+    values: Vec<u16>,
+    flag: bool,
+    #[buftype(if = "flag", with = "u8_prefix")]
+    optional: Option<Vec<String>>,
+}
+
+#[derive(Debug, PartialEq, Packet, BufType)]
+#[packet(id = 0x42, direction = "c2s", state = "play", since = 767)]
+pub struct Custom {
+    #[buftype(with = "i32_as_v32")]
+    value: i32,
+    name: String,
+    kind: Kind,
+}
+
+#[derive(Debug, PartialEq, Packet, BufType)]
+#[packet(id = 0x43, direction = "c2s", state = "play", since = 767)]
+pub struct Empty;
+
+impl_packets_enum![Custom, Empty];
+
+#[derive(Debug, PartialEq, BufType)]
+#[buftype(discriminant = "u8")]
+enum Kind {
+    A,
+    B(bool),
+    #[buftype(unknown)]
+    Unknown(u8),
+}
+
+fn main() {
+    assert_eq!(Custom::NAME, "Custom");
+    assert_eq!(Custom::DIRECTION, Direction::C2s);
+    assert_eq!(Custom::STATE, PacketState::Play);
+
+    let packet = Custom {
+        value: 300,
+        name: "a".to_owned(),
+        kind: Kind::B(true),
+    };
+    let mut body = Vec::new();
+    packet.write_body(&mut body);
+
+    assert_eq!(Custom::read_body(&mut &body[..]).unwrap(), packet);
+
+    let mut enum_body = Vec::new();
+    Packets::Custom(packet).write_body(&mut enum_body);
+    assert_eq!(enum_body, body);
+    assert_eq!(Packets::PACKETS, [(0x42, "Custom"), (0x43, "Empty")]);
+
+    let proxied = Proxied {
+        values: vec![1, 2],
+        flag: true,
+        optional: Some(vec!["a".to_owned()]),
+    };
+    let mut data = Vec::new();
+    proxied.buf_write(&mut data);
+    assert_eq!(proxied.encoded_len(), data.len());
+    assert_eq!(Proxied::buf_read(&mut &data[..]).unwrap(), proxied);
+}